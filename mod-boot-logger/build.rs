@@ -1,10 +1,89 @@
-#![expect(missing_docs, clippy::missing_docs_in_private_items)]
+#![expect(missing_docs)]
 
 use std::path::PathBuf;
 
 use rusttype::{Font, Scale};
 
-const FONT_SIZE: f32 = 20.0;
+/// The font size to bake, in pixels, unless overridden by the
+/// `ORO_BOOT_FONT_SIZE` environment variable.
+const DEFAULT_FONT_SIZE: f32 = 20.0;
+
+/// The environment variable that overrides [`DEFAULT_FONT_SIZE`].
+const FONT_SIZE_VAR: &str = "ORO_BOOT_FONT_SIZE";
+
+/// The bundled font file, relative to the package, which is baked unless
+/// overridden by the `ORO_BOOT_FONT_PATH` environment variable.
+const DEFAULT_FONT_FILE: &str = "AtkinsonHyperlegibleMono-Light.ttf";
+
+/// The environment variable that overrides [`DEFAULT_FONT_FILE`].
+///
+/// Relative paths are resolved against the package directory.
+const FONT_PATH_VAR: &str = "ORO_BOOT_FONT_PATH";
+
+/// The (inclusive) code point ranges to bake into the font.
+///
+/// Every glyph baked costs its width in pixels times the font height in
+/// bytes, so only the ranges the log is likely to contain are included.
+/// Code points outside of these (or missing from the font) are drawn
+/// with a fallback glyph.
+const GLYPH_RANGES: &[(u32, u32)] = &[
+	(0x0020, 0x007E), // Basic Latin (printable)
+	(0x00A0, 0x00FF), // Latin-1 Supplement (printable)
+	(0x0100, 0x017F), // Latin Extended-A
+	(0x0180, 0x024F), // Latin Extended-B
+	(0x2010, 0x2027), // General Punctuation (dashes, quotes, ellipsis)
+	(0x2190, 0x21FF), // Arrows
+	(0x2400, 0x2426), // Control Pictures
+	(0x2500, 0x257F), // Box Drawing
+	(0x2580, 0x259F), // Block Elements
+	(0xFFFD, 0xFFFD), // Replacement Character
+];
+
+/// The (inclusive) code point range kerning pairs are baked for.
+///
+/// Every pair of characters within it is checked, but only pairs the
+/// font actually kerns (by at least half a pixel) are baked.
+const KERNING_RANGE: (u32, u32) = (0x0020, 0x007E);
+
+/// Returns the font size to bake, in pixels.
+///
+/// Panics if the environment variable is set to anything but a positive,
+/// finite number.
+fn font_size() -> f32 {
+	println!("cargo:rerun-if-env-changed={FONT_SIZE_VAR}");
+
+	let Ok(value) = std::env::var(FONT_SIZE_VAR) else {
+		return DEFAULT_FONT_SIZE;
+	};
+
+	let size = value
+		.trim()
+		.parse::<f32>()
+		.unwrap_or_else(|err| panic!("{FONT_SIZE_VAR} is not a number ({value:?}): {err}"));
+
+	assert!(
+		size.is_finite() && size > 0.0,
+		"{FONT_SIZE_VAR} must be positive and finite (got {size})"
+	);
+
+	size
+}
+
+/// Returns the path of the font file to bake.
+fn font_path() -> PathBuf {
+	println!("cargo:rerun-if-env-changed={FONT_PATH_VAR}");
+
+	let file = std::env::var_os(FONT_PATH_VAR).unwrap_or_else(|| DEFAULT_FONT_FILE.into());
+
+	let path = PathBuf::from(
+		std::env::var("CARGO_MANIFEST_DIR").expect("no environment variable 'CARGO_MANIFEST_DIR"),
+	)
+	.join(file);
+
+	println!("cargo:rerun-if-changed={}", path.display());
+
+	path
+}
 
 #[expect(
 	clippy::cast_sign_loss,
@@ -12,37 +91,54 @@ const FONT_SIZE: f32 = 20.0;
 	clippy::cast_possible_wrap
 )]
 fn main() {
-	let raw_font_path = PathBuf::from(
-		std::env::var("CARGO_MANIFEST_DIR").expect("no environment variable 'CARGO_MANIFEST_DIR"),
-	)
-	.join("AtkinsonHyperlegibleMono-Light.ttf");
+	// Emitting any `rerun-if` directive replaces Cargo's default of
+	// re-running on any change to the package, so the inputs are listed too
+	// (the font file by `font_path`).
+	println!("cargo:rerun-if-changed=build.rs");
+
+	let font_size = font_size();
+	let font_path = font_path();
 
-	let raw_font = std::fs::read(raw_font_path).expect("failed to read font file");
+	let raw_font = std::fs::read(&font_path)
+		.unwrap_or_else(|err| panic!("failed to read font file {}: {err}", font_path.display()));
 
-	let font = Font::try_from_vec(raw_font).expect("failed to load font");
+	let font = Font::try_from_vec(raw_font)
+		.unwrap_or_else(|| panic!("{} is not a valid font", font_path.display()));
 
 	let v_metrics = font.v_metrics(Scale::uniform(1.0));
-	let font_height = (FONT_SIZE * (v_metrics.ascent - v_metrics.descent)).ceil() as usize;
+	let font_height = (font_size * (v_metrics.ascent - v_metrics.descent)).ceil() as usize;
 
 	let mut rows: Vec<Vec<u8>> = Vec::with_capacity(font_height);
 	for _ in 0..font_height {
 		rows.push(Vec::new());
 	}
 
-	let dict = (0..256).map(|c| char::from_u32(c).unwrap());
-	let layout = font.glyphs_for(dict).collect::<Vec<_>>();
+	let dict = GLYPH_RANGES
+		.iter()
+		.flat_map(|&(first, last)| first..=last)
+		.filter_map(char::from_u32)
+		.collect::<Vec<_>>();
+
+	assert!(
+		dict.is_sorted() && dict.windows(2).all(|w| w[0] != w[1]),
+		"glyph ranges must be sorted and must not overlap"
+	);
 
-	let y_baseline = (v_metrics.ascent * FONT_SIZE).ceil() as i32;
+	let layout = font.glyphs_for(dict.iter().copied()).collect::<Vec<_>>();
+
+	let y_baseline = (v_metrics.ascent * font_size).ceil() as i32;
+	let ascent = y_baseline as usize;
+	let descent = font_height - ascent;
 	let mut x_base = 0;
-	let mut offsets = Vec::new();
+	let mut glyphs = Vec::new();
 
-	for glyph in layout {
+	for (c, glyph) in dict.into_iter().zip(layout) {
+		// Glyphs missing from the font aren't baked at all.
 		if glyph.id().0 == 0 {
-			offsets.push(u32::MAX);
 			continue;
 		}
 
-		let glyph = glyph.scaled(Scale::uniform(FONT_SIZE));
+		let glyph = glyph.scaled(Scale::uniform(font_size));
 		let glyph = glyph.positioned(rusttype::point(0.0, 0.0));
 
 		if let Some(bb) = glyph.pixel_bounding_box() {
@@ -72,19 +168,56 @@ fn main() {
 			});
 		}
 
-		offsets.push(x_base as u32);
-		assert!(
-			glyph
-				.unpositioned()
-				.h_metrics()
-				.advance_width
-				.ceil()
-				.is_sign_positive()
-		);
-		x_base += glyph.unpositioned().h_metrics().advance_width.ceil() as usize;
+		let advance_width = glyph.unpositioned().h_metrics().advance_width;
+		assert!(advance_width.is_sign_positive());
+
+		// The glyph's slot in the font data is a whole number of pixels wide,
+		// but its advance is kept to 1/256th of a pixel (as 8.8 fixed point),
+		// so that rounding errors don't build up along a line.
+		let width = advance_width.ceil() as usize;
+		let advance = (advance_width * 256.0).round() as usize;
+
+		assert!(width <= 255, "glyph for {c:?} is too wide");
+
+		let (code_point, offset, width, advance) =
+			(c as u32, x_base as u32, width as u8, advance as u16);
+		glyphs.push(quote::quote! { (#code_point, #offset, #width, #advance) });
+
+		x_base += usize::from(width);
+	}
+
+	let kerning_chars = (KERNING_RANGE.0..=KERNING_RANGE.1)
+		.filter_map(char::from_u32)
+		.collect::<Vec<_>>();
+	let mut kerning = Vec::new();
+
+	for &first in &kerning_chars {
+		for &second in &kerning_chars {
+			let adjust = font
+				.pair_kerning(Scale::uniform(font_size), first, second)
+				.round();
+			if adjust == 0.0 {
+				continue;
+			}
+
+			assert!(
+				(-128.0..=127.0).contains(&adjust),
+				"kerning for {first:?}{second:?} is too large"
+			);
+
+			let (first, second, adjust) = (first as u8, second as u8, adjust as i8);
+			kerning.push(quote::quote! { (#first, #second, #adjust) });
+		}
 	}
 
-	let max_len = rows.iter().map(std::vec::Vec::len).max().unwrap();
+	// Every glyph's full advance must be present, even if the last
+	// glyph's pixels don't reach the end of it.
+	let max_len = rows
+		.iter()
+		.map(std::vec::Vec::len)
+		.max()
+		.unwrap()
+		.max(x_base);
 	for row in &mut rows {
 		if row.len() < max_len {
 			row.resize(max_len, 0);
@@ -104,12 +237,32 @@ fn main() {
 		/// The height of the font.
 		pub const FONT_HEIGHT: usize = #font_height;
 
-		/// The offsets of each character in the font.
+		/// The distance from the top of the font to its baseline.
+		pub const FONT_ASCENT: usize = #ascent;
+
+		/// The distance from the font's baseline to its bottom.
+		pub const FONT_DESCENT: usize = #descent;
+
+		/// The glyphs in the font, as `(code_point, offset, width, advance)`,
+		/// sorted by code point.
+		///
+		/// The width is that of the glyph's pixels, and the advance is how
+		/// far text advances after it, in 1/256ths of a pixel.
 		///
-		/// `u32::MAX` indicates that the character is not present in the font.
+		/// Characters not listed are not present in the font.
 		#[allow(clippy::unreadable_literal)]
-		pub static FONT_OFFSETS: [u32; 256] = [
-			#(#offsets),*
+		pub static FONT_GLYPHS: &[(u32, u32, u8, u16)] = &[
+			#(#glyphs),*
+		];
+
+		/// The font's kerning pairs, as `(first, second, adjustment)`,
+		/// sorted by `(first, second)`.
+		///
+		/// The adjustment (in pixels) is added to the first character's
+		/// advance when it's followed by the second. Pairs not listed
+		/// aren't kerned.
+		pub static FONT_KERNING: &[(u8, u8, i8)] = &[
+			#(#kerning),*
 		];
 	}
 	.to_string();
@@ -0,0 +1,211 @@
+//! Parses the subset of ANSI escape sequences used to color log output.
+//!
+//! Only SGR (`ESC [ ... m`) foreground colors, bold and resets are acted upon.
+//! Every other escape sequence is consumed and ignored, so that it isn't
+//! drawn as literal text.
+
+use crate::vbuf::Color;
+
+/// The most parameters of a single control sequence that are interpreted.
+///
+/// Any further parameters are ignored.
+const MAX_PARAMS: usize = 8;
+
+/// A foreground color selected by an SGR sequence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Foreground {
+	/// The console's own text color.
+	Default,
+	/// Black (SGR 30).
+	///
+	/// Drawn as a dark grey, so that it's still visible against the
+	/// (black) background.
+	Black,
+	/// Red (SGR 31).
+	Red,
+	/// Green (SGR 32).
+	Green,
+	/// Yellow (SGR 33).
+	Yellow,
+	/// Blue (SGR 34).
+	Blue,
+	/// Magenta (SGR 35).
+	Magenta,
+	/// Cyan (SGR 36).
+	Cyan,
+	/// White (SGR 37).
+	White,
+}
+
+impl Foreground {
+	/// The standard colors, in SGR order (30 through 37).
+	const STANDARD: [Self; 8] = [
+		Self::Black,
+		Self::Red,
+		Self::Green,
+		Self::Yellow,
+		Self::Blue,
+		Self::Magenta,
+		Self::Cyan,
+		Self::White,
+	];
+
+	/// Returns the color to draw with, given the console's own text color.
+	pub const fn color(self, default: Color) -> Color {
+		let (r, g, b) = match self {
+			Self::Default => return default,
+			Self::Black => (0x55, 0x55, 0x55),
+			Self::Red => (0xE0, 0x40, 0x40),
+			Self::Green => (0x40, 0xC8, 0x40),
+			Self::Yellow => (0xE0, 0xC8, 0x40),
+			Self::Blue => (0x58, 0x78, 0xF0),
+			Self::Magenta => (0xC8, 0x50, 0xC8),
+			Self::Cyan => (0x40, 0xC8, 0xC8),
+			Self::White => (0xE8, 0xE8, 0xE8),
+		};
+
+		Color { r, g, b }
+	}
+}
+
+/// The style characters are drawn in, as selected by SGR sequences.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Style {
+	/// The foreground color.
+	pub foreground: Foreground,
+	/// Whether the text is bold (SGR 1).
+	pub bold:       bool,
+}
+
+impl Style {
+	/// The console's own style, as selected by a reset (SGR 0).
+	pub const DEFAULT: Self = Self {
+		foreground: Foreground::Default,
+		bold:       false,
+	};
+}
+
+/// Where the parser is within an escape sequence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+	/// Not within an escape sequence.
+	Ground,
+	/// Just after an `ESC`.
+	Escape,
+	/// Within a control sequence (`ESC [`).
+	Csi,
+	/// Within an operating system command (`ESC ]`), which runs up to a
+	/// `BEL` or an `ESC` (the start of the `ESC \` terminator).
+	Osc,
+}
+
+/// Strips escape sequences from a stream of characters, tracking the
+/// style they select.
+pub struct AnsiParser {
+	/// Where the parser is within an escape sequence.
+	state:  State,
+	/// The parameters of the control sequence being parsed.
+	params: [u16; MAX_PARAMS],
+	/// The index of the parameter being parsed.
+	param:  usize,
+	/// The current style.
+	style:  Style,
+}
+
+impl AnsiParser {
+	/// Creates a new parser, with the default style.
+	pub const fn new() -> Self {
+		Self {
+			state:  State::Ground,
+			params: [0; MAX_PARAMS],
+			param:  0,
+			style:  Style::DEFAULT,
+		}
+	}
+
+	/// Returns the current style.
+	pub fn style(&self) -> Style {
+		self.style
+	}
+
+	/// Feeds a character to the parser.
+	///
+	/// Returns the character if it should be drawn, or `None` if it was
+	/// part of an escape sequence.
+	pub fn feed(&mut self, c: char) -> Option<char> {
+		match self.state {
+			State::Ground => {
+				if c == '\x1B' {
+					self.state = State::Escape;
+					return None;
+				}
+
+				return Some(c);
+			}
+			State::Escape => {
+				self.state = match c {
+					'[' => {
+						self.params = [0; MAX_PARAMS];
+						self.param = 0;
+						State::Csi
+					}
+					']' => State::Osc,
+					// Any other escape is a single character long.
+					_ => State::Ground,
+				};
+			}
+			State::Csi => {
+				match c {
+					'0'..='9' => {
+						if let Some(param) = self.params.get_mut(self.param) {
+							*param = param
+								.saturating_mul(10)
+								.saturating_add(c as u16 - u16::from(b'0'));
+						}
+					}
+					';' => self.param += 1,
+					// Other parameter and intermediate bytes (e.g. the `?`
+					// of private sequences) don't affect anything we handle.
+					'\x20'..='\x3F' => {}
+					'm' => {
+						self.apply_sgr();
+						self.state = State::Ground;
+					}
+					// Any other final byte ends a sequence we don't handle,
+					// as does anything that isn't valid within one.
+					_ => self.state = State::Ground,
+				}
+			}
+			State::Osc => {
+				if c == '\x07' {
+					self.state = State::Ground;
+				} else if c == '\x1B' {
+					self.state = State::Escape;
+				}
+			}
+		}
+
+		None
+	}
+
+	/// Applies the parameters of an SGR sequence.
+	///
+	/// Only the foreground colors, bold and resets are handled; other
+	/// attributes (e.g. underlines or background colors) are ignored.
+	fn apply_sgr(&mut self) {
+		let count = (self.param + 1).min(MAX_PARAMS);
+
+		for &param in &self.params[..count] {
+			match param {
+				0 => self.style = Style::DEFAULT,
+				1 => self.style.bold = true,
+				22 => self.style.bold = false,
+				39 => self.style.foreground = Foreground::Default,
+				30..=37 => self.style.foreground = Foreground::STANDARD[usize::from(param - 30)],
+				// The bright variants are drawn the same as the standard ones.
+				90..=97 => self.style.foreground = Foreground::STANDARD[usize::from(param - 90)],
+				_ => {}
+			}
+		}
+	}
+}
@@ -0,0 +1,97 @@
+//! Implements the off-screen back buffer that frames are drawn to.
+//!
+//! Drawing straight to the video buffer tears visibly on slower buffers,
+//! since each frame is built up over several passes (the logo, then the
+//! text, then the cursor). Instead, everything is drawn to a back buffer
+//! in ordinary memory, and whatever changed is copied out to the video
+//! buffer in one go at the end of each frame (see [`Vbuf::present`]).
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::Vbuf;
+
+/// The size of the back buffer's memory, in bytes.
+///
+/// There's no allocator, so the memory is a fixed static. It lives in
+/// `.bss`, so it doesn't bloat the module image, but it is reserved for
+/// as long as the module is loaded.
+///
+/// A buffer needs `height * stride` bytes (so any padding at the end of
+/// each row counts against it); this fits up to 1920x1200 at 32 bits per
+/// pixel, or about 8.8 MiB. Buffers that don't fit are drawn to directly
+/// instead, tearing and all.
+const CAPACITY: usize = 1920 * 1200 * 4;
+
+/// The back buffer's memory.
+static mut MEMORY: [u8; CAPACITY] = [0; CAPACITY];
+
+/// Whether [`MEMORY`] is held by a back buffer.
+static TAKEN: AtomicBool = AtomicBool::new(false);
+
+/// An off-screen copy of a video buffer, which is drawn to in its place.
+pub struct BackBuffer {
+	/// The back buffer, backed by [`MEMORY`].
+	vbuf: Vbuf,
+}
+
+impl BackBuffer {
+	/// Creates a back buffer with the same geometry and pixel format as `front`.
+	///
+	/// The back buffer starts out black, with nothing to present. Returns
+	/// `None` if `front` is too large (see [`CAPACITY`]), or if another back
+	/// buffer already exists.
+	pub fn new(front: &Vbuf) -> Option<Self> {
+		let size = front.height.checked_mul(front.stride)?;
+		if size > CAPACITY as u64 || TAKEN.swap(true, Ordering::Relaxed) {
+			return None;
+		}
+
+		let memory = (&raw mut MEMORY).cast::<u8>();
+
+		// SAFETY: The memory is only ever held by one back buffer at a time,
+		// SAFETY: and we've checked that it's large enough for the buffer.
+		let vbuf = unsafe {
+			// A previous back buffer may have left its frame behind.
+			memory.write_bytes(0, size as usize);
+			front.with_memory(memory)
+		};
+
+		Some(Self { vbuf })
+	}
+
+	/// Creates a back buffer for each of `fronts` that can have one.
+	///
+	/// There's only the one back buffer's worth of memory (see [`CAPACITY`]),
+	/// so it goes to the first of `fronts` it fits; the rest are drawn to
+	/// directly, tearing and all.
+	pub fn for_buffers<const N: usize>(fronts: &[Option<Vbuf>; N]) -> [Option<Self>; N] {
+		core::array::from_fn(|index| {
+			let front = fronts[index].as_ref()?;
+			let back = Self::new(front);
+
+			if back.is_none() {
+				println!(
+					"vbuf {index} can't be double-buffered ({} bytes); drawing to it directly",
+					front.height.saturating_mul(front.stride)
+				);
+			}
+
+			back
+		})
+	}
+
+	/// Returns the video buffer to draw to in place of the front buffer.
+	pub fn vbuf(&self) -> &Vbuf {
+		&self.vbuf
+	}
+}
+
+impl Drop for BackBuffer {
+	fn drop(&mut self) {
+		// Nothing refers to the memory once the back buffer is gone.
+		TAKEN.store(false, Ordering::Relaxed);
+	}
+}
+
+#[cfg(all(test, feature = "mock-vbuf"))]
+mod tests;
@@ -0,0 +1,37 @@
+//! Tests for the back buffer, against a [`MockVbuf`].
+
+use super::BackBuffer;
+use crate::{
+	Vbuf,
+	mock_vbuf::{MockFormat, MockVbuf},
+};
+
+/// Returns a buffer sharing the mock's memory.
+fn share(mock: &MockVbuf) -> Vbuf {
+	// SAFETY: The memory outlives the buffer.
+	unsafe { mock.vbuf().with_memory(mock.vbuf().data) }
+}
+
+// This is the only test that takes the back buffer, since there's only
+// the one (and tests run concurrently).
+#[test]
+fn gives_the_back_buffer_to_the_first_buffer_it_fits() {
+	let too_large = MockVbuf::new(2000, 1200, MockFormat::Xrgb8888);
+	let first = MockVbuf::new(8, 8, MockFormat::Xrgb8888);
+	let second = MockVbuf::new(8, 8, MockFormat::Xrgb8888);
+	let fronts = [
+		Some(share(&too_large)),
+		None,
+		Some(share(&first)),
+		Some(share(&second)),
+	];
+
+	let backs = BackBuffer::for_buffers(&fronts);
+	let double_buffered = backs.each_ref().map(Option::is_some);
+	assert_eq!(double_buffered, [false, false, true, false]);
+
+	// Once it's dropped, the back buffer's memory can be taken again.
+	drop(backs);
+	let backs = BackBuffer::for_buffers(&[None, Some(share(&second))]);
+	assert!(backs[1].is_some());
+}
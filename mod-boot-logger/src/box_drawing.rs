@@ -0,0 +1,163 @@
+//! Draws box-drawing characters (U+2500 to U+257F) from lines, rather
+//! than from the font.
+//!
+//! The font's box-drawing glyphs are as proportional as the rest of it,
+//! and stop short of the line gap, so tables drawn with them don't join
+//! up. Instead, each is drawn to fill its whole cell: exactly one
+//! character cell wide (see [`font_rasterizer::cell_width`]) and a whole
+//! row tall, so that lines meet those of the neighboring cells and rows.
+//!
+//! Dashed lines are drawn solid and arcs as square corners. The diagonals
+//! are left to the font.
+
+use crate::{
+	Rect, Vbuf,
+	font_rasterizer::{self, SUBPIXEL_BITS},
+	vbuf::Color,
+};
+
+/// The first character drawn from lines.
+const FIRST: u32 = 0x2500;
+
+/// The arms of each character, indexed from [`FIRST`].
+///
+/// An arm runs from the middle of the cell to the middle of one of its
+/// edges. Each arm's weight takes two bits (zero for none, then light,
+/// heavy and double): up in the lowest bits, then right, down and left.
+/// Characters with no arms are drawn from the font instead.
+const ARMS: [u8; 128] = [
+	0x44, 0x88, 0x11, 0x22, 0x44, 0x88, 0x11, 0x22, // U+2500
+	0x44, 0x88, 0x11, 0x22, 0x14, 0x18, 0x24, 0x28, // U+2508
+	0x50, 0x90, 0x60, 0xA0, 0x05, 0x09, 0x06, 0x0A, // U+2510
+	0x41, 0x81, 0x42, 0x82, 0x15, 0x19, 0x16, 0x25, // U+2518
+	0x26, 0x1A, 0x29, 0x2A, 0x51, 0x91, 0x52, 0x61, // U+2520
+	0x62, 0x92, 0xA1, 0xA2, 0x54, 0x94, 0x58, 0x98, // U+2528
+	0x64, 0xA4, 0x68, 0xA8, 0x45, 0x85, 0x49, 0x89, // U+2530
+	0x46, 0x86, 0x4A, 0x8A, 0x55, 0x95, 0x59, 0x99, // U+2538
+	0x56, 0x65, 0x66, 0x96, 0x5A, 0xA5, 0x69, 0x9A, // U+2540
+	0xA9, 0xA6, 0x6A, 0xAA, 0x44, 0x88, 0x11, 0x22, // U+2548
+	0xCC, 0x33, 0x1C, 0x34, 0x3C, 0xD0, 0x70, 0xF0, // U+2550
+	0x0D, 0x07, 0x0F, 0xC1, 0x43, 0xC3, 0x1D, 0x37, // U+2558
+	0x3F, 0xD1, 0x73, 0xF3, 0xDC, 0x74, 0xFC, 0xCD, // U+2560
+	0x47, 0xCF, 0xDD, 0x77, 0xFF, 0x14, 0x50, 0x41, // U+2568
+	0x05, 0x00, 0x00, 0x00, 0x40, 0x01, 0x04, 0x10, // U+2570
+	0x80, 0x02, 0x08, 0x20, 0x48, 0x21, 0x84, 0x12, // U+2578
+];
+
+/// Returns the arms of `c`, if it's drawn from lines.
+fn arms(c: char) -> Option<u8> {
+	let index = (c as u32).checked_sub(FIRST)?;
+	ARMS.get(index as usize).copied().filter(|&arms| arms != 0)
+}
+
+/// Returns whether `c` is drawn by [`draw`], rather than from the font.
+pub fn is_synthesized(c: char) -> bool {
+	arms(c).is_some()
+}
+
+/// Returns how far, in subpixels, the caret advances after a character
+/// drawn by [`draw`]; exactly one character cell.
+pub fn advance() -> usize {
+	font_rasterizer::cell_width() << SUBPIXEL_BITS
+}
+
+/// Returns the bands that make up a line of the given weight, as
+/// `(offset, thickness)` pairs across the line, in light line thicknesses.
+///
+/// Offsets are from the edge of a heavy line, which is three light lines
+/// thick; a double line is two light lines with a gap between them, the
+/// same width overall.
+const fn bands(weight: u8) -> &'static [(u64, u64)] {
+	match weight {
+		1 => &[(1, 1)],
+		2 => &[(0, 3)],
+		3 => &[(0, 1), (2, 1)],
+		_ => &[],
+	}
+}
+
+/// Returns the `(start, end)` span across a line of the given weight,
+/// as with [`bands`], or `None` if there's no line.
+const fn span(weight: u8) -> Option<(u64, u64)> {
+	match weight {
+		0 => None,
+		1 => Some((1, 2)),
+		_ => Some((0, 3)),
+	}
+}
+
+/// Returns how far an arm's band at `offset` (see [`bands`]) reaches into
+/// the lines crossing it, with `before` and `after` being the weights of
+/// the crossing arms on either side of it (up and down, or left and right).
+///
+/// Arms running up or left end at the first value, and those running down
+/// or right start at the second, as offsets like those of [`bands`]. Each
+/// normally reaches
+/// across every crossing line, so that the lines join. Each line of a
+/// double arm instead stops at the nearest line of a double arm on its own
+/// side, leaving the gap between them open.
+fn reach(weight: u8, offset: u64, before: u8, after: u8) -> (u64, u64) {
+	let side = match (weight, offset) {
+		(3, 0) => before,
+		(3, _) => after,
+		_ => 0,
+	};
+
+	if side == 3 {
+		return (1, 2);
+	}
+
+	[before, after]
+		.into_iter()
+		.filter_map(span)
+		.reduce(|(a0, a1), (b0, b1)| (a0.min(b0), a1.max(b1)))
+		.map_or((2, 1), |(start, end)| (end, start))
+}
+
+/// Draws `c` in `color` to fill `cell` (clipped to `clip`), with light
+/// lines `light` pixels thick.
+///
+/// Does nothing unless `c` [`is_synthesized`]; nothing behind the lines
+/// is cleared.
+pub fn draw(vbuf: &Vbuf, c: char, cell: &Rect, clip: &Rect, color: Color, light: u64) {
+	let Some(arms) = arms(c) else {
+		return;
+	};
+
+	let [up, right, down, left] = [0, 2, 4, 6].map(|shift| (arms >> shift) & 0b11);
+	let clip = cell.intersect(clip);
+
+	// The edges of a heavy line through the middle of the cell, which
+	// every band is offset from.
+	let ox = cell.x + cell.w.saturating_sub(3 * light) / 2;
+	let oy = cell.y + cell.h.saturating_sub(3 * light) / 2;
+
+	let fill = |x0: u64, y0: u64, x1: u64, y1: u64| {
+		let rect = Rect::new(x0, y0, x1.saturating_sub(x0), y1.saturating_sub(y0));
+		vbuf.fill_rect_color(&rect.intersect(&clip), color);
+	};
+
+	for &(offset, thickness) in bands(up) {
+		let x = ox + offset * light;
+		let (end, _) = reach(up, offset, left, right);
+		fill(x, cell.y, x + thickness * light, oy + end * light);
+	}
+
+	for &(offset, thickness) in bands(down) {
+		let x = ox + offset * light;
+		let (_, start) = reach(down, offset, left, right);
+		fill(x, oy + start * light, x + thickness * light, cell.bottom());
+	}
+
+	for &(offset, thickness) in bands(left) {
+		let y = oy + offset * light;
+		let (end, _) = reach(left, offset, up, down);
+		fill(cell.x, y, ox + end * light, y + thickness * light);
+	}
+
+	for &(offset, thickness) in bands(right) {
+		let y = oy + offset * light;
+		let (_, start) = reach(right, offset, up, down);
+		fill(ox + start * light, y, cell.right(), y + thickness * light);
+	}
+}
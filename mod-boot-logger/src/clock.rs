@@ -0,0 +1,83 @@
+//! Provides timestamps for log lines, from the most precise
+//! monotonic source available, and paces frames.
+
+use core::fmt;
+
+/// Roughly how many spins of [`spin_wait_ms`] take a millisecond.
+const SPINS_PER_MS: u64 = 30_000;
+
+/// A point in time since the module started.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Timestamp {
+	/// A precise timestamp, in microseconds.
+	Micros(u64),
+	/// A coarse timestamp, in milliseconds.
+	Millis(u64),
+}
+
+impl fmt::Display for Timestamp {
+	/// Formats the timestamp as fixed-width seconds, e.g. `[    1.234567]`
+	/// for microseconds or `[    1.234]` for milliseconds.
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match *self {
+			Self::Micros(us) => write!(f, "[{:>5}.{:06}]", us / 1_000_000, us % 1_000_000),
+			Self::Millis(ms) => write!(f, "[{:>5}.{:03}]", ms / 1000, ms % 1000),
+		}
+	}
+}
+
+/// Reads the highest-resolution monotonic counter the kernel
+/// exposes, in microseconds.
+///
+/// **Note:** The kernel doesn't yet expose a monotonic counter (or the
+/// frequency needed to make sense of the TSC) to the root ring, so this
+/// never yields anything and timestamps fall back to milliseconds.
+fn read_monotonic_us() -> Option<u64> {
+	None
+}
+
+/// Returns the current time, preferring a precise monotonic source and
+/// falling back to the (coarse) given number of milliseconds.
+pub fn now(coarse_ms: u64) -> Timestamp {
+	read_monotonic_us().map_or(Timestamp::Millis(coarse_ms), Timestamp::Micros)
+}
+
+/// Returns the monotonic counter, in milliseconds, if the kernel exposes one
+/// (see [`read_monotonic_us`]).
+pub fn monotonic_ms() -> Option<u64> {
+	read_monotonic_us().map(|us| us / 1000)
+}
+
+/// Sleeps for (at least) the given number of milliseconds.
+///
+/// Uses the kernel's sleep syscall if it has one, and otherwise
+/// busy-waits (see [`spin_wait_ms`]).
+pub fn sleep_ms(ms: u64) {
+	if ms > 0 && !sleep_syscall(ms) {
+		spin_wait_ms(ms);
+	}
+}
+
+/// Asks the kernel to suspend the module for the given number of
+/// milliseconds, returning whether it did.
+///
+/// **Note:** The kernel doesn't yet expose a sleep (or timer) syscall to
+/// the root ring, so this never sleeps, and [`sleep_ms`] always busy-waits.
+fn sleep_syscall(_ms: u64) -> bool {
+	false
+}
+
+// Busy-waits for roughly the given number of milliseconds.
+//
+// NOTE(qix-): Temporary function. Please do not copy into your modules.
+#[doc(hidden)]
+fn spin_wait_ms(ms: u64) {
+	for _ in 0..(ms * SPINS_PER_MS) {
+		unsafe {
+			core::arch::asm!("nop");
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,38 @@
+//! Tests for the timestamp formatting.
+
+use super::Timestamp;
+
+#[test]
+fn formats_zero() {
+	assert_eq!(Timestamp::Micros(0).to_string(), "[    0.000000]");
+	assert_eq!(Timestamp::Millis(0).to_string(), "[    0.000]");
+}
+
+#[test]
+fn formats_sub_second_timestamps() {
+	assert_eq!(Timestamp::Micros(1).to_string(), "[    0.000001]");
+	assert_eq!(Timestamp::Micros(999_999).to_string(), "[    0.999999]");
+	assert_eq!(Timestamp::Millis(1).to_string(), "[    0.001]");
+	assert_eq!(Timestamp::Millis(999).to_string(), "[    0.999]");
+}
+
+#[test]
+fn rolls_fractions_over_into_seconds() {
+	assert_eq!(Timestamp::Micros(1_000_000).to_string(), "[    1.000000]");
+	assert_eq!(Timestamp::Micros(61_234_567).to_string(), "[   61.234567]");
+	assert_eq!(Timestamp::Millis(1000).to_string(), "[    1.000]");
+	assert_eq!(Timestamp::Millis(61_234).to_string(), "[   61.234]");
+}
+
+#[test]
+fn widens_rather_than_truncating_large_timestamps() {
+	assert_eq!(
+		Timestamp::Micros(99_999_999_999).to_string(),
+		"[99999.999999]"
+	);
+	assert_eq!(
+		Timestamp::Micros(100_000_000_000).to_string(),
+		"[100000.000000]"
+	);
+	assert_eq!(Timestamp::Millis(100_000_000).to_string(), "[100000.000]");
+}
@@ -0,0 +1,1293 @@
+//! Implements the text console, which lays out and draws
+//! incoming log text within a region of a video buffer.
+
+use core::fmt::Write;
+
+use crate::{
+	Rect, Vbuf,
+	ansi::{AnsiParser, Style},
+	box_drawing,
+	clock::Timestamp,
+	font_rasterizer::{self, SUBPIXEL_BITS, ScaledGlyph},
+	glyph_cache::GlyphCache,
+	scrollback::Scrollback,
+	strbuf::StrBuf,
+	utf8::Utf8Decoder,
+	vbuf::Color,
+	watermark::Watermark,
+};
+
+/// How the cursor is drawn.
+const CURSOR_STYLE: CursorStyle = CursorStyle::Bar;
+
+/// Whether the cursor pulses (see [`TextConsole::draw_cursor`]), rather
+/// than being drawn at full brightness.
+const CURSOR_BLINK: bool = true;
+
+/// The thickness of the bar and underline cursors, in pixels (at the
+/// font's natural size).
+const CURSOR_THICKNESS: u64 = 2;
+
+/// Whether control characters should be shown as visible symbols
+/// rather than acted upon (e.g. a newline is drawn instead of starting
+/// a new line). Useful for debugging raw output streams.
+const SHOW_CONTROL_CHARS: bool = false;
+
+/// Whether log output should be drawn as soon as it arrives, rather than
+/// a whole line at a time.
+///
+/// Drawing whole lines keeps partial lines from different producers from
+/// being drawn intermixed, at the cost of output only appearing once its
+/// line is complete (or has gone stale; see [`TextConsole::flush_line`]).
+const ECHO_PARTIAL_LINES: bool = false;
+
+/// Whether each log line should be prefixed with the time it arrived.
+const LINE_TIMESTAMPS: bool = false;
+
+/// The color of the log text, as red, green and blue levels.
+///
+/// Log output can select other colors with ANSI escape sequences (see
+/// [`crate::ansi`]). On buffers that can't show color, the text is drawn
+/// in the equivalent grey.
+const TEXT_COLOR: Color = Color::grey(0xFF);
+
+/// Whether alternate rows should be shaded slightly lighter,
+/// to help the eye track lines in a dense log.
+const ZEBRA_ROWS: bool = false;
+
+/// How much lighter the shaded rows are (see [`ZEBRA_ROWS`]).
+///
+/// Kept small so as not to noticeably reduce the text's contrast.
+const ZEBRA_LEVEL: u8 = 0x0C;
+
+/// How much brighter a highlighted line's background is while lit
+/// (see [`TextConsole::highlight_line`]).
+const HIGHLIGHT_LEVEL: u8 = 0x30;
+
+/// How long a highlighted line stays lit (and then unlit) for each blink,
+/// in milliseconds.
+const HIGHLIGHT_BLINK_MS: u64 = 500;
+
+/// How the console makes room for new lines once the bottom of its region is reached.
+const SCROLL_MODE: ScrollMode = ScrollMode::HardwareScroll;
+
+/// The number of rows recorded for redrawing in [`ScrollMode::RedrawWindow`].
+///
+/// Regions with more rows than this only use this many.
+const REDRAW_ROWS: usize = 32;
+
+/// The maximum number of characters recorded per row in [`ScrollMode::RedrawWindow`].
+///
+/// Characters beyond this are still drawn, but are lost once the row is redrawn.
+const REDRAW_ROW_CHARS: usize = 96;
+
+/// What the console does with words too long to fit on a row of their own.
+const OVERFLOW_MODE: OverflowMode = OverflowMode::Wrap;
+
+/// How fast rows scroll in [`OverflowMode::Marquee`], in pixels per
+/// second (at the font's natural size).
+const MARQUEE_SPEED: u64 = 40;
+
+/// How long rows in [`OverflowMode::Marquee`] pause at either end of
+/// their scroll, in milliseconds.
+const MARQUEE_PAUSE_MS: u64 = 1500;
+
+/// The distance between tab stops, in character cells (see
+/// [`font_rasterizer::cell_width`]).
+///
+/// Tab stops are measured from the left of the region, so that tabular
+/// output (e.g. register dumps) lines up regardless of the glyphs before them.
+const TAB_WIDTH: u64 = 8;
+
+/// Characters drawn as a solid block of color rather than as a glyph,
+/// e.g. so that a log's severity bullets stand out from its text.
+const BLOCK_MARKERS: &[BlockMarker] = &[BlockMarker {
+	c:     '\u{25A0}',
+	color: Color {
+		r: 0xE0,
+		g: 0x30,
+		b: 0x30,
+	},
+	cells: 2,
+}];
+
+/// The maximum number of characters held back while assembling a line.
+///
+/// Longer lines are drawn in pieces of (up to) this many characters.
+const MAX_PENDING: usize = 256;
+
+/// A character drawn as a solid block (see [`BLOCK_MARKERS`]).
+struct BlockMarker {
+	/// The character.
+	c:     char,
+	/// The color of the block, whatever the color of the text around it.
+	color: Color,
+	/// The width of the block, in character cells (see
+	/// [`font_rasterizer::cell_width`]).
+	cells: u64,
+}
+
+/// Returns how `c` is drawn as a block, if it's one of the [`BLOCK_MARKERS`].
+fn block_marker(c: char) -> Option<&'static BlockMarker> {
+	BLOCK_MARKERS.iter().find(|marker| marker.c == c)
+}
+
+/// How a [`TextConsole`] draws its cursor, which sits at the caret (i.e.
+/// where the next character will be written).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(not(all(test, feature = "mock-vbuf")), expect(dead_code))]
+pub enum CursorStyle {
+	/// A vertical bar, the height of the font.
+	Bar,
+	/// A horizontal line along the font's baseline, a character cell wide.
+	Underline,
+	/// A filled character cell.
+	Block,
+}
+
+/// How a [`TextConsole`] makes room for new lines once the bottom
+/// of its region is reached.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(not(all(test, feature = "mock-vbuf")), expect(dead_code))]
+pub enum ScrollMode {
+	/// The region's contents are moved up by a row (by copying pixels
+	/// within the buffer) and the newest line is written at the bottom.
+	///
+	/// **Note:** The background would move with the text, so with a
+	/// watermark or zebra rows, [`Self::RedrawWindow`] is used instead.
+	HardwareScroll,
+	/// Writing wraps back around to the top row, overwriting the oldest
+	/// line. Each row is cleared just before it's written to.
+	WrapTop,
+	/// The newest line is written at the bottom, as with
+	/// [`Self::HardwareScroll`], but rather than copying pixels every row
+	/// is redrawn (one row higher) from a record of what was written to it.
+	RedrawWindow,
+}
+
+/// What a [`TextConsole`] does with words too long to fit on a row of
+/// their own (e.g. long hex strings).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OverflowMode {
+	/// The word is broken wherever the row runs out, and continues on
+	/// the next row.
+	Wrap,
+	/// The word is given a row of its own, and runs off the right of it.
+	/// Once drawn, the row scrolls back and forth (see
+	/// [`TextConsole::scroll_marquees`]) so that all of it is shown in turn.
+	///
+	/// Only whole lines (see [`ECHO_PARTIAL_LINES`]) are scrolled, and only
+	/// the rows the console keeps a record of (see [`REDRAW_ROWS`]), as far
+	/// as their recorded characters go ([`REDRAW_ROW_CHARS`]).
+	Marquee,
+}
+
+/// A record of the glyphs written to a row, used to redraw it.
+#[derive(Clone, Copy)]
+struct RowRecord {
+	/// The caret's X position (relative to the left of the region, in
+	/// subpixels) at the row's first glyph.
+	x: u64,
+	/// The characters written to the row.
+	chars: [char; REDRAW_ROW_CHARS],
+	/// The style each character in `chars` was written in.
+	styles: [Style; REDRAW_ROW_CHARS],
+	/// The number of characters in `chars`.
+	len: usize,
+	/// The caret's X position (as with `x`) after the last character in `chars`.
+	end: u64,
+	/// Whether the row holds a word that runs off its right (see
+	/// [`OverflowMode::Marquee`]).
+	overlong: bool,
+	/// When the row started scrolling, if it has.
+	marquee_since_ms: Option<u64>,
+	/// How far the row is scrolled to the left, in pixels.
+	scroll: u64,
+}
+
+impl RowRecord {
+	/// An empty row.
+	const EMPTY: Self = Self {
+		x: 0,
+		chars: ['\0'; REDRAW_ROW_CHARS],
+		styles: [Style::DEFAULT; REDRAW_ROW_CHARS],
+		len: 0,
+		end: 0,
+		overlong: false,
+		marquee_since_ms: None,
+		scroll: 0,
+	};
+}
+
+/// A text console drawing into a rectangular region of a video buffer.
+///
+/// Text that reaches the right edge of the region is soft-wrapped onto
+/// the next row, between words where possible. Wrapped continuation rows
+/// are indented to match the leading whitespace of the line they continue.
+/// Words too long for a row of their own are handled as per [`OverflowMode`].
+#[expect(clippy::struct_excessive_bools)]
+pub struct TextConsole<'a> {
+	/// The video buffer to draw into.
+	vbuf: &'a Vbuf,
+	/// The region, within the buffer, that text is drawn within.
+	region: Rect,
+	/// The caret's X position, relative to the left of the region, in
+	/// subpixels (see [`SUBPIXEL_BITS`]).
+	///
+	/// Glyphs are placed at the whole pixel the caret is in, so that their
+	/// fractional advances don't add up to a rounding error along the row.
+	x: u64,
+	/// The row the caret is on.
+	row: u64,
+	/// Whether the current row has yet to be cleared.
+	fresh: bool,
+	/// The width of the current logical line's leading whitespace, in subpixels.
+	indent: u64,
+	/// Whether the current logical line has only had whitespace written to it.
+	in_indent: bool,
+	/// Whether the last character written was part of a word (i.e. not whitespace).
+	in_word: bool,
+	/// The last glyph drawn on the current row, which the next is kerned
+	/// against (unless the row is fresh).
+	last_glyph: Option<char>,
+	/// What's done with words too long to fit on a row of their own.
+	overflow_mode: OverflowMode,
+	/// Whether the word being written is being let run off the right of
+	/// its row (see [`OverflowMode::Marquee`]).
+	in_overlong_word: bool,
+	/// Where the cursor was last drawn, if it's still shown.
+	cursor_drawn: Option<Rect>,
+	/// How the cursor is drawn.
+	cursor_style: CursorStyle,
+	/// Whether the cursor pulses.
+	cursor_blink: bool,
+	/// Whether control characters are shown as symbols rather than
+	/// acted upon.
+	show_control_chars: bool,
+	/// Whether alternate rows are shaded slightly lighter.
+	zebra_rows: bool,
+	/// The watermark drawn behind the text, if any.
+	background: Option<&'a Watermark>,
+	/// Decodes log output fed as bytes.
+	decoder: Utf8Decoder,
+	/// Strips escape sequences from log output, tracking the styles they select.
+	ansi: AnsiParser,
+	/// The style characters are currently written in.
+	style: Style,
+	/// Log output being assembled into a line, not yet drawn.
+	pending: [char; MAX_PENDING],
+	/// The style of each character in `pending`.
+	pending_styles: [Style; MAX_PENDING],
+	/// The number of characters in `pending`.
+	pending_len: usize,
+	/// The current time, as last set by [`Self::set_time`].
+	now: Timestamp,
+	/// When the log line currently being fed started to arrive.
+	line_stamp: Timestamp,
+	/// Whether the next character fed starts a new log line.
+	feed_at_line_start: bool,
+	/// Whether the next character written starts a new log line.
+	write_at_line_start: bool,
+	/// How the console makes room for new lines.
+	scroll_mode: ScrollMode,
+	/// What was written to each row, for redrawing it (e.g. in
+	/// [`ScrollMode::RedrawWindow`]).
+	records: [RowRecord; REDRAW_ROWS],
+	/// The row being highlighted, if any.
+	highlight: Option<u64>,
+	/// Whether the highlighted row is currently lit.
+	highlight_lit: bool,
+	/// How many times larger than the font's natural size text is drawn.
+	scale: u64,
+	/// The most recent text written, for redrawing the region.
+	scrollback: Scrollback,
+	/// Whether the scrollback is being replayed (see [`Self::redraw`]),
+	/// and so shouldn't be added to.
+	replaying: bool,
+	/// The glyphs drawn most recently.
+	glyphs: GlyphCache,
+}
+
+impl<'a> TextConsole<'a> {
+	/// Creates a new text console drawing within `region` of `vbuf`.
+	// The row records are built on the stack once per display, which is
+	// then moved into static memory (see `display::Displays`).
+	#[expect(clippy::large_stack_arrays)]
+	pub fn new(vbuf: &'a Vbuf, region: Rect) -> Self {
+		Self {
+			vbuf,
+			region: region.intersect(&Rect::of(vbuf)),
+			x: 0,
+			row: 0,
+			fresh: true,
+			indent: 0,
+			in_indent: true,
+			in_word: false,
+			last_glyph: None,
+			overflow_mode: OVERFLOW_MODE,
+			in_overlong_word: false,
+			cursor_drawn: None,
+			cursor_style: CURSOR_STYLE,
+			cursor_blink: CURSOR_BLINK,
+			show_control_chars: SHOW_CONTROL_CHARS,
+			zebra_rows: ZEBRA_ROWS,
+			background: None,
+			decoder: Utf8Decoder::new(),
+			ansi: AnsiParser::new(),
+			style: Style::DEFAULT,
+			pending: ['\0'; MAX_PENDING],
+			pending_styles: [Style::DEFAULT; MAX_PENDING],
+			pending_len: 0,
+			now: Timestamp::Millis(0),
+			line_stamp: Timestamp::Millis(0),
+			feed_at_line_start: true,
+			write_at_line_start: true,
+			scroll_mode: SCROLL_MODE,
+			records: [RowRecord::EMPTY; REDRAW_ROWS],
+			highlight: None,
+			highlight_lit: false,
+			scale: 1,
+			scrollback: Scrollback::new(),
+			replaying: false,
+			glyphs: GlyphCache::new(),
+		}
+	}
+
+	/// Sets how many times larger than the font's natural size text is
+	/// drawn (e.g. [`font_rasterizer::scale_for_height`]).
+	///
+	/// Should be set before any text is written; rows already drawn at
+	/// another scale are not redrawn.
+	pub fn set_scale(&mut self, scale: usize) {
+		self.scale = scale.max(1) as u64;
+	}
+
+	/// Returns the height of each row, in pixels.
+	fn line_height(&self) -> u64 {
+		font_rasterizer::LINE_HEIGHT as u64 * self.scale
+	}
+
+	/// Returns the width of the region, in subpixels.
+	fn line_width(&self) -> u64 {
+		self.region.w << SUBPIXEL_BITS
+	}
+
+	/// Returns the distance between tab stops, in subpixels.
+	fn tab_stop(&self) -> u64 {
+		(TAB_WIDTH * font_rasterizer::cell_width() as u64 * self.scale) << SUBPIXEL_BITS
+	}
+
+	/// Returns how far, in subpixels, the caret advances after drawing `c`
+	/// in the given style, with its left edge `x` subpixels into the row.
+	///
+	/// The position only matters for tabs, which advance to the next tab stop.
+	fn advance_at(&self, c: char, style: Style, x: u64) -> u64 {
+		if c == '\t' {
+			let stop = self.tab_stop();
+			return (x / stop + 1) * stop - x;
+		}
+
+		if let Some(marker) = block_marker(c) {
+			let width = marker.cells * font_rasterizer::cell_width() as u64;
+			return (width << SUBPIXEL_BITS) * self.scale;
+		}
+
+		let advance = if style.bold {
+			font_rasterizer::advance_bold(c)
+		} else {
+			font_rasterizer::advance(c)
+		};
+
+		advance as u64 * self.scale
+	}
+
+	/// Returns how far, in subpixels, `c` is moved right (or, if negative,
+	/// left) when drawn straight after `prev` (see [`font_rasterizer::kerning`]).
+	fn kerning(&self, prev: Option<char>, c: char) -> i64 {
+		prev.map_or(0, |prev| {
+			(i64::from(font_rasterizer::kerning(prev, c)) << SUBPIXEL_BITS)
+				* self.scale.cast_signed()
+		})
+	}
+
+	/// Starts or stops highlighting an already-drawn row, which then blinks
+	/// (see [`Self::blink_highlight`]) to draw attention to it.
+	///
+	/// Only one row is highlighted at a time. The row's text is redrawn
+	/// over the highlight, so it isn't disturbed. Rows the console has no
+	/// record of (i.e. beyond the first [`REDRAW_ROWS`]) can't be highlighted.
+	#[cfg_attr(not(all(test, feature = "mock-vbuf")), expect(dead_code))]
+	pub fn highlight_line(&mut self, row: u64, on: bool) {
+		if on {
+			if row >= self.rows().min(REDRAW_ROWS as u64) || self.highlight == Some(row) {
+				return;
+			}
+
+			if let Some(previous) = self.highlight.take() {
+				self.redraw_row(previous);
+			}
+
+			self.highlight = Some(row);
+			self.highlight_lit = true;
+			self.redraw_row(row);
+		} else if self.highlight == Some(row) {
+			self.highlight = None;
+			self.highlight_lit = false;
+			self.redraw_row(row);
+		}
+	}
+
+	/// Blinks the highlighted row, if any.
+	///
+	/// Should be called once per frame.
+	pub fn blink_highlight(&mut self, now_ms: u64) {
+		let Some(row) = self.highlight else {
+			return;
+		};
+
+		let lit = (now_ms / HIGHLIGHT_BLINK_MS) & 1 == 0;
+		if lit != self.highlight_lit {
+			self.highlight_lit = lit;
+			self.redraw_row(row);
+		}
+	}
+
+	/// Sets how the cursor is drawn.
+	#[cfg_attr(not(all(test, feature = "mock-vbuf")), expect(dead_code))]
+	pub fn set_cursor_style(&mut self, style: CursorStyle) {
+		self.cursor_style = style;
+	}
+
+	/// Sets whether the cursor pulses, rather than being drawn at full
+	/// brightness.
+	#[cfg_attr(not(all(test, feature = "mock-vbuf")), expect(dead_code))]
+	pub fn set_cursor_blink(&mut self, blink: bool) {
+		self.cursor_blink = blink;
+	}
+
+	/// Sets whether control characters are shown as symbols rather than
+	/// acted upon (see [`SHOW_CONTROL_CHARS`]).
+	#[cfg_attr(not(all(test, feature = "mock-vbuf")), expect(dead_code))]
+	pub fn set_show_control_chars(&mut self, show: bool) {
+		self.show_control_chars = show;
+	}
+
+	/// Sets whether alternate rows are shaded slightly lighter (see
+	/// [`ZEBRA_ROWS`]).
+	///
+	/// Only affects rows cleared from then on.
+	#[cfg_attr(not(all(test, feature = "mock-vbuf")), expect(dead_code))]
+	pub fn set_zebra_rows(&mut self, zebra: bool) {
+		self.zebra_rows = zebra;
+	}
+
+	/// Sets how the console makes room for new lines once the bottom
+	/// of its region is reached.
+	#[cfg_attr(not(all(test, feature = "mock-vbuf")), expect(dead_code))]
+	pub fn set_scroll_mode(&mut self, mode: ScrollMode) {
+		self.scroll_mode = mode;
+	}
+
+	/// Sets what's done with words too long to fit on a row of their own.
+	///
+	/// Only affects text written from then on.
+	#[cfg_attr(not(all(test, feature = "mock-vbuf")), expect(dead_code))]
+	pub fn set_overflow_mode(&mut self, mode: OverflowMode) {
+		self.overflow_mode = mode;
+	}
+
+	/// Scrolls the rows holding words too long to fit on them (see
+	/// [`OverflowMode::Marquee`]), redrawing those that have moved.
+	///
+	/// Each row pauses at its start, scrolls left until the end of its
+	/// text is shown, pauses again, then jumps back to its start.
+	///
+	/// Should be called once per frame.
+	pub fn scroll_marquees(&mut self, now_ms: u64) {
+		let speed = MARQUEE_SPEED * self.scale;
+		let rows = self.window_rows().min(REDRAW_ROWS as u64);
+
+		for row in 0..rows {
+			let record = &mut self.records[row as usize];
+			if !record.overlong {
+				continue;
+			}
+
+			let since = *record.marquee_since_ms.get_or_insert(now_ms);
+			let overflow = (record.end >> SUBPIXEL_BITS).saturating_sub(self.region.w);
+			let travel_ms = overflow * 1000 / speed;
+			let elapsed_ms = now_ms.saturating_sub(since) % (travel_ms + MARQUEE_PAUSE_MS * 2);
+			let scroll = elapsed_ms.saturating_sub(MARQUEE_PAUSE_MS).min(travel_ms) * speed / 1000;
+
+			if scroll != record.scroll {
+				record.scroll = scroll;
+				self.redraw_row(row);
+			}
+		}
+	}
+
+	/// Returns the most recent text written to the console.
+	pub fn scrollback(&self) -> &Scrollback {
+		&self.scrollback
+	}
+
+	/// Replaces everything written to the console with the text held by
+	/// `scrollback` (e.g. from another console), and redraws it.
+	pub fn restore(&mut self, scrollback: &Scrollback) {
+		self.scrollback.clone_from(scrollback);
+		self.redraw();
+	}
+
+	/// Sets the current time, with which new log lines are stamped.
+	pub fn set_time(&mut self, now: Timestamp) {
+		self.now = now;
+	}
+
+	/// Feeds a byte of (UTF-8 encoded) log output to the console.
+	pub fn feed_byte(&mut self, b: u8) {
+		let mut decoder = core::mem::replace(&mut self.decoder, Utf8Decoder::new());
+		decoder.push(b, |c| self.feed(c));
+		self.decoder = decoder;
+	}
+
+	/// Draws everything fed to the console that's still held back,
+	/// e.g. before the module exits or hands the screen off.
+	///
+	/// A trailing, incomplete UTF-8 sequence is drawn as U+FFFD
+	/// REPLACEMENT CHARACTER, and any partial line is drawn as-is.
+	pub fn finish(&mut self) {
+		let mut decoder = core::mem::replace(&mut self.decoder, Utf8Decoder::new());
+		decoder.finish(|c| self.feed(c));
+		self.flush_line();
+	}
+
+	/// Feeds a character of log output to the console.
+	///
+	/// Unless [`ECHO_PARTIAL_LINES`] is set, characters are held back
+	/// until their line is complete, and the whole line is then drawn at once.
+	///
+	/// Escape sequences are consumed rather than drawn, with any styles
+	/// (colors or bold) they select applied to the characters that follow.
+	pub fn feed(&mut self, c: char) {
+		let Some(c) = self.ansi.feed(c) else {
+			return;
+		};
+
+		if self.feed_at_line_start {
+			self.line_stamp = self.now;
+		}
+		self.feed_at_line_start = c == '\n';
+
+		if ECHO_PARTIAL_LINES {
+			self.style = self.ansi.style();
+			self.write_char(c);
+			return;
+		}
+
+		if self.pending_len == MAX_PENDING {
+			self.flush_line();
+		}
+
+		self.pending[self.pending_len] = c;
+		self.pending_styles[self.pending_len] = self.ansi.style();
+		self.pending_len += 1;
+
+		if c == '\n' {
+			self.flush_line();
+		}
+	}
+
+	/// Draws any log output held back by [`Self::feed`], even if its line
+	/// is not yet complete.
+	///
+	/// Should be called once the log has been quiet for a while, so that
+	/// incomplete lines (e.g. prompts) aren't held back indefinitely.
+	///
+	/// Since the whole line is known, words are wrapped as a unit (which
+	/// isn't possible with [`ECHO_PARTIAL_LINES`]).
+	pub fn flush_line(&mut self) {
+		self.write_wrapped(self.pending_len, |console, i| {
+			(console.pending[i], console.pending_styles[i])
+		});
+
+		self.pending_len = 0;
+	}
+
+	/// Writes `len` characters (each given, along with its style, by `at`),
+	/// wrapping words as a unit.
+	fn write_wrapped(&mut self, len: usize, at: impl Fn(&Self, usize) -> (char, Style)) {
+		for i in 0..len {
+			let (c, style) = at(self, i);
+
+			if !self.in_word && !c.is_whitespace() {
+				let mut prev = None;
+				let width = (i..len)
+					.map(|i| at(self, i))
+					.take_while(|(c, _)| !c.is_whitespace())
+					.map(|(c, style)| {
+						let width = self
+							.advance_at(c, style, 0)
+							.saturating_add_signed(self.kerning(prev, c));
+						prev = Some(c);
+						width
+					})
+					.sum();
+				self.wrap_word(width);
+			}
+
+			self.style = style;
+			self.write_char(c);
+		}
+	}
+
+	/// Clears the region and redraws the most recent text written to it
+	/// from the scrollback, e.g. after something else has drawn over it.
+	///
+	/// Only as many lines as fit in the region are redrawn. Line
+	/// timestamps aren't kept in the scrollback, so aren't redrawn.
+	pub fn redraw(&mut self) {
+		if !self.has_room() {
+			return;
+		}
+
+		self.reset();
+
+		let start = self.scrollback.start_of_last_lines(self.window_rows());
+		let style = self.style;
+
+		self.replaying = true;
+		self.write_wrapped(self.scrollback.len() - start, |console, i| {
+			console.scrollback.get(start + i)
+		});
+		self.replaying = false;
+
+		self.style = style;
+	}
+
+	/// Sets a watermark to draw the text over.
+	///
+	/// Cleared areas are restored to the watermark rather than to black,
+	/// and glyphs are composited over it.
+	pub fn set_background(&mut self, background: &'a Watermark) {
+		self.background = Some(background);
+	}
+
+	/// Returns the background level at the given point
+	/// (which must lie within the region).
+	fn background_at(&self, x: u64, y: u64) -> u8 {
+		let mut level = self.background.map_or(0, |bg| bg.level_at(x, y));
+		let row = (y - self.region.y) / self.line_height();
+
+		if self.zebra_rows && row % 2 == 1 {
+			level = level.saturating_add(ZEBRA_LEVEL);
+		}
+
+		if self.highlight_lit && self.highlight == Some(row) {
+			level = level.saturating_add(HIGHLIGHT_LEVEL);
+		}
+
+		level
+	}
+
+	/// Clears a rectangle (which must lie within the region) to the background.
+	fn clear_rect(&self, rect: &Rect) {
+		if self.background.is_none() {
+			// Without a watermark, the background is flat along each line.
+			for y in rect.y..rect.bottom() {
+				self.vbuf.fill_rect(
+					&Rect::new(rect.x, y, rect.w, 1),
+					self.background_at(rect.x, y),
+				);
+			}
+			return;
+		}
+
+		for y in rect.y..rect.bottom() {
+			for x in rect.x..rect.right() {
+				self.vbuf.set_grey_pixel(x, y, self.background_at(x, y));
+			}
+		}
+	}
+
+	/// Returns the number of whole rows that fit within the region.
+	fn rows(&self) -> u64 {
+		self.region.h / self.line_height()
+	}
+
+	/// Returns whether the region has room for at least one row of text.
+	///
+	/// If it doesn't, the console draws nothing at all (neither text nor
+	/// the cursor).
+	pub fn has_room(&self) -> bool {
+		self.rows() > 0
+	}
+
+	/// Returns the rectangle, within the buffer, of the given row.
+	fn row_rect(&self, row: u64) -> Rect {
+		Rect::new(
+			self.region.x,
+			self.region.y + row * self.line_height(),
+			self.region.w,
+			self.line_height(),
+		)
+		.intersect(&self.region)
+	}
+
+	/// Returns how the console actually makes room for new lines.
+	///
+	/// Copying pixels (as with [`ScrollMode::HardwareScroll`]) would move
+	/// any background layer (a watermark or zebra rows) along with the
+	/// text, so the window is redrawn over the background instead.
+	fn effective_scroll_mode(&self) -> ScrollMode {
+		match self.scroll_mode {
+			ScrollMode::HardwareScroll if self.background.is_some() || self.zebra_rows => {
+				ScrollMode::RedrawWindow
+			}
+			mode => mode,
+		}
+	}
+
+	/// Returns the number of rows in use, given the scroll mode.
+	fn window_rows(&self) -> u64 {
+		match self.effective_scroll_mode() {
+			ScrollMode::RedrawWindow => self.rows().min(REDRAW_ROWS as u64),
+			ScrollMode::HardwareScroll | ScrollMode::WrapTop => self.rows(),
+		}
+	}
+
+	/// Moves the caret to the start of the next row, making room for it
+	/// (as per the scroll mode) once the bottom is reached.
+	fn next_row(&mut self) {
+		// Otherwise the cursor would be scrolled along with the text.
+		self.hide_cursor();
+
+		self.x = 0;
+		self.fresh = true;
+		self.in_overlong_word = false;
+
+		let rows = self.window_rows();
+		if self.row + 1 < rows {
+			self.row += 1;
+			return;
+		}
+
+		match self.effective_scroll_mode() {
+			ScrollMode::WrapTop => self.row = 0,
+			ScrollMode::HardwareScroll => {
+				let window = Rect::new(
+					self.region.x,
+					self.region.y,
+					self.region.w,
+					rows * self.line_height(),
+				);
+				self.vbuf.scroll_up(&window, self.line_height());
+				// Otherwise the bottom row would show a copy of the row
+				// above until it's written to.
+				self.clear_rect(&self.row_rect(self.row));
+
+				// Keep the records in step with what's on screen, so that
+				// the rows can still be redrawn (e.g. when highlighted).
+				let recorded = (rows as usize).min(REDRAW_ROWS);
+				self.records[..recorded].rotate_left(1);
+				self.records[recorded - 1] = RowRecord::EMPTY;
+			}
+			ScrollMode::RedrawWindow => {
+				let rows = rows as usize;
+				self.records[..rows].rotate_left(1);
+				self.records[rows - 1] = RowRecord::EMPTY;
+
+				for row in 0..rows {
+					self.redraw_row(row as u64);
+				}
+			}
+		}
+	}
+
+	/// Clears a row and redraws it from its record.
+	///
+	/// Does nothing if there's no record of the row.
+	fn redraw_row(&mut self, row: u64) {
+		let Some(&record) = self.records.get(row as usize) else {
+			return;
+		};
+
+		if self.row == row {
+			self.hide_cursor();
+		}
+
+		self.clear_rect(&self.row_rect(row));
+
+		let mut x = record.x;
+		let mut prev = None;
+		for (&c, &style) in record.chars[..record.len]
+			.iter()
+			.zip(&record.styles[..record.len])
+		{
+			x = x.saturating_add_signed(self.kerning(prev, c));
+			self.draw_glyph(c, style, x, row);
+			x += self.advance_at(c, style, x);
+			prev = Some(c);
+		}
+	}
+
+	/// Writes a single character to the console, in the style last
+	/// selected by the log output.
+	pub fn write_char(&mut self, c: char) {
+		if !self.has_room() {
+			return;
+		}
+
+		if !self.replaying {
+			self.scrollback.push(c, self.style);
+		}
+
+		if LINE_TIMESTAMPS
+			&& !self.replaying
+			&& self.write_at_line_start
+			&& !matches!(c, '\n' | '\r')
+		{
+			self.write_at_line_start = false;
+			self.put_timestamp();
+		}
+
+		if self.show_control_chars && c.is_control() {
+			self.put_control_picture(c);
+			return;
+		}
+
+		if c == '\n' {
+			self.newline();
+			return;
+		}
+
+		if c == '\r' {
+			// Back to the start of the row, which is cleared (as a new row
+			// would be) once it's written to again, so that the new text
+			// overwrites the old (e.g. for progress indicators).
+			self.x = 0;
+			self.fresh = true;
+			self.indent = 0;
+			self.in_indent = true;
+			self.in_word = false;
+			self.in_overlong_word = false;
+			self.write_at_line_start = true;
+			return;
+		}
+
+		self.put_glyph(c);
+	}
+
+	/// Ends the current line, moving the caret to the start of the next row.
+	pub fn newline(&mut self) {
+		if !self.has_room() {
+			return;
+		}
+
+		self.next_row();
+		self.indent = 0;
+		self.in_indent = true;
+		self.in_word = false;
+		self.write_at_line_start = true;
+	}
+
+	/// Clears the whole region (along with the cursor) and moves the caret
+	/// back to the top, forgetting everything written so far (including
+	/// the scrollback).
+	///
+	/// Log output still held back by [`Self::feed`] is kept, and drawn as usual.
+	#[cfg_attr(not(all(test, feature = "mock-vbuf")), expect(dead_code))]
+	pub fn clear(&mut self) {
+		if !self.has_room() {
+			return;
+		}
+
+		self.reset();
+		self.scrollback.clear();
+	}
+
+	/// Moves the console to `region` of its buffer (e.g. once the buffer
+	/// has been laid out afresh), forgetting everything written or fed to
+	/// it so far, along with its background.
+	///
+	/// The new region is cleared; the old one is left as it is. The
+	/// console's state is reused in place, rather than making a new console
+	/// (which would take up a lot of stack).
+	pub fn relocate(&mut self, region: Rect) {
+		// The cursor is erased from the old region, before it's forgotten.
+		self.background = None;
+		self.hide_cursor();
+
+		self.region = region.intersect(&Rect::of(self.vbuf));
+		self.reset();
+		self.scrollback.clear();
+
+		self.decoder = Utf8Decoder::new();
+		self.ansi = AnsiParser::new();
+		self.style = Style::DEFAULT;
+		self.pending_len = 0;
+		self.feed_at_line_start = true;
+	}
+
+	/// Clears the whole region (along with the cursor) and moves the caret
+	/// back to the top, as though nothing had been written.
+	fn reset(&mut self) {
+		self.highlight = None;
+		self.highlight_lit = false;
+		self.hide_cursor();
+		self.clear_rect(&self.region);
+
+		self.x = 0;
+		self.row = 0;
+		self.fresh = true;
+		self.indent = 0;
+		self.in_indent = true;
+		self.in_word = false;
+		self.in_overlong_word = false;
+		self.write_at_line_start = true;
+		self.records.fill(RowRecord::EMPTY);
+	}
+
+	/// Draws the current line's timestamp, followed by a space.
+	///
+	/// The prefix counts towards the line's indent, so wrapped
+	/// continuations line up with the text rather than the timestamp.
+	fn put_timestamp(&mut self) {
+		let mut stamp = StrBuf::<24>::new();
+		// A timestamp that doesn't fit is merely truncated.
+		let _ = write!(stamp, "{} ", self.line_stamp);
+
+		// The timestamp isn't part of the log output, so isn't styled by it.
+		let style = core::mem::replace(&mut self.style, Style::DEFAULT);
+		for c in stamp.as_str().chars() {
+			self.put_glyph(c);
+		}
+		self.style = style;
+
+		self.in_indent = true;
+		self.indent = self.x;
+	}
+
+	/// Erases the glyph for `c`, which must be the last character written
+	/// on the current row (in the current style), moving the caret back over it.
+	///
+	/// Tabs are erased back to the previous tab stop, along with any text
+	/// between it and the tab.
+	///
+	/// Does nothing if the caret is at the start of the row.
+	pub fn erase_char(&mut self, c: char) {
+		if !self.has_room() {
+			return;
+		}
+
+		let width = if c == '\t' {
+			let stop = self.tab_stop();
+			self.x - (self.x.saturating_sub(1) / stop) * stop
+		} else {
+			self.advance_at(c, self.style, 0).min(self.x)
+		};
+		if width == 0 {
+			return;
+		}
+
+		self.hide_cursor();
+		self.x -= width;
+		self.scrollback.pop();
+
+		// Clear every pixel the glyph could have touched.
+		let left = self.x >> SUBPIXEL_BITS;
+		let right = (self.x + width).div_ceil(1 << SUBPIXEL_BITS);
+		let row = self.row_rect(self.row);
+		self.clear_rect(&Rect::new(row.x + left, row.y, right - left, row.h).intersect(&row));
+
+		// Undo the glyph's kerning against the one before it, which the next
+		// glyph is then kerned against in its place.
+		let prev = self
+			.records
+			.get(self.row as usize)
+			.and_then(|record| record.len.checked_sub(2).map(|i| record.chars[i]));
+		self.x = self.x.saturating_sub_signed(self.kerning(prev, c));
+		self.last_glyph = prev;
+
+		if let Some(record) = self.records.get_mut(self.row as usize) {
+			record.len = record.len.saturating_sub(1);
+			record.end = self.x;
+		}
+	}
+
+	/// Moves the caret to the (indented) start of the next row ahead of
+	/// a word `width` subpixels wide, if it won't fit on the current row.
+	///
+	/// Words too wide to fit on a row of their own are left where they are,
+	/// and broken wherever the row runs out (see [`Self::put_glyph`]), unless
+	/// they're to run off the row instead (see [`OverflowMode::Marquee`]).
+	fn wrap_word(&mut self, width: u64) {
+		if !self.has_room() {
+			return;
+		}
+
+		let starts_row = self.in_indent || self.x <= self.indent;
+
+		if self.overflow_mode == OverflowMode::Marquee && self.indent + width > self.line_width() {
+			// The word is given a row of its own, to scroll along.
+			if !starts_row {
+				self.next_row();
+				self.x = self.indent;
+			}
+
+			self.in_overlong_word = true;
+			return;
+		}
+
+		if starts_row {
+			return;
+		}
+
+		if self.x + width > self.line_width() && self.indent + width <= self.line_width() {
+			self.next_row();
+			self.x = self.indent;
+		}
+	}
+
+	/// Draws a visible symbol for a control character.
+	///
+	/// Uses the Unicode control picture for the character if the font has
+	/// one, otherwise falls back to caret notation (e.g. `^J` for a newline).
+	fn put_control_picture(&mut self, c: char) {
+		let picture = match c {
+			'\0'..='\x1F' => char::from_u32(0x2400 + c as u32),
+			'\x7F' => Some('\u{2421}'),
+			_ => None,
+		};
+
+		if let Some(picture) = picture.filter(|&p| font_rasterizer::render_glyph(p).is_some()) {
+			self.put_glyph(picture);
+			return;
+		}
+
+		self.put_glyph('^');
+		if let '\0'..='\x1F' = c {
+			self.put_glyph(char::from(c as u8 + b'@'));
+		} else {
+			// DEL is `^?`; other (C1) control characters have no
+			// caret notation, so they're shown the same way.
+			self.put_glyph('?');
+		}
+	}
+
+	/// Draws a single glyph at the caret and advances it,
+	/// wrapping onto the next row if need be.
+	///
+	/// Wide characters are treated as a unit; if only one cell
+	/// remains on the row, they're wrapped rather than split. Tabs
+	/// draw nothing, but advance the caret to the next tab stop.
+	fn put_glyph(&mut self, c: char) {
+		// The glyph may be drawn where the cursor is.
+		self.hide_cursor();
+
+		if !self.fresh {
+			self.x = self
+				.x
+				.saturating_add_signed(self.kerning(self.last_glyph, c));
+		}
+
+		let mut width = self.advance_at(c, self.style, self.x);
+
+		if self.in_indent {
+			if c.is_whitespace() {
+				self.indent += width;
+			} else {
+				self.in_indent = false;
+			}
+		}
+
+		if !self.in_indent
+			&& !self.in_overlong_word
+			&& self.x > self.indent
+			&& self.x + width > self.line_width()
+		{
+			self.next_row();
+
+			// Indent the continuation to line up with the line it continues,
+			// unless the indent is so wide there'd be no room left for text.
+			if self.indent + width <= self.line_width() {
+				self.x = self.indent;
+			}
+
+			width = self.advance_at(c, self.style, self.x);
+		}
+
+		if self.fresh {
+			// First write of the row; clear it.
+			self.clear_rect(&self.row_rect(self.row));
+			self.fresh = false;
+
+			if let Some(record) = self.records.get_mut(self.row as usize) {
+				*record = RowRecord {
+					x: self.x,
+					..RowRecord::EMPTY
+				};
+			}
+		}
+
+		if let Some(record) = self.records.get_mut(self.row as usize)
+			&& record.len < REDRAW_ROW_CHARS
+		{
+			record.chars[record.len] = c;
+			record.styles[record.len] = self.style;
+			record.len += 1;
+			record.end = self.x + width;
+			record.overlong |= self.in_overlong_word;
+		}
+
+		self.draw_glyph(c, self.style, self.x, self.row);
+
+		self.x += width;
+		self.last_glyph = Some(c);
+		self.in_word = !c.is_whitespace();
+
+		// Whatever follows an over-long word (once past this whitespace)
+		// wraps as usual.
+		if !self.in_word {
+			self.in_overlong_word = false;
+		}
+	}
+
+	/// Draws a single glyph with its left edge `x` subpixels into the given
+	/// row (less however far the row is scrolled; see [`Self::scroll_marquees`]).
+	fn draw_glyph(&mut self, c: char, style: Style, x: u64, row: u64) {
+		if c == '\t' {
+			return;
+		}
+
+		let color = style.foreground.color(TEXT_COLOR);
+		let top = self.region.y + row * self.line_height();
+		let scroll = self
+			.records
+			.get(row as usize)
+			.map_or(0, |record| record.scroll);
+
+		let marker = block_marker(c);
+
+		if marker.is_some() || box_drawing::is_synthesized(c) {
+			// Box-drawing characters and block markers are drawn over
+			// their cells rather than from the font. Cells scrolled partly
+			// off the left of the region are left out.
+			let left = self.region.x + (x >> SUBPIXEL_BITS);
+			let right = self.region.x + ((x + self.advance_at(c, style, x)) >> SUBPIXEL_BITS);
+			if left < self.region.x + scroll {
+				return;
+			}
+
+			let cell = Rect::new(left - scroll, top, right - left, self.line_height());
+
+			if let Some(marker) = marker {
+				// Blocks leave the line gap, and a pixel to their right,
+				// clear, so as not to run into the text around them.
+				let block = Rect::new(
+					cell.x,
+					cell.y,
+					cell.w.saturating_sub(self.scale),
+					font_rasterizer::FONT_HEIGHT as u64 * self.scale,
+				);
+				self.vbuf
+					.fill_rect_color(&block.intersect(&self.region), marker.color);
+			} else {
+				// Box-drawing characters fill their whole cell, line gap and
+				// all, so that they join up with their neighbors.
+				box_drawing::draw(self.vbuf, c, &cell, &self.region, color, self.scale);
+			}
+
+			return;
+		}
+
+		let index = self.glyphs.load(c, style.bold);
+		let glyph = ScaledGlyph::new(self.glyphs.glyph(index), self.scale as usize);
+		let advance = self.advance_at(c, style, x);
+
+		// Glyphs narrower than their advance (i.e. wide characters) are centered.
+		let left = self.region.x
+			+ (x >> SUBPIXEL_BITS)
+			+ font_rasterizer::centering_offset(glyph.width(), advance as usize) as u64;
+
+		for (x, y, v) in glyph {
+			let Some(x) = (left + x as u64).checked_sub(scroll) else {
+				continue;
+			};
+			let y = top + y as u64;
+			if self.region.contains(x, y) {
+				// The glyph's coverage is composited over the background,
+				// so fully covered pixels are always the full text color.
+				let background = Color::grey(self.background_at(x, y));
+				self.vbuf.set_pixel(x, y, color.blended_over(background, v));
+			}
+		}
+	}
+
+	/// Draws the cursor at the given brightness level (or at full
+	/// brightness, if it doesn't pulse), clearing it from its previous
+	/// position if it's moved.
+	pub fn draw_cursor(&mut self, level: u8) {
+		if !self.has_room() {
+			return;
+		}
+
+		let rect = self.cursor_rect();
+		if self.cursor_drawn != Some(rect) {
+			self.hide_cursor();
+		}
+
+		let level = if self.cursor_blink { level } else { u8::MAX };
+		self.vbuf.fill_rect(&rect, level);
+		self.cursor_drawn = Some(rect);
+	}
+
+	/// Clears the cursor, if it's shown.
+	///
+	/// Must be called before anything is drawn where the cursor may be.
+	fn hide_cursor(&mut self) {
+		if let Some(rect) = self.cursor_drawn.take() {
+			self.clear_rect(&rect);
+		}
+	}
+
+	/// Returns the rectangle, within the buffer, that the cursor covers
+	/// (as per its style), clamped to the region.
+	fn cursor_rect(&self) -> Rect {
+		let left = self.region.x + (self.x >> SUBPIXEL_BITS);
+		let top = self.region.y + self.row * self.line_height();
+		let height = font_rasterizer::FONT_HEIGHT as u64 * self.scale;
+		let cell = font_rasterizer::cell_width() as u64 * self.scale;
+		let thickness = CURSOR_THICKNESS * self.scale;
+
+		match self.cursor_style {
+			CursorStyle::Bar => Rect::new(left, top, thickness, height),
+			CursorStyle::Underline => {
+				Rect::new(
+					left,
+					top + font_rasterizer::ASCENT as u64 * self.scale,
+					cell,
+					thickness,
+				)
+			}
+			CursorStyle::Block => Rect::new(left, top, cell, height),
+		}
+		.intersect(&self.region)
+	}
+}
+
+impl Write for TextConsole<'_> {
+	/// Writes a string to the console, as with [`TextConsole::write_char`].
+	///
+	/// Unlike log output fed to the console, the string is drawn straight
+	/// away, and any escape sequences in it are drawn as-is.
+	fn write_str(&mut self, s: &str) -> core::fmt::Result {
+		for c in s.chars() {
+			self.write_char(c);
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(all(test, feature = "mock-vbuf"))]
+mod tests;
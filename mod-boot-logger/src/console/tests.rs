@@ -0,0 +1,535 @@
+//! Tests for the text console, against a [`MockVbuf`].
+
+use core::fmt::Write;
+
+use super::{CURSOR_THICKNESS, CursorStyle, OverflowMode, ScrollMode, TextConsole, ZEBRA_LEVEL};
+use crate::{
+	Rect,
+	font_rasterizer::{
+		ASCENT, DESCENT, FONT_HEIGHT, LINE_HEIGHT, cell_width, measure_text, render_glyph,
+	},
+	mock_vbuf::{MockFormat, MockVbuf},
+	watermark::Watermark,
+};
+
+/// Returns the text held in a console's scrollback.
+fn text(console: &TextConsole<'_>) -> String {
+	let scrollback = console.scrollback();
+	(0..scrollback.len()).map(|i| scrollback.get(i).0).collect()
+}
+
+#[test]
+fn finishes_truncated_sequences_with_a_replacement_character() {
+	let mock = MockVbuf::new(320, 64, MockFormat::Xrgb8888);
+	let vbuf = mock.vbuf();
+	let mut console = TextConsole::new(vbuf, Rect::of(vbuf));
+
+	for &b in b"ok \xE2\x82" {
+		console.feed_byte(b);
+	}
+	console.finish();
+
+	assert_eq!(text(&console), "ok \u{FFFD}");
+}
+
+#[test]
+fn finishes_cleanly_between_sequences() {
+	let mock = MockVbuf::new(320, 64, MockFormat::Xrgb8888);
+	let vbuf = mock.vbuf();
+	let mut console = TextConsole::new(vbuf, Rect::of(vbuf));
+
+	for &b in "ok €".as_bytes() {
+		console.feed_byte(b);
+	}
+	console.finish();
+
+	assert_eq!(text(&console), "ok €");
+}
+
+#[test]
+fn relocating_forgets_everything_written() {
+	let mock = MockVbuf::new(320, 64, MockFormat::Xrgb8888);
+	let vbuf = mock.vbuf();
+	let mut console = TextConsole::new(vbuf, Rect::new(0, 0, 320, 32));
+
+	for &b in b"held \xE2\x82" {
+		console.feed_byte(b);
+	}
+	console.flush_line();
+
+	console.relocate(Rect::new(0, 32, 320, 32));
+	assert_eq!(text(&console), "");
+
+	// Nothing's left of the sequence that was cut short, and the text
+	// goes to the new region.
+	let before = mock.lit_count();
+	for &b in b"ok" {
+		console.feed_byte(b);
+	}
+	console.finish();
+
+	assert_eq!(text(&console), "ok");
+	assert!(mock.lit_count() > before);
+	assert!((32..64).any(|y| (0..320).any(|x| mock.is_lit(x, y))));
+}
+
+/// Returns the raw pixels of each of a console's rows, `rows` rows tall
+/// from the top of the buffer.
+fn row_pixels(mock: &MockVbuf, rows: u64) -> Vec<Vec<u32>> {
+	let line_height = LINE_HEIGHT as u64;
+	(0..rows)
+		.map(|row| {
+			(row * line_height..(row + 1) * line_height)
+				.flat_map(|y| (0..mock.vbuf().width).map(move |x| (x, y)))
+				.map(|(x, y)| mock.raw_pixel(x, y))
+				.collect()
+		})
+		.collect()
+}
+
+#[test]
+fn scrolls_rows_up_and_clears_the_bottom_one() {
+	let mock = MockVbuf::new(64, LINE_HEIGHT as u64 * 3, MockFormat::Xrgb8888);
+	let vbuf = mock.vbuf();
+	let mut console = TextConsole::new(vbuf, Rect::of(vbuf));
+	console.set_scroll_mode(ScrollMode::HardwareScroll);
+
+	console.write_str("1\n2\n3").unwrap();
+	let before = row_pixels(&mock, 3);
+	let line_height = LINE_HEIGHT as u64;
+	let row_lit = |row: u64| {
+		(row * line_height..(row + 1) * line_height).any(|y| (0..64).any(|x| mock.is_lit(x, y)))
+	};
+	assert!((0..3).all(row_lit));
+
+	console.write_str("\n").unwrap();
+	let after = row_pixels(&mock, 3);
+
+	assert_eq!(after[0], before[1]);
+	assert_eq!(after[1], before[2]);
+	assert!(!row_lit(2));
+}
+
+#[test]
+fn redraws_rows_one_higher_in_a_redrawn_window() {
+	let mock = MockVbuf::new(64, LINE_HEIGHT as u64 * 3, MockFormat::Xrgb8888);
+	let vbuf = mock.vbuf();
+	let mut console = TextConsole::new(vbuf, Rect::of(vbuf));
+	console.set_scroll_mode(ScrollMode::RedrawWindow);
+
+	console.write_str("1\n2\n3").unwrap();
+	let before = row_pixels(&mock, 3);
+
+	console.write_str("\n").unwrap();
+	let after = row_pixels(&mock, 3);
+
+	assert_eq!(after[0], before[1]);
+	assert_eq!(after[1], before[2]);
+	assert!(!row_lit(&mock, 2));
+}
+
+#[test]
+fn wraps_back_around_to_the_top_row() {
+	let mock = MockVbuf::new(64, LINE_HEIGHT as u64 * 3, MockFormat::Xrgb8888);
+	let vbuf = mock.vbuf();
+	let mut console = TextConsole::new(vbuf, Rect::of(vbuf));
+	console.set_scroll_mode(ScrollMode::WrapTop);
+
+	console.write_str("1\n2\n3").unwrap();
+	let before = row_pixels(&mock, 3);
+
+	// The oldest line is overwritten in place; the others stay put.
+	console.write_str("\n4").unwrap();
+	let after = row_pixels(&mock, 3);
+
+	let fresh = MockVbuf::new(64, LINE_HEIGHT as u64 * 3, MockFormat::Xrgb8888);
+	let mut expected = TextConsole::new(fresh.vbuf(), Rect::of(fresh.vbuf()));
+	expected.write_str("4").unwrap();
+
+	assert_eq!(after[0], row_pixels(&fresh, 1)[0]);
+	assert_eq!(after[1], before[1]);
+	assert_eq!(after[2], before[2]);
+}
+
+#[test]
+fn redraws_the_window_rather_than_scroll_a_background() {
+	let mock = MockVbuf::new(128, 128, MockFormat::Grey8);
+	let vbuf = mock.vbuf();
+	let watermark = Watermark::new(&Rect::of(vbuf)).unwrap();
+
+	let mut console = TextConsole::new(vbuf, Rect::of(vbuf));
+	console.set_scroll_mode(ScrollMode::HardwareScroll);
+	assert_eq!(console.effective_scroll_mode(), ScrollMode::HardwareScroll);
+
+	console.set_zebra_rows(true);
+	assert_eq!(console.effective_scroll_mode(), ScrollMode::RedrawWindow);
+
+	console.set_zebra_rows(false);
+	console.set_background(&watermark);
+	assert_eq!(console.effective_scroll_mode(), ScrollMode::RedrawWindow);
+
+	// Neither of the other modes moves pixels, so they're kept as they are.
+	for mode in [ScrollMode::WrapTop, ScrollMode::RedrawWindow] {
+		console.set_scroll_mode(mode);
+		assert_eq!(console.effective_scroll_mode(), mode);
+	}
+}
+
+#[test]
+fn keeps_the_watermark_in_place_when_scrolling() {
+	let mock = MockVbuf::new(128, 128, MockFormat::Grey8);
+	let vbuf = mock.vbuf();
+	let watermark = Watermark::new(&Rect::of(vbuf)).unwrap();
+	watermark.draw(vbuf);
+
+	let mut console = TextConsole::new(vbuf, Rect::of(vbuf));
+	console.set_background(&watermark);
+	console.set_scroll_mode(ScrollMode::HardwareScroll);
+
+	// Blank lines draw nothing but the background, which has to end up
+	// where it started, however many times the console scrolls.
+	for _ in 0..20 {
+		console.write_str(" \n").unwrap();
+	}
+
+	for y in 0..vbuf.height {
+		for x in 0..vbuf.width {
+			assert_eq!(
+				vbuf.get_grey_pixel(x, y),
+				Some(watermark.level_at(x, y)),
+				"({x}, {y})"
+			);
+		}
+	}
+}
+
+#[test]
+fn retyping_an_erased_glyph_draws_it_where_it_was() {
+	let typed = MockVbuf::new(320, 32, MockFormat::Xrgb8888);
+	let mut console = TextConsole::new(typed.vbuf(), Rect::of(typed.vbuf()));
+	console.write_str("To AV").unwrap();
+
+	let retyped = MockVbuf::new(320, 32, MockFormat::Xrgb8888);
+	let mut console = TextConsole::new(retyped.vbuf(), Rect::of(retyped.vbuf()));
+	console.write_str("To AW").unwrap();
+	console.erase_char('W');
+	console.write_str("V").unwrap();
+
+	assert_eq!(retyped.render(), typed.render());
+}
+
+#[test]
+fn redrawing_lays_text_out_as_it_was_written() {
+	let mock = MockVbuf::new(320, 32, MockFormat::Xrgb8888);
+	let mut console = TextConsole::new(mock.vbuf(), Rect::of(mock.vbuf()));
+	console.write_str("AVATAR Yo, To.").unwrap();
+	let written = mock.render();
+
+	console.redraw();
+
+	assert_eq!(mock.render(), written);
+}
+
+/// Returns the lit pixels of a buffer.
+fn lit_pixels(mock: &MockVbuf) -> Vec<(u64, u64)> {
+	let vbuf = mock.vbuf();
+	(0..vbuf.height)
+		.flat_map(|y| (0..vbuf.width).map(move |x| (x, y)))
+		.filter(|&(x, y)| mock.is_lit(x, y))
+		.collect()
+}
+
+#[test]
+fn draws_the_cursor_in_each_style() {
+	let mock = MockVbuf::new(64, 32, MockFormat::Grey8);
+	let vbuf = mock.vbuf();
+	let mut console = TextConsole::new(vbuf, Rect::of(vbuf));
+	let cell = cell_width() as u64;
+	let height = FONT_HEIGHT as u64;
+
+	console.set_cursor_style(CursorStyle::Block);
+	console.draw_cursor(u8::MAX);
+	assert_eq!(mock.lit_count() as u64, cell * height);
+
+	// Switching styles clears the old cursor.
+	console.set_cursor_style(CursorStyle::Bar);
+	console.draw_cursor(u8::MAX);
+	let bar = lit_pixels(&mock);
+	assert_eq!(bar.len() as u64, CURSOR_THICKNESS * height);
+	assert!(bar.iter().all(|&(x, _)| x < CURSOR_THICKNESS));
+
+	console.set_cursor_style(CursorStyle::Underline);
+	console.draw_cursor(u8::MAX);
+	let underline = lit_pixels(&mock);
+	assert_eq!(underline.len() as u64, cell * CURSOR_THICKNESS);
+	assert!(
+		underline
+			.iter()
+			.all(|&(_, y)| (ASCENT..ASCENT + DESCENT).contains(&(y as usize)))
+	);
+}
+
+#[test]
+fn only_pulses_the_cursor_if_it_blinks() {
+	let mock = MockVbuf::new(64, 32, MockFormat::Grey8);
+	let vbuf = mock.vbuf();
+	let mut console = TextConsole::new(vbuf, Rect::of(vbuf));
+
+	console.set_cursor_blink(true);
+	console.draw_cursor(0x40);
+	assert_eq!(mock.raw_pixel(0, 0), 0x40);
+
+	console.set_cursor_blink(false);
+	console.draw_cursor(0x40);
+	assert_eq!(mock.raw_pixel(0, 0), 0xFF);
+}
+
+/// Returns whether any pixel of row `row` of the console is lit.
+fn row_lit(mock: &MockVbuf, row: u64) -> bool {
+	let line = LINE_HEIGHT as u64;
+	(row * line..(row + 1) * line).any(|y| (0..mock.vbuf().width).any(|x| mock.is_lit(x, y)))
+}
+
+#[test]
+fn lets_over_long_words_run_off_the_row_as_a_marquee() {
+	let word = b"an-unbreakably-long-word-for-the-row";
+
+	let wrapped = MockVbuf::new(64, 64, MockFormat::Grey8);
+	let mut console = TextConsole::new(wrapped.vbuf(), Rect::of(wrapped.vbuf()));
+	console.set_overflow_mode(OverflowMode::Wrap);
+	for &b in word {
+		console.feed_byte(b);
+	}
+	console.flush_line();
+	assert!(row_lit(&wrapped, 0) && row_lit(&wrapped, 1));
+
+	let marquee = MockVbuf::new(64, 64, MockFormat::Grey8);
+	let mut console = TextConsole::new(marquee.vbuf(), Rect::of(marquee.vbuf()));
+	console.set_overflow_mode(OverflowMode::Marquee);
+	for &b in word {
+		console.feed_byte(b);
+	}
+	console.flush_line();
+	assert!(row_lit(&marquee, 0) && !row_lit(&marquee, 1));
+}
+
+#[test]
+fn highlights_a_row_behind_its_text() {
+	let mock = MockVbuf::new(64, 64, MockFormat::Grey8);
+	let vbuf = mock.vbuf();
+	let mut console = TextConsole::new(vbuf, Rect::of(vbuf));
+	console.write_str("one\ntwo").unwrap();
+	let written = mock.render();
+
+	console.highlight_line(0, true);
+	let line = LINE_HEIGHT as u64;
+	assert!((0..line).all(|y| (0..vbuf.width).all(|x| mock.is_lit(x, y))));
+	assert_eq!(
+		mock.render().lines().skip(LINE_HEIGHT).collect::<Vec<_>>(),
+		written.lines().skip(LINE_HEIGHT).collect::<Vec<_>>()
+	);
+
+	// Rows past the bottom of the region can't be highlighted.
+	console.highlight_line(64, true);
+	assert!(mock.is_lit(0, 0));
+
+	console.highlight_line(0, false);
+	assert_eq!(mock.render(), written);
+}
+
+#[test]
+fn clearing_forgets_everything_written() {
+	let mock = MockVbuf::new(64, 64, MockFormat::Grey8);
+	let vbuf = mock.vbuf();
+	let mut console = TextConsole::new(vbuf, Rect::of(vbuf));
+	console.write_str("one\ntwo").unwrap();
+
+	console.clear();
+	assert_eq!(mock.lit_count(), 0);
+	assert_eq!(text(&console), "");
+
+	// Writing starts over from the top.
+	console.write_str("one").unwrap();
+	assert!(row_lit(&mock, 0) && !row_lit(&mock, 1));
+}
+
+/// Returns the leftmost lit column of row `row` of the console, if any.
+fn first_lit_x(mock: &MockVbuf, row: u64) -> Option<u64> {
+	let line = LINE_HEIGHT as u64;
+	(0..mock.vbuf().width).find(|&x| (row * line..(row + 1) * line).any(|y| mock.is_lit(x, y)))
+}
+
+#[test]
+fn indents_wrapped_lines_to_match_the_line_they_continue() {
+	let mock = MockVbuf::new(240, 64, MockFormat::Grey8);
+	let vbuf = mock.vbuf();
+	let mut console = TextConsole::new(vbuf, Rect::of(vbuf));
+	// Every glyph is the same, so each row's text starts the same way
+	// (wherever the line's broken).
+	console
+		.write_str("    aaaa aaaa aaaa aaaa aaaa aaaa")
+		.unwrap();
+	console.flush_line();
+
+	let first = first_lit_x(&mock, 0).unwrap();
+	assert!(first >= 3 * cell_width() as u64);
+	assert!(row_lit(&mock, 1), "the line didn't wrap");
+	assert_eq!(first_lit_x(&mock, 1), Some(first));
+
+	// A new line starts back at the left of the region.
+	console.write_str("\naaaa").unwrap();
+	console.flush_line();
+	let start = first_lit_x(&mock, 2).expect("the new line wasn't drawn");
+	assert!(start + 3 * cell_width() as u64 <= first);
+	// ...and leaves the rows above it where they were.
+	assert_eq!(first_lit_x(&mock, 1), Some(first));
+}
+
+#[test]
+fn shows_control_characters_as_symbols_when_asked() {
+	let shown = MockVbuf::new(160, 64, MockFormat::Grey8);
+	let mut console = TextConsole::new(shown.vbuf(), Rect::of(shown.vbuf()));
+	console.set_show_control_chars(true);
+	console.write_str("a\nb").unwrap();
+
+	// The newline is drawn as its control picture (or in caret notation,
+	// if the font lacks it), and the text carries on along the row.
+	let picture = if render_glyph('\u{2424}').is_some() {
+		"\u{2424}"
+	} else {
+		"^J"
+	};
+	let drawn = MockVbuf::new(160, 64, MockFormat::Grey8);
+	let mut console = TextConsole::new(drawn.vbuf(), Rect::of(drawn.vbuf()));
+	console.write_str("a").unwrap();
+	console.write_str(picture).unwrap();
+	console.write_str("b").unwrap();
+
+	assert!(!row_lit(&shown, 1));
+	assert_eq!(shown.render(), drawn.render());
+}
+
+#[test]
+fn holds_partial_lines_back_until_complete_or_flushed() {
+	let fed = MockVbuf::new(160, 64, MockFormat::Grey8);
+	let mut console = TextConsole::new(fed.vbuf(), Rect::of(fed.vbuf()));
+	let written = MockVbuf::new(160, 64, MockFormat::Grey8);
+	let mut expected = TextConsole::new(written.vbuf(), Rect::of(written.vbuf()));
+
+	for &b in b"ab" {
+		console.feed_byte(b);
+	}
+	assert_eq!(fed.lit_count(), 0);
+
+	// Completing the line draws it whole, holding back the next one.
+	for &b in b"c\nxy" {
+		console.feed_byte(b);
+	}
+	expected.write_str("abc\n").unwrap();
+	assert_eq!(fed.render(), written.render());
+
+	// Once the log's been quiet a while, the partial line is drawn as-is,
+	// and the rest of it carries on along the same row.
+	console.flush_line();
+	expected.write_str("xy").unwrap();
+	assert_eq!(fed.render(), written.render());
+
+	for &b in b"z\n" {
+		console.feed_byte(b);
+	}
+	expected.write_str("z\n").unwrap();
+	assert_eq!(fed.render(), written.render());
+	assert!(!row_lit(&fed, 2));
+}
+
+#[test]
+fn wraps_a_wide_character_whole_rather_than_split_it() {
+	// Room for the row's text and a cell and a half more; a narrow
+	// character fits there, but a wide one (two cells) doesn't.
+	let cell = cell_width() as u64;
+	let width = measure_text("0000000") as u64 + cell * 3 / 2;
+
+	let narrow = MockVbuf::new(width, 64, MockFormat::Grey8);
+	let mut console = TextConsole::new(narrow.vbuf(), Rect::of(narrow.vbuf()));
+	console.write_str("00000000").unwrap();
+	assert!(!row_lit(&narrow, 1));
+
+	let wide = MockVbuf::new(width, 64, MockFormat::Grey8);
+	let mut console = TextConsole::new(wide.vbuf(), Rect::of(wide.vbuf()));
+	console.write_str("0000000\u{4E2D}").unwrap();
+
+	let expected = MockVbuf::new(width, 64, MockFormat::Grey8);
+	let mut console = TextConsole::new(expected.vbuf(), Rect::of(expected.vbuf()));
+	console.write_str("0000000\n\u{4E2D}").unwrap();
+	assert!(row_lit(&wide, 1));
+	assert_eq!(wide.render(), expected.render());
+}
+
+#[test]
+fn shades_alternate_rows_when_zebra_striped() {
+	let line = LINE_HEIGHT as u64;
+
+	for zebra in [true, false] {
+		let mock = MockVbuf::new(120, line * 4, MockFormat::Grey8);
+		let vbuf = mock.vbuf();
+		let mut console = TextConsole::new(vbuf, Rect::of(vbuf));
+		console.set_zebra_rows(zebra);
+		console.write_str("a\nb\nc\nd").unwrap();
+
+		// The end of each row is clear of its text.
+		let shade = if zebra { ZEBRA_LEVEL } else { 0 };
+		let levels: Vec<_> = (0..4).map(|row| mock.raw_pixel(119, row * line)).collect();
+		assert_eq!(levels, [0, shade.into(), 0, shade.into()], "zebra: {zebra}");
+	}
+}
+
+#[test]
+fn draws_nothing_in_a_region_without_a_whole_row() {
+	let line = LINE_HEIGHT as u64;
+
+	for height in [0, 1, line - 1] {
+		let mock = MockVbuf::new(120, line * 3, MockFormat::Grey8);
+		let vbuf = mock.vbuf();
+		let mut console = TextConsole::new(vbuf, Rect::new(10, line, 100, height));
+		assert!(!console.has_room());
+
+		console.write_str("some text\nand more").unwrap();
+		console.erase_char('e');
+		console.newline();
+		console.redraw();
+		for style in [CursorStyle::Block, CursorStyle::Bar, CursorStyle::Underline] {
+			console.set_cursor_style(style);
+			console.draw_cursor(u8::MAX);
+		}
+
+		assert_eq!(mock.lit_count(), 0, "height: {height}");
+	}
+}
+
+/// Feeds `bytes` of log output to a fresh console, returning what it drew.
+fn render_fed(bytes: &[u8]) -> String {
+	let mock = MockVbuf::new(120, LINE_HEIGHT as u64, MockFormat::Grey8);
+	let mut console = TextConsole::new(mock.vbuf(), Rect::of(mock.vbuf()));
+	for &b in bytes {
+		console.feed_byte(b);
+	}
+	console.finish();
+	mock.render()
+}
+
+#[test]
+fn draws_text_in_bold_once_selected() {
+	let plain = render_fed(b"Hello");
+	let bold = render_fed(b"\x1B[1mHello");
+
+	assert_ne!(bold, plain);
+	// Each glyph is emboldened, lighting more of it.
+	let lit = |render: &str| render.chars().filter(|&c| c == '#').count();
+	assert!(lit(&bold) > lit(&plain));
+
+	// Bold is turned back off by SGR 22, or by a reset part way through.
+	assert_eq!(render_fed(b"\x1B[1m\x1B[22mHello"), plain);
+	let mixed = render_fed(b"\x1B[1mHel\x1B[0mlo");
+	assert_ne!(mixed, plain);
+	assert_ne!(mixed, bold);
+}
@@ -0,0 +1,104 @@
+//! Detects other modules drawing to the same video buffer.
+//!
+//! Arbitrating access to the buffer is the kernel's job, but until it
+//! does, two modules drawing to the same buffer will just corrupt each
+//! other's output. To avoid fighting over it, the logger paints a small
+//! pattern into a strip of pixels it owns and periodically checks that
+//! it's still intact; if it keeps getting clobbered, the logger backs off.
+
+use crate::{Rect, Vbuf};
+
+/// The width of the sentinel strip, in pixels.
+pub const SENTINEL_WIDTH: u64 = 8;
+
+/// How often the sentinel is checked, in milliseconds.
+const CHECK_INTERVAL_MS: u64 = 500;
+
+/// How many consecutive checks must find the sentinel clobbered
+/// before the logger backs off.
+///
+/// A single mismatch is tolerated (and the sentinel repainted) so that
+/// a one-off stray write doesn't stop the logger.
+const MAX_STRIKES: u32 = 3;
+
+/// Returns the sentinel's grey level for the pixel at `i` along the strip.
+///
+/// The levels are dark enough to be all but invisible, but irregular
+/// enough that e.g. a full-screen clear or fill is always noticed. They're
+/// multiples of 8 so that they survive being reduced to 5 bits per channel
+/// (with or without dithering) unchanged.
+fn pattern(i: u64) -> u8 {
+	[0x08, 0x18, 0x00, 0x10][(i % 4) as usize]
+}
+
+/// Watches the sentinel strip for modifications made by someone else.
+pub struct ContentionGuard {
+	/// The strip of pixels the sentinel occupies.
+	rect:          Rect,
+	/// When the sentinel was last checked, in milliseconds.
+	last_check_ms: u64,
+	/// How many consecutive checks have found the sentinel clobbered.
+	strikes:       u32,
+}
+
+impl ContentionGuard {
+	/// Creates a new guard, painting the sentinel into `rect`.
+	pub fn new(vbuf: &Vbuf, rect: Rect) -> Self {
+		let guard = Self {
+			rect:          rect.intersect(&Rect::of(vbuf)),
+			last_check_ms: 0,
+			strikes:       0,
+		};
+
+		guard.paint(vbuf);
+		guard
+	}
+
+	/// Paints the sentinel pattern (e.g. again, once the buffer has been
+	/// cleared).
+	pub fn paint(&self, vbuf: &Vbuf) {
+		for (i, x) in (self.rect.x..self.rect.right()).enumerate() {
+			for y in self.rect.y..self.rect.bottom() {
+				vbuf.set_grey_pixel(x, y, pattern(i as u64));
+			}
+		}
+	}
+
+	/// Returns whether the sentinel is exactly as it was painted.
+	fn is_intact(&self, vbuf: &Vbuf) -> bool {
+		(self.rect.x..self.rect.right()).enumerate().all(|(i, x)| {
+			(self.rect.y..self.rect.bottom())
+				.all(|y| vbuf.get_grey_pixel(x, y) == Some(pattern(i as u64)))
+		})
+	}
+
+	/// Checks the sentinel (at most once every [`CHECK_INTERVAL_MS`]).
+	///
+	/// The sentinel is read back from `screen` (the buffer that's actually
+	/// shown), but repainted into `canvas` (the buffer being drawn to, which
+	/// may be a back buffer presented to `screen` later on).
+	///
+	/// Returns `true` once the sentinel has been found clobbered on
+	/// [`MAX_STRIKES`] consecutive checks, at which point the logger
+	/// should stop drawing to the buffer.
+	pub fn check(&mut self, screen: &Vbuf, canvas: &Vbuf, now_ms: u64) -> bool {
+		if now_ms - self.last_check_ms < CHECK_INTERVAL_MS {
+			return false;
+		}
+
+		self.last_check_ms = now_ms;
+
+		if self.is_intact(screen) {
+			self.strikes = 0;
+			return false;
+		}
+
+		self.strikes += 1;
+		self.paint(canvas);
+
+		self.strikes >= MAX_STRIKES
+	}
+}
+
+#[cfg(all(test, feature = "mock-vbuf"))]
+mod tests;
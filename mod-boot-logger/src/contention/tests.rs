@@ -0,0 +1,154 @@
+//! Tests for the contention guard, against a [`MockVbuf`].
+
+use super::{CHECK_INTERVAL_MS, ContentionGuard, MAX_STRIKES, SENTINEL_WIDTH, pattern};
+use crate::{
+	Rect, Vbuf,
+	mock_vbuf::{MockFormat, MockVbuf},
+	vbuf::quantize,
+};
+
+/// Returns the rectangle the sentinel is painted into on `vbuf`.
+fn strip(vbuf: &Vbuf) -> Rect {
+	Rect::new(0, 0, SENTINEL_WIDTH, 1).intersect(&Rect::of(vbuf))
+}
+
+/// Overwrites a pixel of the sentinel, as another module drawing to the
+/// buffer would.
+fn clobber(vbuf: &Vbuf) {
+	vbuf.set_grey_pixel(1, 0, 0xFF);
+}
+
+#[test]
+fn tolerates_an_intact_sentinel() {
+	let mock = MockVbuf::new(16, 4, MockFormat::Xrgb8888);
+	let vbuf = mock.vbuf();
+	let mut guard = ContentionGuard::new(vbuf, strip(vbuf));
+
+	for i in 1..=10 {
+		assert!(!guard.check(vbuf, vbuf, i * CHECK_INTERVAL_MS));
+	}
+}
+
+#[test]
+fn backs_off_after_consecutive_strikes() {
+	let mock = MockVbuf::new(16, 4, MockFormat::Xrgb8888);
+	let vbuf = mock.vbuf();
+	let mut guard = ContentionGuard::new(vbuf, strip(vbuf));
+
+	for strike in 1..=u64::from(MAX_STRIKES) {
+		clobber(vbuf);
+		let backed_off = guard.check(vbuf, vbuf, strike * CHECK_INTERVAL_MS);
+		assert_eq!(
+			backed_off,
+			strike == u64::from(MAX_STRIKES),
+			"strike {strike}"
+		);
+
+		// The sentinel is repainted after each strike.
+		assert_eq!(vbuf.get_grey_pixel(1, 0), Some(pattern(1)));
+	}
+}
+
+#[test]
+fn forgives_strikes_after_an_intact_check() {
+	let mock = MockVbuf::new(16, 4, MockFormat::Xrgb8888);
+	let vbuf = mock.vbuf();
+	let mut guard = ContentionGuard::new(vbuf, strip(vbuf));
+	let mut now = 0;
+	let mut check = |clobbered: bool| {
+		if clobbered {
+			clobber(vbuf);
+		}
+		now += CHECK_INTERVAL_MS;
+		guard.check(vbuf, vbuf, now)
+	};
+
+	for _ in 1..MAX_STRIKES {
+		assert!(!check(true));
+	}
+	assert!(!check(false));
+
+	// The count starts over.
+	for _ in 1..MAX_STRIKES {
+		assert!(!check(true));
+	}
+	assert!(check(true));
+}
+
+#[test]
+fn checks_at_most_once_per_interval() {
+	let mock = MockVbuf::new(16, 4, MockFormat::Xrgb8888);
+	let vbuf = mock.vbuf();
+	let mut guard = ContentionGuard::new(vbuf, strip(vbuf));
+
+	for strike in 1..u64::from(MAX_STRIKES) {
+		clobber(vbuf);
+		assert!(!guard.check(vbuf, vbuf, strike * CHECK_INTERVAL_MS));
+	}
+
+	// Checks made too soon after the last are skipped, so neither sees the
+	// clobbered sentinel (nor repaints it).
+	clobber(vbuf);
+	let last = u64::from(MAX_STRIKES - 1) * CHECK_INTERVAL_MS;
+	assert!(!guard.check(vbuf, vbuf, last + 1));
+	assert!(!guard.check(vbuf, vbuf, last + CHECK_INTERVAL_MS - 1));
+	assert_eq!(vbuf.get_grey_pixel(1, 0), Some(0xFF));
+
+	assert!(guard.check(vbuf, vbuf, last + CHECK_INTERVAL_MS));
+}
+
+#[test]
+fn reads_back_from_the_screen_and_repaints_the_canvas() {
+	let screen = MockVbuf::new(16, 4, MockFormat::Xrgb8888);
+	let canvas = MockVbuf::new(16, 4, MockFormat::Xrgb8888);
+	let mut guard = ContentionGuard::new(screen.vbuf(), strip(screen.vbuf()));
+	guard.paint(canvas.vbuf());
+
+	clobber(screen.vbuf());
+	clobber(canvas.vbuf());
+	assert!(!guard.check(screen.vbuf(), canvas.vbuf(), CHECK_INTERVAL_MS));
+
+	assert_eq!(screen.vbuf().get_grey_pixel(1, 0), Some(0xFF));
+	assert_eq!(canvas.vbuf().get_grey_pixel(1, 0), Some(pattern(1)));
+}
+
+#[test]
+fn sentinel_survives_every_format() {
+	for format in [
+		MockFormat::Grey8,
+		MockFormat::Rgb555,
+		MockFormat::Rgb565,
+		MockFormat::Rgb888,
+		MockFormat::Xrgb8888,
+	] {
+		let mock = MockVbuf::new(16, 4, format);
+		let vbuf = mock.vbuf();
+		let mut guard = ContentionGuard::new(vbuf, strip(vbuf));
+
+		for i in 1..=u64::from(MAX_STRIKES) {
+			assert!(
+				!guard.check(vbuf, vbuf, i * CHECK_INTERVAL_MS),
+				"{format:?}"
+			);
+		}
+	}
+}
+
+#[test]
+fn sentinel_levels_are_unchanged_by_dithering() {
+	for i in 0..SENTINEL_WIDTH {
+		let level = pattern(i);
+
+		for bits in [5, 6] {
+			for y in 0..4 {
+				for x in 0..4 {
+					assert_eq!(
+						quantize(level, bits, x, y, true),
+						quantize(level, bits, x, y, false),
+						"level {level:#x} at {bits} bits"
+					);
+				}
+			}
+		}
+	}
+}
@@ -0,0 +1,113 @@
+//! Reads the root ring's debug output stream.
+
+use oro::{LazyIfaceId, id::iface::ROOT_DEBUG_OUT_V0, key, syscall_get};
+
+/// The root ring debug output interface ID.
+static DEBUG_OUT_IFACE: LazyIfaceId<ROOT_DEBUG_OUT_V0> = LazyIfaceId::new();
+
+/// Returns whether the debug output interface is present.
+pub fn is_available() -> bool {
+	DEBUG_OUT_IFACE.get().is_some()
+}
+
+/// The most words of debug output staged at once.
+const STAGING_WORDS: usize = 64;
+
+/// Reads the debug output a batch of words at a time.
+///
+/// The interface only hands out a word (up to 8 bytes) per query, so
+/// rather than decoding (and drawing) each word as it arrives, the words
+/// are first pulled into a staging buffer in a tight loop, and only then
+/// passed on.
+pub struct DebugReader {
+	/// The words read by the last call to [`Self::fill`].
+	words: [u64; STAGING_WORDS],
+}
+
+impl DebugReader {
+	/// Creates a new debug output reader.
+	pub const fn new() -> Self {
+		Self {
+			words: [0; STAGING_WORDS],
+		}
+	}
+
+	/// Reads as many words as are waiting (up to `max`, which must be at
+	/// most [`STAGING_WORDS`]) into the staging buffer, returning how many
+	/// were read.
+	///
+	/// A zero word means nothing more is waiting.
+	fn fill(&mut self, max: usize) -> usize {
+		let Some(debug_iface) = DEBUG_OUT_IFACE.get() else {
+			return 0;
+		};
+
+		let mut len = 0;
+
+		while len < max {
+			// SAFETY: This is always safe.
+			let Ok(word) =
+				(unsafe { syscall_get!(ROOT_DEBUG_OUT_V0, debug_iface, 0, key!("ring_u64")) })
+			else {
+				break;
+			};
+
+			if word == 0 {
+				break;
+			}
+
+			self.words[len] = word;
+			len += 1;
+		}
+
+		len
+	}
+
+	/// Drains whatever's waiting on the debug output, passing each byte
+	/// of it to `each`.
+	///
+	/// Returns whether anything was read.
+	pub fn drain(&mut self, each: impl FnMut(u8)) -> bool {
+		self.drain_up_to(usize::MAX, each)
+	}
+
+	/// Drains whatever's waiting on the debug output, as with [`Self::drain`],
+	/// but reads at most `max_words` words; the rest is left waiting.
+	pub fn drain_up_to(&mut self, max_words: usize, mut each: impl FnMut(u8)) -> bool {
+		let mut received = false;
+		let mut remaining = max_words;
+
+		while remaining > 0 {
+			let batch = remaining.min(STAGING_WORDS);
+			let len = self.fill(batch);
+			received |= len > 0;
+			remaining -= len;
+
+			for &word in &self.words[..len] {
+				unpack_word(word, &mut each);
+			}
+
+			// A short batch means the stream ran dry.
+			if len < batch {
+				break;
+			}
+		}
+
+		received
+	}
+}
+
+/// Passes each byte packed into a word read from the debug output to `each`.
+///
+/// Bytes are packed into each word from the most significant end; a zero
+/// byte ends the word early.
+pub fn unpack_word(word: u64, mut each: impl FnMut(u8)) {
+	for shift in (0..=(64 - 8)).rev().step_by(8) {
+		let b = ((word >> shift) & 0xFF) as u8;
+		if b == 0 {
+			break;
+		}
+
+		each(b);
+	}
+}
@@ -0,0 +1,220 @@
+//! Implements an on-screen diagnostic overlay, toggled by a sentinel
+//! sequence in the debug output stream.
+//!
+//! This is a poor-man's inspector for debugging on real hardware
+//! without any host tooling.
+
+use core::fmt::Write;
+
+use crate::{
+	LIGHTNESSES, Rect, Vbuf, debug_out, font_rasterizer::LINE_HEIGHT, layout::Layout,
+	packed::Packed2bpp, strbuf::StrBuf, widgets,
+};
+
+/// The sequence in the debug output stream that toggles the overlay.
+///
+/// Formatted as an OSC escape sequence so that it's unlikely to appear
+/// by accident.
+pub const SENTINEL: &[u8] = b"\x1B]oro-diag\x07";
+
+/// How long the overlay stays up before dismissing itself, in milliseconds.
+const OVERLAY_MS: u64 = 5000;
+
+/// The width of the overlay, in pixels.
+const OVERLAY_WIDTH: u64 = 360;
+
+/// The number of rows in the overlay.
+const OVERLAY_ROWS: usize = 5;
+
+/// The size of the square block each sample is expanded to by
+/// [`draw_packed_2bpp`], in pixels.
+pub const PACKED_BLOCK: u64 = 2;
+
+/// Watches a byte stream for a sentinel sequence (e.g. [`SENTINEL`]).
+pub struct SentinelMatcher {
+	/// The sequence being watched for.
+	sentinel: &'static [u8],
+	/// How many bytes of the sentinel have been matched so far.
+	matched:  usize,
+}
+
+impl SentinelMatcher {
+	/// Creates a new matcher for `sentinel`, whose first byte mustn't
+	/// appear anywhere else within it (as with an escape sequence).
+	pub const fn new(sentinel: &'static [u8]) -> Self {
+		Self {
+			sentinel,
+			matched: 0,
+		}
+	}
+
+	/// Feeds a byte to the matcher.
+	///
+	/// Bytes that turn out not to be part of a sentinel are passed on
+	/// to `out` (possibly later than they were fed, since a potential
+	/// sentinel is held back while it's being matched). The sentinel
+	/// itself is swallowed.
+	///
+	/// Returns `true` once a whole sentinel has been matched.
+	pub fn feed(&mut self, b: u8, mut out: impl FnMut(u8)) -> bool {
+		if b == self.sentinel[self.matched] {
+			self.matched += 1;
+			if self.matched == self.sentinel.len() {
+				self.matched = 0;
+				return true;
+			}
+			return false;
+		}
+
+		// Not a sentinel after all; release what was held back. The sentinel's
+		// first byte never appears again within it, so the held back bytes can't
+		// be the start of another sentinel.
+		for &held in &self.sentinel[..self.matched] {
+			out(held);
+		}
+
+		if b == self.sentinel[0] {
+			self.matched = 1;
+		} else {
+			self.matched = 0;
+			out(b);
+		}
+
+		false
+	}
+}
+
+/// Frame timing statistics shown by the overlay.
+pub struct FrameStats {
+	/// The number of frames rendered so far.
+	pub frames:      u64,
+	/// The (nominal) time since the module started, in milliseconds.
+	pub now_ms:      u64,
+	/// The (nominal) duration of each frame, in milliseconds.
+	pub interval_ms: u64,
+}
+
+/// The diagnostic overlay.
+pub struct DiagOverlay {
+	/// When the overlay was shown, if it's currently up.
+	shown_at: Option<u64>,
+	/// Whether the overlay needs to be cleared from the screen.
+	stale:    bool,
+}
+
+impl DiagOverlay {
+	/// Creates a new, hidden diagnostic overlay.
+	pub const fn new() -> Self {
+		Self {
+			shown_at: None,
+			stale:    false,
+		}
+	}
+
+	/// Shows the overlay if it's hidden, or hides it if it's shown.
+	pub fn toggle(&mut self, now_ms: u64) {
+		if self.shown_at.take().is_some() {
+			self.stale = true;
+		} else {
+			self.shown_at = Some(now_ms);
+		}
+	}
+
+	/// Returns the rectangle the overlay occupies on the given buffer.
+	fn rect(vbuf: &Vbuf) -> Rect {
+		let w = OVERLAY_WIDTH.min(vbuf.width);
+		let h = (OVERLAY_ROWS as u64 * LINE_HEIGHT as u64 + widgets::PADDING * 2).min(vbuf.height);
+		Rect::new((vbuf.width - w) / 2, (vbuf.height - h) / 2, w, h)
+	}
+
+	/// Draws the overlay (if it's up), or clears it (if it was just dismissed).
+	///
+	/// Should be called at the end of every frame, so that it's drawn
+	/// over everything else.
+	pub fn draw(&mut self, vbuf: &Vbuf, layout: &Layout, stats: &FrameStats) {
+		if self
+			.shown_at
+			.is_some_and(|shown_at| stats.now_ms - shown_at >= OVERLAY_MS)
+		{
+			self.shown_at = None;
+			self.stale = true;
+		}
+
+		let rect = Self::rect(vbuf);
+
+		if self.stale {
+			vbuf.fill_rect(&rect, 0);
+			self.stale = false;
+		}
+
+		if self.shown_at.is_none() {
+			return;
+		}
+
+		// Formatting errors only ever truncate the values, which is fine here.
+		let mut format = StrBuf::<64>::new();
+		let _ = write!(format, "{}", vbuf.describe());
+
+		let mut logo = StrBuf::<48>::new();
+		let _ = write_rect(&mut logo, &layout.logo);
+
+		let mut text = StrBuf::<48>::new();
+		let _ = write_rect(&mut text, &layout.text);
+
+		let mut frames = StrBuf::<48>::new();
+		let _ = write!(
+			frames,
+			"{} @ {}ms ({}ms up)",
+			stats.frames, stats.interval_ms, stats.now_ms
+		);
+
+		let debug_out = if debug_out::is_available() {
+			"resolved"
+		} else {
+			"missing"
+		};
+
+		let rows = [
+			("vbuf", format.as_str()),
+			("logo", logo.as_str()),
+			("text", text.as_str()),
+			("frames", frames.as_str()),
+			("debug out", debug_out),
+		];
+
+		widgets::draw_kv_table(vbuf, &rect, &rows, 0xFF);
+	}
+}
+
+/// Writes a rectangle in a compact, human-readable form.
+fn write_rect(w: &mut impl Write, rect: &Rect) -> core::fmt::Result {
+	write!(w, "{}x{} at {},{}", rect.w, rect.h, rect.x, rect.y)
+}
+
+/// Draws a packed 2-bit buffer (such as the logo's current frame),
+/// `width` samples wide, with its top-left corner at `(x, y)`.
+///
+/// Each sample is expanded to a [`PACKED_BLOCK`] square of its lightness,
+/// so that the buffer's contents can be inspected directly.
+pub fn draw_packed_2bpp<const BYTES: usize>(
+	vbuf: &Vbuf,
+	x: u64,
+	y: u64,
+	packed: &Packed2bpp<BYTES>,
+	width: usize,
+) {
+	for off in 0..Packed2bpp::<BYTES>::LEN {
+		let lightness = packed.get(off);
+
+		let left = x + (off % width) as u64 * PACKED_BLOCK;
+		let top = y + (off / width) as u64 * PACKED_BLOCK;
+
+		vbuf.fill_rect(
+			&Rect::new(left, top, PACKED_BLOCK, PACKED_BLOCK),
+			LIGHTNESSES[lightness as usize],
+		);
+	}
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,145 @@
+//! Tests for the sentinel matcher and the overlay's drawing.
+
+#[cfg(feature = "mock-vbuf")]
+use super::{PACKED_BLOCK, draw_packed_2bpp};
+use super::{SENTINEL, SentinelMatcher};
+use crate::PANIC_SENTINEL;
+#[cfg(feature = "mock-vbuf")]
+use crate::{
+	LIGHTNESSES, Rect,
+	mock_vbuf::{MockFormat, MockVbuf},
+	packed::Packed2bpp,
+};
+
+/// Feeds each of `drains` to the matcher in turn (as successive drains of
+/// the debug output), returning the bytes passed on and how many
+/// sentinels were matched.
+fn feed_all(matcher: &mut SentinelMatcher, drains: &[&[u8]]) -> (Vec<u8>, usize) {
+	let mut out = Vec::new();
+	let mut found = 0;
+
+	for drain in drains {
+		for &b in *drain {
+			if matcher.feed(b, |b| out.push(b)) {
+				found += 1;
+			}
+		}
+	}
+
+	(out, found)
+}
+
+#[test]
+fn swallows_sentinels() {
+	let mut matcher = SentinelMatcher::new(SENTINEL);
+	let (out, found) = feed_all(&mut matcher, &[b"before\x1B]oro-diag\x07after"]);
+
+	assert_eq!(out, b"beforeafter");
+	assert_eq!(found, 1);
+}
+
+#[test]
+fn releases_partial_matches_in_order() {
+	let mut matcher = SentinelMatcher::new(SENTINEL);
+	let (out, found) = feed_all(&mut matcher, &[b"a\x1B]oro-dix\x1B]orb"]);
+
+	assert_eq!(out, b"a\x1B]oro-dix\x1B]orb");
+	assert_eq!(found, 0);
+}
+
+#[test]
+fn holds_partial_matches_until_they_fail() {
+	let mut matcher = SentinelMatcher::new(SENTINEL);
+	let (out, _) = feed_all(&mut matcher, &[b"a\x1B]oro-d"]);
+	assert_eq!(out, b"a");
+
+	let (out, _) = feed_all(&mut matcher, &[b"x"]);
+	assert_eq!(out, b"\x1B]oro-dx");
+}
+
+#[test]
+fn matches_sentinels_split_across_drains() {
+	let mut matcher = SentinelMatcher::new(SENTINEL);
+	let (out, found) = feed_all(&mut matcher, &[b"one\x1B]or", b"o-di", b"ag\x07two"]);
+
+	assert_eq!(out, b"onetwo");
+	assert_eq!(found, 1);
+}
+
+#[test]
+fn restarts_matching_on_an_escape() {
+	let mut matcher = SentinelMatcher::new(SENTINEL);
+	let (out, found) = feed_all(&mut matcher, &[b"\x1B]oro\x1B]oro-diag\x07"]);
+
+	assert_eq!(out, b"\x1B]oro");
+	assert_eq!(found, 1);
+}
+
+#[test]
+fn passes_ansi_sequences_through() {
+	let mut matcher = SentinelMatcher::new(SENTINEL);
+	let text: &[u8] = b"\x1B[1;31mred\x1B[0m \x1B[2J\x1B[";
+	let (out, found) = feed_all(&mut matcher, &[text, b"K"]);
+
+	assert_eq!(out, b"\x1B[1;31mred\x1B[0m \x1B[2J\x1B[K");
+	assert_eq!(found, 0);
+}
+
+#[test]
+fn nests_the_diagnostic_and_panic_matchers() {
+	// As in the logger's drain loop, the panic matcher sees whatever the
+	// diagnostic matcher passes on. Both sentinels start with `\x1B]oro-`,
+	// so the panic sentinel is first held back by the diagnostic matcher.
+	let mut diag = SentinelMatcher::new(SENTINEL);
+	let mut panic = SentinelMatcher::new(PANIC_SENTINEL);
+	let mut out = Vec::new();
+	let mut toggles = 0;
+	let mut panicked = 0;
+
+	let stream: &[u8] = b"x\x1B]oro-panic\x07y\x1B]oro-diag\x07z\x1B]oro-pa\x1B[0m";
+	for &b in stream {
+		let diag_toggled = diag.feed(b, |b| {
+			if panic.feed(b, |b| out.push(b)) {
+				panicked += 1;
+			}
+		});
+
+		if diag_toggled {
+			toggles += 1;
+		}
+	}
+
+	assert_eq!(out, b"xyz\x1B]oro-pa\x1B[0m");
+	assert_eq!(toggles, 1);
+	assert_eq!(panicked, 1);
+}
+
+#[test]
+#[cfg(feature = "mock-vbuf")]
+fn draws_each_packed_sample_as_a_block_of_its_lightness() {
+	// Two rows of four samples, drawn a pixel in from the corner.
+	let samples = [0, 1, 2, 3, 3, 2, 1, 0];
+	let mut packed = Packed2bpp::<2>::new();
+	for (i, &v) in samples.iter().enumerate() {
+		packed.set(i, v);
+	}
+
+	let block = PACKED_BLOCK;
+	let mock = MockVbuf::new(4 * block + 2, 2 * block + 2, MockFormat::Grey8);
+	let vbuf = mock.vbuf();
+	vbuf.fill_rect(&Rect::of(vbuf), 0x11);
+	draw_packed_2bpp(vbuf, 1, 1, &packed, 4);
+
+	for y in 0..vbuf.height {
+		for x in 0..vbuf.width {
+			let inside = (1..=4 * block).contains(&x) && (1..=2 * block).contains(&y);
+			let expected = if inside {
+				let sample = ((y - 1) / block * 4 + (x - 1) / block) as usize;
+				LIGHTNESSES[samples[sample] as usize]
+			} else {
+				0x11
+			};
+			assert_eq!(mock.raw_pixel(x, y), u32::from(expected), "({x}, {y})");
+		}
+	}
+}
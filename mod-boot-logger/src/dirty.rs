@@ -0,0 +1,106 @@
+//! Tracks which regions of a buffer have been drawn to.
+//!
+//! A single bounding rectangle is a poor fit for the boot screen: the
+//! logo sits in one corner and the line being written in another, so
+//! their bounding box is most of the screen. Instead, a handful of
+//! rectangles are kept, and each modified pixel either grows one of them
+//! or starts a new one.
+
+use core::cell::Cell;
+
+use crate::Rect;
+
+/// The most rectangles tracked at once.
+///
+/// Once they've all been used, new regions are merged into whichever
+/// rectangle they'd grow the least.
+const MAX_RECTS: usize = 8;
+
+/// How close (in pixels) a region must be to an existing rectangle to be
+/// merged into it, rather than starting a new one.
+///
+/// Copying a few untouched pixels is cheaper than fragmenting e.g. a
+/// line of text into a rectangle per glyph.
+const MERGE_DISTANCE: u64 = 8;
+
+/// Accumulates the bounding rectangles of modified pixels.
+pub struct DirtyTracker {
+	/// The tracked rectangles; only the first `len` are meaningful.
+	rects: [Cell<Rect>; MAX_RECTS],
+	/// How many rectangles are in use.
+	len:   Cell<usize>,
+}
+
+impl DirtyTracker {
+	/// Creates a new tracker, with nothing marked.
+	pub const fn new() -> Self {
+		Self {
+			rects: [const { Cell::new(Rect::new(0, 0, 0, 0)) }; MAX_RECTS],
+			len:   Cell::new(0),
+		}
+	}
+
+	/// Marks a region as modified.
+	pub fn mark(&self, rect: &Rect) {
+		if rect.is_empty() {
+			return;
+		}
+
+		let rects = &self.rects[..self.len.get()];
+
+		// Most marks are single pixels landing within (or right next to)
+		// a rectangle that's already tracked.
+		if rects
+			.iter()
+			.any(|tracked| tracked.get().intersect(rect) == *rect)
+		{
+			return;
+		}
+
+		if let Some(near) = rects.iter().find(|tracked| is_near(&tracked.get(), rect)) {
+			near.set(near.get().union(rect));
+			return;
+		}
+
+		if rects.len() < MAX_RECTS {
+			self.rects[rects.len()].set(*rect);
+			self.len.set(rects.len() + 1);
+			return;
+		}
+
+		// Out of rectangles; grow whichever one grows the least.
+		let growth = |tracked: &Cell<Rect>| {
+			let tracked = tracked.get();
+			area(&tracked.union(rect)) - area(&tracked)
+		};
+
+		if let Some(cheapest) = rects.iter().min_by_key(|tracked| growth(tracked)) {
+			cheapest.set(cheapest.get().union(rect));
+		}
+	}
+
+	/// Returns the modified regions, and resets the tracker.
+	///
+	/// Unused entries are empty. The regions may overlap.
+	pub fn take(&self) -> [Rect; MAX_RECTS] {
+		self.len.set(0);
+		core::array::from_fn(|i| self.rects[i].replace(Rect::new(0, 0, 0, 0)))
+	}
+}
+
+/// Returns whether `rect` lies within [`MERGE_DISTANCE`] of `tracked`.
+fn is_near(tracked: &Rect, rect: &Rect) -> bool {
+	let grown = Rect::new(
+		tracked.x.saturating_sub(MERGE_DISTANCE),
+		tracked.y.saturating_sub(MERGE_DISTANCE),
+		tracked.w + MERGE_DISTANCE * 2,
+		tracked.h + MERGE_DISTANCE * 2,
+	);
+
+	grown.overlaps(rect)
+}
+
+/// Returns the number of pixels a rectangle covers.
+fn area(rect: &Rect) -> u64 {
+	rect.w * rect.h
+}
@@ -0,0 +1,513 @@
+//! Holds everything drawn to a single video buffer.
+//!
+//! The log is mirrored to every video buffer the module could map, each
+//! of which may have its own geometry; everything that depends on it (the
+//! layout, the logo's resolution, the console, and so on) is kept per
+//! buffer in a [`Display`].
+
+use core::{
+	marker::PhantomData,
+	ops::{Deref, DerefMut},
+	sync::atomic::{AtomicBool, Ordering},
+};
+
+use crate::{
+	BORDER_RADIUS, DEBUG_LOGO_FRAME, LOGO_PALETTE, LOGO_SHADOW_LEVEL, LOGO_SHADOW_OFFSET,
+	PANIC_TEXT_SCALE, Rect, Vbuf,
+	backbuf::BackBuffer,
+	console::TextConsole,
+	contention::ContentionGuard,
+	diag::{self, DiagOverlay, FrameStats},
+	font_rasterizer,
+	layout::{Config, Layout},
+	logo::{self, Logo},
+	packed::Packed2bpp,
+	prompt::LineEditor,
+	vbuf::Color,
+	watermark::Watermark,
+};
+
+/// The most video buffers that output is mirrored to.
+///
+/// Any further buffers are left alone.
+pub const MAX_DISPLAYS: usize = 4;
+
+/// The displays' memory.
+///
+/// Each display (its console's scrollback and row records, what's behind
+/// its logo, and so on) takes up hundreds of KiB, which is too much to keep on
+/// the stack for every buffer, so they're kept in a fixed static instead.
+/// Like the back buffer's memory, it lives in `.bss`.
+///
+/// The displays borrow the buffers they draw to, which don't live forever;
+/// they're only ever held here for as long as a [`Displays`] (bound to
+/// those borrows) exists, and are dropped along with it.
+static mut DISPLAYS: [Option<Display<'static>>; MAX_DISPLAYS] = [const { None }; MAX_DISPLAYS];
+
+/// Whether [`DISPLAYS`] is held by a [`Displays`].
+static TAKEN: AtomicBool = AtomicBool::new(false);
+
+/// The display set up on each video buffer, kept in static memory (see
+/// [`DISPLAYS`]) rather than on the stack.
+///
+/// Starts out empty, and dereferences to one slot per buffer.
+pub struct Displays<'a> {
+	/// The displays, backed by [`DISPLAYS`].
+	displays: *mut [Option<Display<'a>>; MAX_DISPLAYS],
+	/// Ties the displays to the buffers they borrow.
+	_borrows: PhantomData<[Option<Display<'a>>; MAX_DISPLAYS]>,
+}
+
+impl Displays<'_> {
+	/// Takes hold of the displays' memory.
+	///
+	/// # Panics
+	/// Panics if another [`Displays`] already exists.
+	pub fn new() -> Self {
+		assert!(
+			!TAKEN.swap(true, Ordering::Relaxed),
+			"the displays' memory is already in use"
+		);
+
+		Self {
+			// Only the lifetime differs, which doesn't change the layout.
+			displays: (&raw mut DISPLAYS).cast(),
+			_borrows: PhantomData,
+		}
+	}
+}
+
+impl<'a> Deref for Displays<'a> {
+	type Target = [Option<Display<'a>>; MAX_DISPLAYS];
+
+	fn deref(&self) -> &Self::Target {
+		// SAFETY: The memory is only ever held by one `Displays` at a time.
+		unsafe { &*self.displays }
+	}
+}
+
+impl DerefMut for Displays<'_> {
+	fn deref_mut(&mut self) -> &mut Self::Target {
+		// SAFETY: The memory is only ever held by one `Displays` at a time.
+		unsafe { &mut *self.displays }
+	}
+}
+
+impl Drop for Displays<'_> {
+	fn drop(&mut self) {
+		// The displays mustn't outlive the buffers they borrow.
+		for slot in self.iter_mut() {
+			*slot = None;
+		}
+
+		TAKEN.store(false, Ordering::Relaxed);
+	}
+}
+
+/// The most pixels the logo's box, extended by its shadow, can cover.
+const BACKDROP_PIXELS: usize = (logo::MAX_WIDTH + LOGO_SHADOW_OFFSET as usize)
+	* (logo::MAX_HEIGHT + LOGO_SHADOW_OFFSET as usize);
+
+/// Whatever was drawn under the logo's box (and its shadow) before the
+/// logo was, for the logo to be blended over.
+struct Backdrop {
+	/// The region captured, which lies entirely within the buffer.
+	rect:   Rect,
+	/// The pixels captured, row by row. It's sized for the largest logo, of
+	/// which the chosen one uses the start.
+	pixels: [Color; BACKDROP_PIXELS],
+}
+
+impl Backdrop {
+	/// Captures the pixels of `vbuf` within `rect`, which must lie
+	/// entirely within the buffer.
+	// As with the console's row records, it's built on the stack once per
+	// display, which is then moved into static memory (see `Displays`).
+	#[expect(clippy::large_stack_arrays)]
+	fn capture(vbuf: &Vbuf, rect: Rect) -> Self {
+		let mut pixels = [Color::grey(0); BACKDROP_PIXELS];
+
+		for y in 0..rect.h {
+			for x in 0..rect.w {
+				pixels[(y * rect.w + x) as usize] = vbuf
+					.get_pixel(rect.x + x, rect.y + y)
+					.unwrap_or(Color::grey(0));
+			}
+		}
+
+		Self { rect, pixels }
+	}
+
+	/// Returns the captured pixel at `(x, y)`, which must lie within the
+	/// captured region.
+	fn at(&self, x: u64, y: u64) -> Color {
+		self.pixels[((y - self.rect.y) * self.rect.w + (x - self.rect.x)) as usize]
+	}
+
+	/// Restores the pixel at `(x, y)`, which must lie within the captured
+	/// region, to what it was when captured.
+	fn restore(&self, vbuf: &Vbuf, x: u64, y: u64) {
+		// SAFETY: The captured region is within the buffer bounds.
+		unsafe {
+			vbuf.set_pixel_unchecked(x, y, self.at(x, y));
+		}
+	}
+}
+
+/// The boot screen, as drawn to a single video buffer.
+pub struct Display<'a> {
+	/// The index of the video buffer, for diagnostics.
+	index:       u64,
+	/// The video buffer that's actually shown.
+	screen:      &'a Vbuf,
+	/// The back buffer drawn to in place of `screen`, if there is one.
+	back:        Option<&'a BackBuffer>,
+	/// The buffer everything is drawn to (either `screen` or the back buffer).
+	canvas:      &'a Vbuf,
+	/// How the boot screen is laid out.
+	config:      Config,
+	/// The placement of each element on the buffer.
+	layout:      Layout,
+	/// The logo, at the resolution chosen for the buffer.
+	logo:        Logo,
+	/// The logo's box, clipped to the buffer.
+	logo_clip:   Rect,
+	/// What's behind the logo's box, extended by its shadow (and clipped to
+	/// the buffer), as captured before the logo was first drawn.
+	backdrop:    Backdrop,
+	/// The logo's current frame, as lightness values. Each frame only holds
+	/// the changes since the last, so the logo is drawn again from this as
+	/// a whole whenever it's dimmed further. It's sized for the largest
+	/// logo, of which the chosen one uses the start.
+	frame:       Packed2bpp<{ logo::MAX_PIXELS / 4 }>,
+	/// How far the logo was dimmed when it was last drawn.
+	last_dim:    u8,
+	/// How many frames of the logo have been decoded.
+	logo_frames: u64,
+	/// When the logo was first drawn, in milliseconds, which its frames are
+	/// timed from (it starts over whenever the display is set up afresh).
+	logo_origin: Option<u64>,
+	/// The console the log is drawn to.
+	console:     TextConsole<'a>,
+	/// The boot prompt's line, as echoed to this display's console.
+	prompt:      LineEditor,
+	/// Watches for others drawing to the buffer.
+	contention:  ContentionGuard,
+	/// The diagnostic overlay.
+	diag:        DiagOverlay,
+}
+
+impl<'a> Display<'a> {
+	/// Sets up the boot screen on video buffer `index`, drawing to `back`
+	/// (if given) in place of `screen`, laid out as per `config` (including
+	/// whether it has a border).
+	///
+	/// If given, `watermark` is drawn behind the log text.
+	pub fn new(
+		index: u64,
+		screen: &'a Vbuf,
+		back: Option<&'a BackBuffer>,
+		watermark: Option<&'a Watermark>,
+		config: &Config,
+	) -> Self {
+		// Everything is drawn to the back buffer (if there is one), which is
+		// then presented to the video buffer once per frame.
+		let canvas = back.map_or(screen, BackBuffer::vbuf);
+
+		if config.border {
+			if canvas.width > 6 && canvas.height > 6 {
+				canvas.draw_round_box(
+					3,
+					3,
+					canvas.width - 3,
+					canvas.height - 3,
+					BORDER_RADIUS,
+					0x77,
+				);
+			} else {
+				println!("vbuf {index} is too small for the border; skipping it");
+			}
+		}
+
+		let logo = Logo::for_buffer(canvas);
+		let layout = Layout::new(canvas, &logo, config);
+
+		if !layout.has_logo() {
+			println!(
+				"vbuf {index} is too small for the logo ({}x{}); skipping it",
+				canvas.width, canvas.height
+			);
+		}
+
+		// The logo never draws outside of its own box, nor off of the buffer.
+		let logo_clip = layout.logo.intersect(&Rect::of(canvas));
+		// The shadow (if any) may spill out of the box down and to the right by
+		// its offset (unless there's no room for the logo at all).
+		let shadow_clip = if config.shadow && layout.has_logo() {
+			Rect::new(
+				layout.logo.x,
+				layout.logo.y,
+				logo.width() as u64 + LOGO_SHADOW_OFFSET,
+				logo.height() as u64 + LOGO_SHADOW_OFFSET,
+			)
+			.intersect(&Rect::of(canvas))
+		} else {
+			logo_clip
+		};
+
+		let mut console = TextConsole::new(canvas, layout.text);
+		console.set_scale(font_rasterizer::scale_for_height(canvas.height));
+
+		if !console.has_room() {
+			println!("vbuf {index} has no room for log text; only the logo will be shown");
+		}
+
+		if let Some(watermark) = watermark {
+			// Drawn before any text, which is then composited over it.
+			watermark.draw(canvas);
+			console.set_background(watermark);
+		}
+
+		let contention = ContentionGuard::new(canvas, layout.sentinel);
+
+		// Nothing else draws within the logo's box (nor its shadow), so the
+		// logo is blended over whatever's there by now.
+		let backdrop = Backdrop::capture(canvas, shadow_clip);
+
+		Self {
+			index,
+			screen,
+			back,
+			canvas,
+			config: *config,
+			layout,
+			logo,
+			logo_clip,
+			backdrop,
+			frame: Packed2bpp::new(),
+			last_dim: 0,
+			logo_frames: 0,
+			logo_origin: None,
+			console,
+			prompt: LineEditor::new(),
+			contention,
+			diag: DiagOverlay::new(),
+		}
+	}
+
+	/// Returns the index of the video buffer.
+	pub fn index(&self) -> u64 {
+		self.index
+	}
+
+	/// Returns the frame rate of the logo drawn to this display.
+	pub fn logo_fps(&self) -> u64 {
+		self.logo.fps() as u64
+	}
+
+	/// Returns the console the log is drawn to.
+	pub fn console(&mut self) -> &mut TextConsole<'a> {
+		&mut self.console
+	}
+
+	/// Feeds a typed character to the boot prompt, echoing it to the console.
+	///
+	/// Returns the completed line once enter is pressed.
+	pub fn feed_prompt(&mut self, c: char) -> Option<&str> {
+		self.prompt.feed(c, &mut self.console)
+	}
+
+	/// Replaces the boot screen with the panic screen: the whole buffer is
+	/// cleared, the logo is drawn (still) centered along the top, and a
+	/// fresh console, with larger text, takes up the rest of the screen.
+	///
+	/// The log drawn so far is dropped from the screen; whatever's written
+	/// to [`Self::console`] from then on goes to the panic screen's console.
+	/// The logo isn't animated on the panic screen, so [`Self::draw_logo`]
+	/// mustn't be called after this.
+	pub fn enter_panic(&mut self) {
+		let canvas = self.canvas;
+
+		canvas.fill_rect(&Rect::of(canvas), 0);
+		self.contention.paint(canvas);
+
+		self.layout = Layout::for_panic(canvas, &self.logo, &self.config);
+
+		if self.layout.has_logo() {
+			self.logo
+				.blit(canvas, self.layout.logo.x, self.layout.logo.y);
+		}
+
+		self.console.relocate(self.layout.text);
+		self.console
+			.set_scale(font_rasterizer::scale_for_height(canvas.height) * PANIC_TEXT_SCALE);
+
+		if !self.console.has_room() {
+			println!(
+				"vbuf {} has no room for panic text; only the logo will be shown",
+				self.index
+			);
+		}
+	}
+
+	/// Toggles the diagnostic overlay.
+	pub fn toggle_diag(&mut self, now_ms: u64) {
+		self.diag.toggle(now_ms);
+	}
+
+	/// Draws however many logo frames are due by `now_ms`, dimmed by `dim`
+	/// (`0` being undimmed, and `255` fully faded out).
+	///
+	/// The logo is blended over what's behind it (see [`Backdrop`]), which
+	/// its unlit pixels leave as it was, so dimming it fades it out into
+	/// its surroundings.
+	///
+	/// Returns `false` if the logo ran out of draw commands (which
+	/// shouldn't happen).
+	pub fn draw_logo(&mut self, now_ms: u64, dim: u8) -> bool {
+		let logo_w = self.logo.width();
+		let logo_h = self.logo.height();
+		let left = self.layout.logo.x;
+		let top = self.layout.logo.y;
+		let canvas = self.canvas;
+		let alpha = u8::MAX - dim;
+
+		let plot = |off: usize, lightness: u8| {
+			let x = (off % logo_w) as u64 + left;
+			let y = (off / logo_w) as u64 + top;
+
+			if self.logo_clip.contains(x, y) {
+				self.backdrop.restore(canvas, x, y);
+
+				if lightness != 0 {
+					canvas.blend_pixel(x, y, LOGO_PALETTE[(lightness & 0b11) as usize], alpha);
+				}
+			}
+		};
+
+		// Dimming the logo any further changes every one of its pixels, as
+		// does redrawing its shadow, so those are drawn again as a whole
+		// (below); otherwise, only the pixels that change are drawn.
+		let redraw = dim != self.last_dim || self.config.shadow;
+		let mut logo_changed = false;
+
+		// Decode however many logo frames are due by now, so that the logo
+		// animates at its own rate however long each of our frames takes.
+		// (When frames are slow, the frames in between are still drawn,
+		// since each only holds the changes since the last.)
+		let origin = *self.logo_origin.get_or_insert(now_ms);
+		let logo_frames_due = now_ms.saturating_sub(origin) * self.logo_fps() / 1000 + 1;
+
+		while self.logo_frames < logo_frames_due {
+			let decoded = logo::decode_frame(&mut self.logo, |off, count, lightness| {
+				let Some(lightness) = lightness else {
+					return;
+				};
+
+				logo_changed = true;
+
+				for i in 0..count {
+					self.frame.set(off + i, lightness);
+
+					if !redraw {
+						plot(off + i, lightness);
+					}
+				}
+			});
+
+			if !decoded {
+				return false;
+			}
+
+			self.logo_frames += 1;
+		}
+
+		if dim != self.last_dim || (self.config.shadow && logo_changed) {
+			canvas.mark_damaged(&self.backdrop.rect);
+
+			for off in 0..(logo_w * logo_h) {
+				plot(off, self.frame.get(off));
+			}
+
+			if self.config.shadow {
+				self.draw_shadow(alpha);
+			}
+		}
+
+		self.last_dim = dim;
+
+		if DEBUG_LOGO_FRAME && logo_changed {
+			let height = logo_h as u64 * diag::PACKED_BLOCK;
+			diag::draw_packed_2bpp(
+				canvas,
+				0,
+				canvas.height.saturating_sub(height),
+				&self.frame,
+				logo_w,
+			);
+		}
+
+		true
+	}
+
+	/// Draws the logo's shadow, at the same opacity as the logo, having
+	/// cleared the old one away. The logo must have just been drawn.
+	fn draw_shadow(&self, alpha: u8) {
+		let logo_w = self.logo.width();
+		let logo_h = self.logo.height();
+		let left = self.layout.logo.x;
+		let top = self.layout.logo.y;
+		let canvas = self.canvas;
+		let rect = self.backdrop.rect;
+
+		// The logo has cleared the shadow within its own box; the rest of
+		// it (which the logo's box doesn't cover) is cleared here.
+		for y in rect.y..rect.y + rect.h {
+			for x in rect.x..rect.x + rect.w {
+				if !self.logo_clip.contains(x, y) {
+					self.backdrop.restore(canvas, x, y);
+				}
+			}
+		}
+
+		// Darken every unlit pixel that sits diagonally behind a lit one.
+		for off in 0..(logo_w * logo_h) {
+			if self.frame.get(off) == 0 {
+				continue;
+			}
+
+			let lx = (off % logo_w) as u64 + LOGO_SHADOW_OFFSET;
+			let ly = (off / logo_w) as u64 + LOGO_SHADOW_OFFSET;
+
+			let inside_logo = lx < logo_w as u64 && ly < logo_h as u64;
+			if inside_logo && self.frame.get((ly as usize * logo_w) + lx as usize) != 0 {
+				continue;
+			}
+
+			let x = lx + left;
+			let y = ly + top;
+			if rect.contains(x, y) && self.backdrop.at(x, y).luma() > LOGO_SHADOW_LEVEL {
+				canvas.blend_pixel(x, y, Color::grey(LOGO_SHADOW_LEVEL), alpha);
+			}
+		}
+	}
+
+	/// Draws whatever's overlaid on top of everything else, presents the
+	/// frame (if drawing to a back buffer), and checks for contention.
+	///
+	/// Returns `true` if someone else is drawing to the buffer, at which
+	/// point the logger should stop drawing to it.
+	pub fn finish_frame(&mut self, stats: &FrameStats) -> bool {
+		self.diag.draw(self.canvas, &self.layout, stats);
+
+		if let Some(back) = self.back {
+			self.screen.present(back);
+		}
+
+		self.contention
+			.check(self.screen, self.canvas, stats.now_ms)
+	}
+}
+
+#[cfg(all(test, feature = "mock-vbuf"))]
+mod tests;
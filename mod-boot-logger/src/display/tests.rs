@@ -0,0 +1,204 @@
+//! Tests for the boot screen, against a [`MockVbuf`].
+
+use core::fmt::Write;
+
+use super::{Backdrop, Display};
+use crate::{
+	LOGO_PALETTE, LOGO_SHADOW_LEVEL, LOGO_SHADOW_OFFSET, Rect,
+	layout::{BORDERLESS_MARGIN, CONFIG, Config, GUTTER},
+	mock_vbuf::{MockFormat, MockVbuf},
+	vbuf::Color,
+};
+
+/// A background that isn't black, for the logo to be blended over.
+const BACKGROUND: Color = Color {
+	r: 0x20,
+	g: 0x60,
+	b: 0x30,
+};
+
+#[test]
+fn times_the_logo_from_when_it_was_first_drawn() {
+	let mock = MockVbuf::new(320, 240, MockFormat::Xrgb8888);
+	let mut display = Display::new(0, mock.vbuf(), None, None, &CONFIG);
+
+	// A display set up long after boot (e.g. once its buffer was resized)
+	// starts the logo over, rather than catching up on every frame since.
+	let start_ms = 60 * 60 * 1000;
+	assert!(display.draw_logo(start_ms, 0));
+	assert_eq!(display.logo_frames, 1);
+
+	assert!(display.draw_logo(start_ms + 1000, 0));
+	assert_eq!(display.logo_frames, 1 + display.logo_fps());
+}
+
+#[test]
+fn fades_the_logo_into_the_background() {
+	let mock = MockVbuf::new(320, 240, MockFormat::Xrgb8888);
+	let vbuf = mock.vbuf();
+	vbuf.fill_rect_color(&Rect::of(vbuf), BACKGROUND);
+
+	let mut display = Display::new(0, vbuf, None, None, &CONFIG);
+	assert!(display.layout.has_logo());
+
+	// The logo's first frame lights the start of its top row.
+	let Rect { x, y, .. } = display.layout.logo;
+	let lit = || vbuf.get_pixel(x, y);
+	let unlit = || vbuf.get_pixel(x + 20, y + 20);
+
+	assert!(display.draw_logo(0, u8::MAX));
+	assert_eq!(lit(), Some(BACKGROUND));
+	assert_eq!(unlit(), Some(BACKGROUND));
+
+	assert!(display.draw_logo(0, 0x80));
+	assert_eq!(lit(), Some(LOGO_PALETTE[2].blended_over(BACKGROUND, 0x7F)));
+	assert_eq!(unlit(), Some(BACKGROUND));
+
+	assert!(display.draw_logo(0, 0));
+	assert_eq!(lit(), Some(LOGO_PALETTE[2]));
+	assert_eq!(unlit(), Some(BACKGROUND));
+}
+
+#[test]
+fn draws_nothing_around_the_edge_without_a_border() {
+	for border in [true, false] {
+		let mock = MockVbuf::new(320, 240, MockFormat::Xrgb8888);
+		let vbuf = mock.vbuf();
+		let config = Config { border, ..CONFIG };
+		let display = Display::new(0, vbuf, None, None, &config);
+
+		// Nothing else has been drawn yet, other than the sentinel.
+		let drawn = (0..240)
+			.flat_map(|y| (0..320).map(move |x| (x, y)))
+			.filter(|&(x, y)| mock.is_lit(x, y) && !display.layout.sentinel.contains(x, y))
+			.count();
+		assert_eq!(drawn > 0, border, "border: {border}");
+
+		if !border {
+			let text = display.layout.text;
+			assert_eq!(text.x, BORDERLESS_MARGIN + GUTTER);
+			assert_eq!(text.y, BORDERLESS_MARGIN);
+			assert_eq!(text.bottom(), 240 - BORDERLESS_MARGIN);
+		}
+	}
+}
+
+/// Moves the display's logo to `(x, y)`, as if it had been placed there
+/// (whether or not it fits on the buffer).
+fn move_logo(display: &mut Display<'_>, x: u64, y: u64) {
+	display.layout.logo.x = x;
+	display.layout.logo.y = y;
+	display.logo_clip = display.layout.logo.intersect(&Rect::of(display.canvas));
+	display.backdrop = Backdrop::capture(display.canvas, display.logo_clip);
+}
+
+#[test]
+fn clips_a_logo_overhanging_the_buffer_to_its_box() {
+	let mock = MockVbuf::new(320, 240, MockFormat::Xrgb8888);
+	let vbuf = mock.vbuf();
+	vbuf.fill_rect_color(&Rect::of(vbuf), BACKGROUND);
+
+	let mut display = Display::new(0, vbuf, None, None, &CONFIG);
+	// The logo's first frame lights the first 14 pixels of its top row, the
+	// last 4 of which fall off the right edge (and would otherwise wrap
+	// around to the start of the next row).
+	move_logo(&mut display, 310, 200);
+	let before: Vec<_> = (0..240)
+		.flat_map(|y| (0..320).map(move |x| (x, y)))
+		.map(|(x, y)| vbuf.get_pixel(x, y))
+		.collect();
+
+	// Both while fading in and once faded in.
+	assert!(display.draw_logo(0, 0x80));
+	assert!(display.draw_logo(0, 0));
+
+	assert_eq!(vbuf.get_pixel(319, 200), Some(LOGO_PALETTE[2]));
+	for y in 0..240 {
+		for x in 0..320 {
+			if !display.logo_clip.contains(x, y) {
+				assert_eq!(
+					vbuf.get_pixel(x, y),
+					before[(y * 320 + x) as usize],
+					"({x}, {y})"
+				);
+			}
+		}
+	}
+}
+
+#[test]
+fn casts_the_logo_shadow_at_its_offset() {
+	let white = Color::grey(0xFF);
+	let shade = Color::grey(LOGO_SHADOW_LEVEL);
+
+	for shadow in [true, false] {
+		let mock = MockVbuf::new(320, 240, MockFormat::Xrgb8888);
+		let vbuf = mock.vbuf();
+		vbuf.fill_rect_color(&Rect::of(vbuf), white);
+
+		let config = Config { shadow, ..CONFIG };
+		let mut display = Display::new(0, vbuf, None, None, &config);
+		assert!(display.draw_logo(0, 0));
+
+		// The logo's first frame lights the first 14 pixels of its top row,
+		// so the shadow falls along the row below, shifted right.
+		let Rect { x, y, .. } = display.layout.logo;
+		let (first, last) = (x + LOGO_SHADOW_OFFSET, x + 13 + LOGO_SHADOW_OFFSET);
+		let row = y + LOGO_SHADOW_OFFSET;
+
+		let expected = if shadow { shade } else { white };
+		for sx in first..=last {
+			assert_eq!(vbuf.get_pixel(sx, row), Some(expected), "({sx}, {row})");
+		}
+		for sx in [first - 1, last + 1] {
+			assert_eq!(vbuf.get_pixel(sx, row), Some(white), "({sx}, {row})");
+		}
+		// The lit pixels themselves aren't shaded.
+		assert_eq!(vbuf.get_pixel(x + 13, y), Some(LOGO_PALETTE[2]));
+		assert_eq!(vbuf.get_pixel(last, y), Some(white));
+	}
+}
+
+#[test]
+fn leaves_the_text_beside_the_logo_alone_while_it_fades() {
+	let mock = MockVbuf::new(320, 240, MockFormat::Xrgb8888);
+	let vbuf = mock.vbuf();
+	vbuf.fill_rect_color(&Rect::of(vbuf), BACKGROUND);
+
+	let mut display = Display::new(0, vbuf, None, None, &CONFIG);
+	assert!(display.layout.has_logo());
+	for _ in 0..32 {
+		display
+			.console()
+			.write_str("WWWW WWWW WWWW WWWW WWWW\n")
+			.unwrap();
+	}
+
+	// The text region and the cursor gutter to its left.
+	let text = display.layout.text;
+	let region = Rect::new(
+		text.x.saturating_sub(GUTTER),
+		text.y,
+		text.w + text.x.min(GUTTER),
+		text.h,
+	);
+	let snapshot = || -> Vec<_> {
+		(region.y..region.bottom())
+			.flat_map(|y| (region.x..region.x + region.w).map(move |x| (x, y)))
+			.map(|(x, y)| vbuf.get_pixel(x, y))
+			.collect()
+	};
+	let before = snapshot();
+	assert!(before.iter().any(|&p| p != Some(BACKGROUND)));
+
+	for (now_ms, dim) in [
+		(0, u8::MAX),
+		(100, 0xC0),
+		(200, 0x80),
+		(300, 0x40),
+		(400, 0),
+	] {
+		assert!(display.draw_logo(now_ms, dim));
+		assert!(snapshot() == before, "the text was drawn over at dim {dim}");
+	}
+}
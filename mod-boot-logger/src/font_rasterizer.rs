@@ -1,5 +1,7 @@
 //! Implements the font rasterizer and layout engine.
 
+use crate::{Rect, Vbuf, box_drawing};
+
 /// The font to load and use.
 static FONT_DATA: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/font.bin"));
 
@@ -18,8 +20,75 @@ const _: () = {
 	);
 };
 
-/// The height of each line.
-pub const LINE_HEIGHT: usize = FONT_HEIGHT + 1;
+/// The space between consecutive lines, in pixels.
+const LINE_GAP: usize = 1;
+
+/// The height of each line of text (the font's height, plus the gap
+/// between lines), in pixels.
+pub const LINE_HEIGHT: usize = FONT_HEIGHT + LINE_GAP;
+
+/// The distance from the top of a line to the font's baseline, in pixels.
+pub const ASCENT: usize = FONT_ASCENT;
+
+/// The distance from the font's baseline to the bottom of its glyphs, in pixels.
+///
+/// [`ASCENT`] and [`DESCENT`] always sum to the font's height.
+#[cfg_attr(not(all(test, feature = "mock-vbuf")), expect(dead_code))]
+pub const DESCENT: usize = FONT_DESCENT;
+
+const _: () = {
+	assert!(
+		FONT_ASCENT + FONT_DESCENT == FONT_HEIGHT,
+		"font ascent and descent don't add up to the font height"
+	);
+};
+
+/// The number of fractional bits in advances and positions measured in
+/// subpixels (i.e. they're in 1/256ths of a pixel).
+///
+/// Text is laid out in subpixels, and only floored to whole pixels as
+/// each glyph is placed, so that rounding errors don't build up along a line.
+pub const SUBPIXEL_BITS: u32 = 8;
+
+/// The characters whose glyphs are drawn, in order of preference, in
+/// place of characters that aren't present in the font.
+///
+/// If none of them are present either, a blank glyph [`BLANK_WIDTH`]
+/// pixels wide is drawn instead, so rendering never fails.
+const FALLBACK_CHARS: &[char] = &[char::REPLACEMENT_CHARACTER, '?'];
+
+/// The width, in pixels, of the blank glyph drawn when neither a character
+/// nor any of its fallbacks (see [`FALLBACK_CHARS`]) are present in the font.
+const BLANK_WIDTH: usize = 1;
+
+/// The width, in pixels, of the widest glyph, including the extra pixel
+/// of bold glyphs (see [`render_glyph_bold`]).
+pub const MAX_GLYPH_WIDTH: usize = {
+	let mut max = BLANK_WIDTH;
+	let mut i = 0;
+	while i < FONT_GLYPHS.len() {
+		let width = FONT_GLYPHS[i].2 as usize;
+		if width > max {
+			max = width;
+		}
+		i += 1;
+	}
+	max + 1
+};
+
+/// Looks up the entry for `c` in [`FONT_GLYPHS`].
+fn find_glyph(c: char) -> Option<(u32, u32, u8, u16)> {
+	let index = FONT_GLYPHS
+		.binary_search_by_key(&(c as u32), |&(code_point, ..)| code_point)
+		.ok()?;
+	Some(FONT_GLYPHS[index])
+}
+
+/// Looks up the entry for `c` in [`FONT_GLYPHS`], or else for the first
+/// of its fallbacks (see [`FALLBACK_CHARS`]) that's present.
+fn find_glyph_or_fallback(c: char) -> Option<(u32, u32, u8, u16)> {
+	find_glyph(c).or_else(|| FALLBACK_CHARS.iter().find_map(|&f| find_glyph(f)))
+}
 
 /// Renders a glyph to a linear buffer with the given width and height,
 /// at the given position.
@@ -32,32 +101,373 @@ pub const LINE_HEIGHT: usize = FONT_HEIGHT + 1;
 ///
 /// Returns `None` if the glyph is not present in the font.
 pub fn render_glyph(c: char) -> Option<GlyphIterator> {
-	let offset = FONT_OFFSETS[c as usize];
-	if offset == u32::MAX {
-		return None;
+	let (_, offset, width, _) = find_glyph(c)?;
+	Some(GlyphIterator::new(offset, width))
+}
+
+/// Renders a glyph in (synthesized) bold, as with [`render_glyph`].
+///
+/// The font only has a single weight, so each row of the glyph is
+/// combined with a copy of itself shifted one pixel to the right. The
+/// glyph (and so its advance; see [`advance_bold`]) is a pixel wider.
+pub fn render_glyph_bold(c: char) -> Option<GlyphIterator> {
+	render_glyph(c).map(GlyphIterator::emboldened)
+}
+
+/// Renders a glyph, falling back to the first of [`FALLBACK_CHARS`] that's
+/// present if it's not present in the font (or, failing that, to a blank).
+pub fn render_glyph_or_fallback(c: char) -> GlyphIterator {
+	find_glyph_or_fallback(c).map_or(GlyphIterator::BLANK, |(_, offset, width, _)| {
+		GlyphIterator::new(offset, width)
+	})
+}
+
+/// Renders a glyph in bold, falling back as with [`render_glyph_or_fallback`].
+pub fn render_glyph_bold_or_fallback(c: char) -> GlyphIterator {
+	render_glyph_bold(c)
+		.or_else(|| FALLBACK_CHARS.iter().find_map(|&f| render_glyph_bold(f)))
+		.unwrap_or_else(|| GlyphIterator::BLANK.emboldened())
+}
+
+/// Inclusive code point ranges of East Asian wide and fullwidth characters
+/// (and emoji), which occupy two character cells rather than one.
+const WIDE_RANGES: &[(u32, u32)] = &[
+	(0x1100, 0x115F),   // Hangul Jamo
+	(0x2E80, 0x303E),   // CJK radicals through CJK symbols and punctuation
+	(0x3041, 0x33FF),   // Hiragana through CJK compatibility
+	(0x3400, 0x4DBF),   // CJK unified ideographs extension A
+	(0x4E00, 0x9FFF),   // CJK unified ideographs
+	(0xA000, 0xA4CF),   // Yi syllables and radicals
+	(0xAC00, 0xD7A3),   // Hangul syllables
+	(0xF900, 0xFAFF),   // CJK compatibility ideographs
+	(0xFE30, 0xFE4F),   // CJK compatibility forms
+	(0xFF00, 0xFF60),   // Fullwidth forms
+	(0xFFE0, 0xFFE6),   // Fullwidth signs
+	(0x1F300, 0x1F64F), // Miscellaneous symbols and pictographs, emoticons
+	(0x20000, 0x3FFFD), // CJK unified ideographs extensions B and beyond
+];
+
+/// Returns whether `c` is a wide character, occupying two character cells.
+pub fn is_wide(c: char) -> bool {
+	let c = c as u32;
+	WIDE_RANGES
+		.iter()
+		.any(|&(first, last)| (first..=last).contains(&c))
+}
+
+/// Returns the width of a single (narrow) character cell, in pixels.
+///
+/// The font is proportional, so this is nominal; it's used to give
+/// wide characters a consistent footprint.
+pub fn cell_width() -> usize {
+	usize::from(advance_width('0'))
+}
+
+/// Returns the advance width of `c`'s glyph (or of the fallback glyph,
+/// if the font doesn't have it), in pixels.
+///
+/// This is the glyph's own width; see [`advance`] for how far text
+/// actually advances after it.
+pub fn advance_width(c: char) -> u8 {
+	let width = render_glyph_or_fallback(c).width();
+	// The build script ensures glyphs are at most 255 pixels wide.
+	u8::try_from(width).unwrap()
+}
+
+/// Returns how far, in subpixels (see [`SUBPIXEL_BITS`]), the caret
+/// advances after drawing `c`.
+///
+/// This is the font's (fractional) advance for its glyph, except for wide
+/// characters, which always occupy at least two cells (even if they're
+/// drawn with a narrower fallback glyph) so that they're laid out consistently,
+/// and box-drawing characters (see [`box_drawing`]), which occupy exactly one.
+pub fn advance(c: char) -> usize {
+	if box_drawing::is_synthesized(c) {
+		return box_drawing::advance();
 	}
 
-	let offset = usize::try_from(offset).unwrap();
+	let advance = find_glyph_or_fallback(c)
+		.map_or(BLANK_WIDTH << SUBPIXEL_BITS, |(.., advance)| {
+			usize::from(advance)
+		});
+
+	if is_wide(c) {
+		advance.max((cell_width() * 2) << SUBPIXEL_BITS)
+	} else {
+		advance
+	}
+}
 
-	let next_offset = FONT_OFFSETS
-		.get(c as usize + 1)
-		.copied()
-		.map_or(FONT_DATA_ROW_WIDTH, |o| usize::try_from(o).unwrap());
+/// Returns how far, in subpixels, the caret advances after drawing `c`
+/// in bold (see [`render_glyph_bold`]).
+///
+/// Box-drawing characters aren't emboldened, so they advance as usual.
+pub fn advance_bold(c: char) -> usize {
+	if box_drawing::is_synthesized(c) {
+		return advance(c);
+	}
 
-	Some(GlyphIterator {
-		x_offset: offset,
-		width:    next_offset - offset,
-		offset:   0,
-		total:    FONT_HEIGHT * (next_offset - offset),
-	})
+	advance(c) + (1 << SUBPIXEL_BITS)
+}
+
+/// Returns how many whole pixels a glyph `width` pixels wide is shifted
+/// right by, to center it within its advance (in subpixels).
+///
+/// Only glyphs much narrower than their advance (i.e. wide characters)
+/// are shifted at all.
+pub fn centering_offset(width: usize, advance: usize) -> usize {
+	(advance >> SUBPIXEL_BITS).saturating_sub(width) / 2
+}
+
+/// Returns the kerning adjustment, in pixels, to add to `left`'s advance
+/// when it's followed by `right`.
+///
+/// Only pairs of (printable) ASCII characters are ever kerned. The bundled
+/// font is monospaced and kerns none of them, so this is always `0` unless
+/// a proportional font is baked in its place (see `ORO_BOOT_FONT_PATH`).
+pub fn kerning(left: char, right: char) -> i8 {
+	let (Ok(left), Ok(right)) = (u8::try_from(left), u8::try_from(right)) else {
+		return 0;
+	};
+
+	FONT_KERNING
+		.binary_search_by_key(&(left, right), |&(first, second, _)| (first, second))
+		.map_or(0, |index| FONT_KERNING[index].2)
+}
+
+/// Returns the width, in pixels, that a single line of text
+/// occupies when drawn (including any kerning between its glyphs).
+pub fn measure_text(s: &str) -> usize {
+	let mut width = 0usize;
+	let mut prev = None;
+
+	for c in s.chars() {
+		if let Some(prev) = prev {
+			width = width.saturating_add_signed(isize::from(kerning(prev, c)) << SUBPIXEL_BITS);
+		}
+
+		width += advance(c);
+		prev = Some(c);
+	}
+
+	// Partial pixels still need room.
+	width.div_ceil(1 << SUBPIXEL_BITS)
+}
+
+/// Draws text with its top-left corner at `(x, y)`.
+///
+/// Each newline moves back to `x`, one line further down. Pixels falling
+/// outside of `clip` are discarded. Returns the position immediately after
+/// the last glyph (i.e. where any further text would be drawn).
+///
+/// Consecutive glyphs are kerned (see [`kerning`]), and positioned to
+/// the subpixel (see [`SUBPIXEL_BITS`]).
+pub fn draw_text(vbuf: &Vbuf, x: u64, y: u64, s: &str, level: u8, clip: &Rect) -> (u64, u64) {
+	let clip = clip.intersect(&Rect::of(vbuf));
+	// How far along the line the pen is, in subpixels.
+	let mut pen = 0u64;
+	let mut top = y;
+	let mut prev = None;
+
+	for c in s.chars() {
+		if c == '\n' {
+			pen = 0;
+			top += LINE_HEIGHT as u64;
+			prev = None;
+			continue;
+		}
+
+		if let Some(prev) = prev {
+			pen = pen.saturating_add_signed(i64::from(kerning(prev, c)) << SUBPIXEL_BITS);
+		}
+		prev = Some(c);
+
+		let glyph = render_glyph_or_fallback(c);
+		let advance = advance(c);
+		let left = x + (pen >> SUBPIXEL_BITS) + centering_offset(glyph.width(), advance) as u64;
+
+		for (gx, gy, v) in glyph {
+			let px = left + gx as u64;
+			let py = top + gy as u64;
+			if clip.contains(px, py) {
+				// Glyph coverage scales the level, so the text is
+				// anti-aliased against black.
+				vbuf.set_grey_pixel(px, py, ((u16::from(v) * u16::from(level)) / 255) as u8);
+			}
+		}
+
+		pen += advance as u64;
+	}
+
+	(x + (pen >> SUBPIXEL_BITS), top)
+}
+
+/// The direction in which vertical text is rotated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(not(all(test, feature = "mock-vbuf")), expect(dead_code))]
+pub enum Rotation {
+	/// Rotated 90° clockwise; the text reads from top to bottom.
+	Clockwise,
+	/// Rotated 90° counter-clockwise; the text reads from bottom to top.
+	CounterClockwise,
+}
+
+/// Draws a single line of text rotated by 90°, with the top-left corner
+/// of its (rotated) bounding box at `(x, y)`.
+///
+/// The text is `FONT_HEIGHT` pixels wide and [`measure_text`] pixels tall.
+/// Pixels falling outside of `clip` are discarded. Returns the Y position
+/// immediately below the bounding box.
+#[cfg_attr(not(all(test, feature = "mock-vbuf")), expect(dead_code))]
+pub fn draw_text_vertical(
+	vbuf: &Vbuf,
+	x: u64,
+	y: u64,
+	s: &str,
+	level: u8,
+	rotation: Rotation,
+	clip: &Rect,
+) -> u64 {
+	let clip = clip.intersect(&Rect::of(vbuf));
+	let height = FONT_HEIGHT as u64;
+	let length = measure_text(s) as u64;
+
+	// How far along the text (in its own, unrotated frame) the current
+	// glyph starts, in subpixels.
+	let mut along = 0u64;
+	let mut prev = None;
+
+	for c in s.chars() {
+		if let Some(prev) = prev {
+			along = along.saturating_add_signed(i64::from(kerning(prev, c)) << SUBPIXEL_BITS);
+		}
+		prev = Some(c);
+
+		let glyph = render_glyph_or_fallback(c);
+		let advance = advance(c);
+		let start = (along >> SUBPIXEL_BITS) + centering_offset(glyph.width(), advance) as u64;
+
+		for (gx, gy, v) in glyph {
+			let (gx, gy) = (gx as u64 + start, gy as u64);
+
+			let (px, py) = match rotation {
+				Rotation::Clockwise => (x + height - 1 - gy, y + gx),
+				Rotation::CounterClockwise => (x + gy, y + length - 1 - gx),
+			};
+
+			if clip.contains(px, py) {
+				vbuf.set_grey_pixel(px, py, ((u16::from(v) * u16::from(level)) / 255) as u8);
+			}
+		}
+
+		along += advance as u64;
+	}
+
+	y + length
+}
+
+/// How the lines of a block of text are aligned horizontally.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(not(all(test, feature = "mock-vbuf")), expect(dead_code))]
+pub enum Align {
+	/// Lines start at the left edge, with a single space between words.
+	Left,
+	/// Lines other than the last of each paragraph have their word gaps
+	/// widened so that they span the full width.
+	Justify,
+}
+
+/// Draws a block of text within `rect`, word-wrapping it to the rectangle's width.
+///
+/// Runs of whitespace between words are collapsed to a single space, and
+/// newlines start new paragraphs. Words wider than the rectangle are placed
+/// on their own line and clipped. Lines that don't fit vertically are dropped.
+///
+/// Returns the Y position immediately below the last line drawn.
+#[cfg_attr(not(all(test, feature = "mock-vbuf")), expect(dead_code))]
+pub fn draw_text_block(vbuf: &Vbuf, rect: &Rect, s: &str, level: u8, align: Align) -> u64 {
+	let space = render_glyph_or_fallback(' ').width();
+	let width = rect.w as usize;
+	let mut y = rect.y;
+
+	for paragraph in s.split('\n') {
+		let mut words = paragraph.split_whitespace();
+
+		loop {
+			if y + LINE_HEIGHT as u64 > rect.bottom() {
+				return y;
+			}
+
+			// Find how many words fit on this line (always at least one).
+			let mut count = 0;
+			let mut line_width = 0;
+			for word in words.clone() {
+				let advance = if count == 0 { 0 } else { space } + measure_text(word);
+				if count > 0 && line_width + advance > width {
+					break;
+				}
+				line_width += advance;
+				count += 1;
+			}
+
+			let is_last = words.clone().nth(count).is_none();
+
+			// Only full lines are justified; a single word has no gaps to widen.
+			let gaps = count.saturating_sub(1);
+			let extra = if align == Align::Justify && !is_last && gaps > 0 {
+				width - line_width
+			} else {
+				0
+			};
+
+			let mut x = rect.x;
+			for (i, word) in words.by_ref().take(count).enumerate() {
+				if i > 0 {
+					// Spread the extra space as evenly as possible, giving
+					// any remainder to the leftmost gaps.
+					let share = extra / gaps + usize::from(i - 1 < extra % gaps);
+					x += (space + share) as u64;
+				}
+
+				// Words are spaced by their measured widths (as the line was
+				// laid out), so that justified lines end flush with the edge.
+				draw_text(vbuf, x, y, word, level, rect);
+				x += measure_text(word) as u64;
+			}
+
+			y += LINE_HEIGHT as u64;
+
+			if is_last {
+				break;
+			}
+		}
+	}
+
+	y
+}
+
+/// A glyph's pixels, which may be read in any order.
+///
+/// Glyphs are always [`FONT_HEIGHT`] pixels tall.
+pub trait Glyph {
+	/// Returns the width of the glyph, in pixels.
+	fn width(&self) -> usize;
+
+	/// Returns the value of the glyph's pixel at `(x, y)`.
+	fn pixel(&self, x: usize, y: usize) -> u8;
 }
 
 /// Iterates over the pixels of a glyph.
 pub struct GlyphIterator {
 	/// The X offset for each row in the glyph data.
 	x_offset: usize,
-	/// The width of the glyph, in pixels.
+	/// The width of the glyph in the font data, in pixels.
 	width:    usize,
+	/// Whether the glyph is emboldened (see [`render_glyph_bold`]).
+	bold:     bool,
+	/// Whether the glyph is the blank drawn when even the fallbacks are
+	/// missing (see [`FALLBACK_CHARS`]), which has no font data.
+	blank:    bool,
 	/// The current offset into the glyph data (absolute).
 	offset:   usize,
 	/// The total number of pixels in the glyph.
@@ -65,9 +475,61 @@ pub struct GlyphIterator {
 }
 
 impl GlyphIterator {
-	/// Returns the width of the glyph.
-	pub fn width(&self) -> usize {
-		self.width
+	/// The blank glyph (see [`BLANK_WIDTH`]).
+	const BLANK: Self = Self {
+		x_offset: 0,
+		width:    BLANK_WIDTH,
+		bold:     false,
+		blank:    true,
+		offset:   0,
+		total:    FONT_HEIGHT * BLANK_WIDTH,
+	};
+
+	/// Creates an iterator over the glyph at `offset` in the font data,
+	/// `width` pixels wide.
+	fn new(offset: u32, width: u8) -> Self {
+		let width = usize::from(width);
+
+		Self {
+			x_offset: usize::try_from(offset).unwrap(),
+			width,
+			bold: false,
+			blank: false,
+			offset: 0,
+			total: FONT_HEIGHT * width,
+		}
+	}
+
+	/// Returns the glyph in bold, one pixel wider.
+	fn emboldened(self) -> Self {
+		Self {
+			bold: true,
+			total: FONT_HEIGHT * (self.width + 1),
+			..self
+		}
+	}
+}
+
+impl Glyph for GlyphIterator {
+	fn width(&self) -> usize {
+		self.width + usize::from(self.bold)
+	}
+
+	fn pixel(&self, x: usize, y: usize) -> u8 {
+		let raw = |x: usize| {
+			if x < self.width && !self.blank {
+				FONT_DATA[self.x_offset + y * FONT_DATA_ROW_WIDTH + x]
+			} else {
+				0
+			}
+		};
+
+		if self.bold {
+			// Each pixel is combined with the one to its left.
+			raw(x).max(x.checked_sub(1).map_or(0, raw))
+		} else {
+			raw(x)
+		}
 	}
 }
 
@@ -79,11 +541,72 @@ impl Iterator for GlyphIterator {
 			return None;
 		}
 
-		let x = self.offset % self.width;
-		let y = self.offset / self.width;
+		let x = self.offset % self.width();
+		let y = self.offset / self.width();
 
-		let byte = FONT_DATA[self.x_offset + y * FONT_DATA_ROW_WIDTH + x];
 		self.offset += 1;
-		Some((x, y, byte))
+		Some((x, y, self.pixel(x, y)))
 	}
 }
+
+/// Iterates over the pixels of a glyph scaled up by an integer factor,
+/// using nearest-neighbor scaling (so each pixel becomes a square block).
+///
+/// Yields the same `(x, y, value)` triples as [`GlyphIterator`], in the
+/// scaled glyph's coordinates.
+pub struct ScaledGlyph<G> {
+	/// The glyph being scaled.
+	glyph:  G,
+	/// The scale factor.
+	scale:  usize,
+	/// The current offset into the scaled glyph.
+	offset: usize,
+}
+
+impl<G: Glyph> ScaledGlyph<G> {
+	/// Scales a glyph up by `scale` (which is taken to be at least 1).
+	pub fn new(glyph: G, scale: usize) -> Self {
+		Self {
+			glyph,
+			scale: scale.max(1),
+			offset: 0,
+		}
+	}
+
+	/// Returns the width of the scaled glyph.
+	pub fn width(&self) -> usize {
+		self.glyph.width() * self.scale
+	}
+}
+
+impl<G: Glyph> Iterator for ScaledGlyph<G> {
+	type Item = (usize, usize, u8);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.offset >= self.width() * FONT_HEIGHT * self.scale {
+			return None;
+		}
+
+		let x = self.offset % self.width();
+		let y = self.offset / self.width();
+
+		self.offset += 1;
+		Some((x, y, self.glyph.pixel(x / self.scale, y / self.scale)))
+	}
+}
+
+/// The buffer height, in pixels, per step of the default text scale
+/// (see [`scale_for_height`]).
+const SCALE_STEP_HEIGHT: u64 = 1000;
+
+/// Returns the default text scale for a buffer of the given height.
+///
+/// Text is drawn at its natural size on buffers up to (just under) 2000
+/// pixels tall, at double size on e.g. 4K buffers, and so on, so that it
+/// stays legible on high-density displays.
+pub fn scale_for_height(height: u64) -> usize {
+	(height / SCALE_STEP_HEIGHT).max(1) as usize
+}
+
+#[cfg(test)]
+mod tests;
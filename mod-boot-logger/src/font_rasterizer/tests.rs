@@ -0,0 +1,156 @@
+//! Tests for the glyph rasterizer.
+
+#[cfg(feature = "mock-vbuf")]
+use super::{
+	Align, FONT_HEIGHT, LINE_HEIGHT, Rotation, cell_width, draw_text, draw_text_block,
+	draw_text_vertical, measure_text,
+};
+use super::{Glyph, render_glyph, render_glyph_bold};
+#[cfg(feature = "mock-vbuf")]
+use crate::{
+	Rect,
+	mock_vbuf::{MockFormat, MockVbuf},
+};
+
+/// Returns the coordinates of each pixel of a glyph with any coverage.
+fn covered(glyph: &impl Glyph) -> Vec<(usize, usize)> {
+	(0..super::FONT_HEIGHT)
+		.flat_map(|y| (0..glyph.width()).map(move |x| (x, y)))
+		.filter(|&(x, y)| glyph.pixel(x, y) > 0)
+		.collect()
+}
+
+#[test]
+fn bold_glyphs_cover_more_than_regular_ones() {
+	for c in ['H', 'i', 'm', '0', '#'] {
+		let regular = render_glyph(c).unwrap();
+		let bold = render_glyph_bold(c).unwrap();
+
+		assert_eq!(bold.width(), regular.width() + 1, "{c:?}");
+
+		let regular = covered(&regular);
+		let bold = covered(&bold);
+		assert!(bold.len() > regular.len(), "{c:?}");
+
+		// The regular glyph is still all there, as is a copy of it shifted
+		// a pixel to the right.
+		for &(x, y) in &regular {
+			assert!(bold.contains(&(x, y)), "{c:?} lost ({x}, {y})");
+			assert!(bold.contains(&(x + 1, y)), "{c:?} lacks ({}, {y})", x + 1);
+		}
+	}
+}
+
+#[cfg(feature = "mock-vbuf")]
+#[test]
+fn rotates_vertical_text_either_way() {
+	let text = "Oro!";
+	let length = measure_text(text) as u64;
+	let height = FONT_HEIGHT as u64;
+
+	let flat = MockVbuf::new(length, height, MockFormat::Grey8);
+	draw_text(flat.vbuf(), 0, 0, text, 0xFF, &Rect::of(flat.vbuf()));
+
+	let clockwise = MockVbuf::new(height, length, MockFormat::Grey8);
+	let below = draw_text_vertical(
+		clockwise.vbuf(),
+		0,
+		0,
+		text,
+		0xFF,
+		Rotation::Clockwise,
+		&Rect::of(clockwise.vbuf()),
+	);
+	assert_eq!(below, length);
+
+	let counter = MockVbuf::new(height, length, MockFormat::Grey8);
+	draw_text_vertical(
+		counter.vbuf(),
+		0,
+		0,
+		text,
+		0xFF,
+		Rotation::CounterClockwise,
+		&Rect::of(counter.vbuf()),
+	);
+
+	assert!(flat.lit_count() > 0);
+	for y in 0..height {
+		for x in 0..length {
+			let pixel = flat.raw_pixel(x, y);
+			assert_eq!(clockwise.raw_pixel(height - 1 - y, x), pixel, "({x}, {y})");
+			assert_eq!(counter.raw_pixel(y, length - 1 - x), pixel, "({x}, {y})");
+		}
+	}
+}
+
+/// Draws `lines` of words with `draw_text`, one after the other, each
+/// word starting at the given X position.
+#[cfg(feature = "mock-vbuf")]
+fn draw_lines(mock: &MockVbuf, lines: &[&[(u64, &str)]]) {
+	let clip = Rect::of(mock.vbuf());
+
+	for (row, words) in lines.iter().enumerate() {
+		for &(x, word) in *words {
+			draw_text(
+				mock.vbuf(),
+				x,
+				(row * LINE_HEIGHT) as u64,
+				word,
+				0xFF,
+				&clip,
+			);
+		}
+	}
+}
+
+#[cfg(feature = "mock-vbuf")]
+#[test]
+fn wraps_text_blocks_at_word_boundaries() {
+	let cell = cell_width() as u64;
+	let space = render_glyph(' ').unwrap().width() as u64;
+	let rect = Rect::new(0, 0, cell * 6, LINE_HEIGHT as u64 * 3);
+
+	let block = MockVbuf::new(rect.w, rect.h, MockFormat::Grey8);
+	let below = draw_text_block(block.vbuf(), &rect, "aa  bb cc\ndd", 0xFF, Align::Left);
+	assert_eq!(below, LINE_HEIGHT as u64 * 3);
+
+	let expected = MockVbuf::new(rect.w, rect.h, MockFormat::Grey8);
+	let bb = measure_text("aa") as u64 + space;
+	draw_lines(
+		&expected,
+		&[&[(0, "aa"), (bb, "bb")], &[(0, "cc")], &[(0, "dd")]],
+	);
+
+	assert_eq!(block.render(), expected.render());
+}
+
+#[cfg(feature = "mock-vbuf")]
+#[test]
+fn justifies_all_but_the_last_line_of_each_paragraph() {
+	let cell = cell_width() as u64;
+	let rect = Rect::new(0, 0, cell * 6, LINE_HEIGHT as u64 * 2);
+
+	let block = MockVbuf::new(rect.w, rect.h, MockFormat::Grey8);
+	draw_text_block(block.vbuf(), &rect, "aa bb cc", 0xFF, Align::Justify);
+
+	let expected = MockVbuf::new(rect.w, rect.h, MockFormat::Grey8);
+	let bb = rect.w - measure_text("bb") as u64;
+	draw_lines(&expected, &[&[(0, "aa"), (bb, "bb")], &[(0, "cc")]]);
+
+	assert_eq!(block.render(), expected.render());
+}
+
+#[cfg(feature = "mock-vbuf")]
+#[test]
+fn drops_lines_that_do_not_fit_the_block() {
+	let cell = cell_width() as u64;
+	let rect = Rect::new(0, 0, cell * 6, LINE_HEIGHT as u64 + 1);
+
+	let block = MockVbuf::new(rect.w, LINE_HEIGHT as u64 * 2, MockFormat::Grey8);
+	let below = draw_text_block(block.vbuf(), &rect, "aa bb cc", 0xFF, Align::Left);
+	assert_eq!(below, LINE_HEIGHT as u64);
+
+	let line = LINE_HEIGHT as u64;
+	assert!((line..line * 2).all(|y| (0..rect.w).all(|x| !block.is_lit(x, y))));
+}
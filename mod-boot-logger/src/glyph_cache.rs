@@ -0,0 +1,138 @@
+//! Implements a small cache of rasterized glyphs.
+//!
+//! The font data is a single strip of every glyph laid side by side, so
+//! consecutive rows of a glyph are a whole strip apart, and reading one
+//! touches a different part of the font data for every row. The console
+//! draws the same few characters over and over (and redraws whole rows
+//! at once), so it keeps contiguous copies of the glyphs it draws.
+
+use crate::font_rasterizer::{self, FONT_HEIGHT, Glyph, MAX_GLYPH_WIDTH};
+
+/// The number of glyphs the cache holds.
+///
+/// Once full, each new glyph replaces the least recently used one.
+const CACHE_SIZE: usize = 64;
+
+/// The number of pixels set aside for each cached glyph.
+const ENTRY_PIXELS: usize = FONT_HEIGHT * MAX_GLYPH_WIDTH;
+
+/// A single cached glyph.
+#[derive(Clone, Copy)]
+struct Entry {
+	/// The character the glyph was rasterized for.
+	c:         char,
+	/// Whether the glyph is in bold.
+	bold:      bool,
+	/// The width of the glyph, in pixels.
+	width:     usize,
+	/// When the glyph was last looked up, in lookups since the cache was created.
+	last_used: u64,
+	/// The glyph's pixels, row by row, `width` pixels to a row.
+	pixels:    [u8; ENTRY_PIXELS],
+}
+
+impl Entry {
+	/// An unused entry.
+	const EMPTY: Self = Self {
+		c:         '\0',
+		bold:      false,
+		width:     0,
+		last_used: 0,
+		pixels:    [0; ENTRY_PIXELS],
+	};
+}
+
+/// A fixed-size cache of rasterized glyphs, keyed by character (and
+/// whether it's in bold).
+pub struct GlyphCache {
+	/// The cached glyphs, of which the first `len` are in use.
+	entries: [Entry; CACHE_SIZE],
+	/// The number of entries in use.
+	len:     usize,
+	/// The number of lookups made, used to find the least recently used entry.
+	uses:    u64,
+}
+
+impl GlyphCache {
+	/// Creates a new, empty glyph cache.
+	pub const fn new() -> Self {
+		Self {
+			entries: [Entry::EMPTY; CACHE_SIZE],
+			len:     0,
+			uses:    0,
+		}
+	}
+
+	/// Returns the index of the glyph for `c` (in bold, if `bold`),
+	/// rasterizing it if it's not already cached.
+	///
+	/// The index remains valid until the next lookup.
+	pub fn load(&mut self, c: char, bold: bool) -> usize {
+		self.uses += 1;
+
+		if let Some(i) = self.entries[..self.len]
+			.iter()
+			.position(|e| e.c == c && e.bold == bold)
+		{
+			self.entries[i].last_used = self.uses;
+			return i;
+		}
+
+		let i = if self.len < CACHE_SIZE {
+			self.len += 1;
+			self.len - 1
+		} else {
+			(0..CACHE_SIZE)
+				.min_by_key(|&i| self.entries[i].last_used)
+				.unwrap_or(0)
+		};
+
+		let glyph = if bold {
+			font_rasterizer::render_glyph_bold_or_fallback(c)
+		} else {
+			font_rasterizer::render_glyph_or_fallback(c)
+		};
+		let width = glyph.width();
+
+		let entry = &mut self.entries[i];
+		entry.c = c;
+		entry.bold = bold;
+		entry.width = width;
+		entry.last_used = self.uses;
+
+		for y in 0..FONT_HEIGHT {
+			for x in 0..width {
+				entry.pixels[y * width + x] = glyph.pixel(x, y);
+			}
+		}
+
+		i
+	}
+
+	/// Returns the cached glyph at `index`, as returned by [`Self::load`].
+	pub fn glyph(&self, index: usize) -> CachedGlyph<'_> {
+		let entry = &self.entries[index];
+		CachedGlyph {
+			width:  entry.width,
+			pixels: &entry.pixels[..FONT_HEIGHT * entry.width],
+		}
+	}
+}
+
+/// A glyph held by a [`GlyphCache`].
+pub struct CachedGlyph<'a> {
+	/// The width of the glyph, in pixels.
+	width:  usize,
+	/// The glyph's pixels, row by row.
+	pixels: &'a [u8],
+}
+
+impl Glyph for CachedGlyph<'_> {
+	fn width(&self) -> usize {
+		self.width
+	}
+
+	fn pixel(&self, x: usize, y: usize) -> u8 {
+		self.pixels[y * self.width + x]
+	}
+}
@@ -0,0 +1,55 @@
+//! Echoes the log when there's no video buffer to draw it to.
+//!
+//! On serial-only boots (or once every buffer has been lost to contention),
+//! the logger still drains the root ring's debug output, and echoes it via
+//! [`println!`] so that the logs keep flowing.
+
+use core::fmt::Write;
+
+use crate::{LINE_FLUSH_MS, clock, debug_out::DebugReader, strbuf::StrBuf, utf8::Utf8Decoder};
+
+/// How often the debug output is polled, in milliseconds.
+const POLL_INTERVAL_MS: u64 = 50;
+
+/// The longest line echoed at once, in bytes.
+///
+/// Longer lines are split.
+const MAX_LINE: usize = 256;
+
+/// Echoes the root ring's debug output, line by line, forever.
+pub fn run() -> ! {
+	let mut debug_out = DebugReader::new();
+	let mut decoder = Utf8Decoder::new();
+	let mut line = StrBuf::<MAX_LINE>::new();
+	let mut idle_ms: u64 = 0;
+
+	loop {
+		let received = debug_out.drain(|b| {
+			decoder.push(b, |c| {
+				if c == '\n' {
+					println!("{}", line.as_str());
+					line.clear();
+				} else if line.write_char(c).is_err() {
+					println!("{}", line.as_str());
+					line.clear();
+					// A single character always fits in an empty line.
+					let _ = line.write_char(c);
+				}
+			});
+		});
+
+		if received {
+			idle_ms = 0;
+		} else {
+			idle_ms += POLL_INTERVAL_MS;
+		}
+
+		// As with the console, an incomplete line isn't held back forever.
+		if idle_ms >= LINE_FLUSH_MS && !line.is_empty() {
+			println!("{}", line.as_str());
+			line.clear();
+		}
+
+		clock::sleep_ms(POLL_INTERVAL_MS);
+	}
+}
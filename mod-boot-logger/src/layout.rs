@@ -0,0 +1,387 @@
+//! Computes where each element of the boot screen is placed.
+
+use crate::{DRAW_BORDER, LOGO_SHADOW, LOGO_SHADOW_OFFSET, Rect, Vbuf, contention, logo::Logo};
+
+/// The layout preset to use (see [`CONFIG`]).
+const PRESET: Preset = Preset::Full;
+
+/// Where the logo is placed when using [`Preset::Full`] (see [`CONFIG`]).
+const LOGO_PLACEMENT: LogoPlacement = LogoPlacement::BottomRight;
+
+/// The width of the log panel when using [`Preset::SidePanel`], in pixels.
+const PANEL_WIDTH: u64 = 320;
+
+/// Which edge of the screen the log panel sits on when using [`Preset::SidePanel`].
+const PANEL_SIDE: Side = Side::Right;
+
+/// The layout of the boot screen.
+pub const CONFIG: Config = Config {
+	preset:      PRESET,
+	placement:   LOGO_PLACEMENT,
+	panel_width: PANEL_WIDTH,
+	panel_side:  PANEL_SIDE,
+	border:      DRAW_BORDER,
+	shadow:      LOGO_SHADOW,
+};
+
+/// The margin between the elements of the screen, in pixels.
+const MARGIN: u64 = 5;
+
+/// The margin between the edge of the screen and its contents when no
+/// border is drawn, in pixels.
+pub const BORDERLESS_MARGIN: u64 = 0;
+
+/// The space left of the text region for the cursor, in pixels.
+pub const GUTTER: u64 = 10;
+
+/// How the boot screen is laid out (see [`Layout::new`]).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Config {
+	/// The layout preset.
+	pub preset:      Preset,
+	/// Where the logo is placed when using [`Preset::Full`].
+	pub placement:   LogoPlacement,
+	/// The width of the log panel when using [`Preset::SidePanel`], in pixels.
+	pub panel_width: u64,
+	/// Which edge of the screen the log panel sits on when using
+	/// [`Preset::SidePanel`].
+	pub panel_side:  Side,
+	/// Whether a border is drawn around the edge of the screen (see
+	/// [`crate::display::Display::new`]).
+	pub border:      bool,
+	/// Whether the logo has a drop shadow, which its box is extended by
+	/// (see [`LOGO_SHADOW_OFFSET`]).
+	pub shadow:      bool,
+}
+
+impl Config {
+	/// Returns the margin between the edge of the screen and its contents,
+	/// in pixels.
+	///
+	/// When the border is drawn, this leaves room for it.
+	pub const fn edge_margin(&self) -> u64 {
+		if self.border {
+			MARGIN
+		} else {
+			BORDERLESS_MARGIN
+		}
+	}
+}
+
+/// A layout preset.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(not(all(test, feature = "mock-vbuf")), expect(dead_code))]
+pub enum Preset {
+	/// The logo is placed as per [`LOGO_PLACEMENT`], and the log fills
+	/// the rest of the screen.
+	Full,
+	/// The log is confined to a fixed-width panel on one edge of the
+	/// screen, with the logo centered in the remaining space.
+	SidePanel,
+}
+
+/// Where the logo is placed on the screen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(not(all(test, feature = "mock-vbuf")), expect(dead_code))]
+pub enum LogoPlacement {
+	/// In the bottom-right corner, with the log to its left.
+	BottomRight,
+	/// In the middle of the screen, with the log below it.
+	Center,
+	/// In the top-left corner, with the log to its right.
+	TopLeft,
+}
+
+/// An edge of the screen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(not(all(test, feature = "mock-vbuf")), expect(dead_code))]
+pub enum Side {
+	/// The left edge.
+	Left,
+	/// The right edge.
+	Right,
+}
+
+/// The placement of each element of the boot screen.
+///
+/// The logo and text regions never overlap; the logo owns its box
+/// (including its drop shadow, if any) and the text region, along with
+/// the cursor gutter to its left, is trimmed so that it never enters it.
+/// This way the logo (drawn first each frame) and the text (drawn after)
+/// never overwrite one another, even while the logo fades in.
+///
+/// The text region is likewise kept clear of the contention sentinel.
+pub struct Layout {
+	/// The box the logo is drawn within.
+	pub logo:     Rect,
+	/// The region the log text is drawn within.
+	pub text:     Rect,
+	/// The strip of pixels used to detect other modules drawing to
+	/// the buffer (see [`crate::contention`]).
+	pub sentinel: Rect,
+}
+
+impl Layout {
+	/// Computes the layout for the given video buffer and logo, as
+	/// configured by `config`.
+	///
+	/// If the buffer is too small to fit the logo (along with its margins),
+	/// the logo's box is left empty (see [`Self::has_logo`]).
+	pub fn new(vbuf: &Vbuf, logo: &Logo, config: &Config) -> Self {
+		Self::from_preset(vbuf, logo, config).resolve(vbuf, config)
+	}
+
+	/// Computes the layout of the panic screen (see
+	/// [`crate::display::Display::enter_panic`]) for the given video buffer
+	/// and logo: the logo centered along the top, with the panic text
+	/// filling the rest of the screen below it.
+	///
+	/// As with [`Self::new`], the logo's box is left empty if there's no
+	/// room for it. Only the border is taken from `config`.
+	pub fn for_panic(vbuf: &Vbuf, logo: &Logo, config: &Config) -> Self {
+		let logo_w = logo.width() as u64;
+		let logo_h = logo.height() as u64;
+		let edge = config.edge_margin();
+
+		// Without room for the logo, the text moves up into its place.
+		let (logo_w, logo_h) = if logo_fits(vbuf, logo_w, logo_h, edge) {
+			(logo_w, logo_h)
+		} else {
+			(0, 0)
+		};
+
+		let logo = Rect::new(
+			vbuf.width.saturating_sub(logo_w) / 2,
+			edge + MARGIN,
+			logo_w,
+			logo_h,
+		);
+		let text_top = logo.bottom() + MARGIN;
+
+		Self {
+			logo,
+			text: Rect::new(
+				edge + GUTTER,
+				text_top,
+				vbuf.width.saturating_sub(edge * 2 + GUTTER),
+				vbuf.height.saturating_sub(text_top + edge),
+			),
+			sentinel: Self::sentinel(vbuf),
+		}
+		.resolve(vbuf, config)
+	}
+
+	/// Drops the logo if it doesn't fit on the buffer, and trims the text
+	/// region (and its cursor gutter) so that it stays clear of the logo
+	/// and the sentinel.
+	fn resolve(mut self, vbuf: &Vbuf, config: &Config) -> Self {
+		if !logo_fits(vbuf, self.logo.w, self.logo.h, config.edge_margin()) {
+			self.logo = Rect::new(0, 0, 0, 0);
+		}
+
+		let shadow = if config.shadow { LOGO_SHADOW_OFFSET } else { 0 };
+		let reserved = Rect::new(
+			self.logo.x,
+			self.logo.y,
+			self.logo.w + shadow,
+			self.logo.h + shadow,
+		);
+
+		for reserved in [reserved, self.sentinel] {
+			let with_gutter = Rect::new(
+				self.text.x.saturating_sub(GUTTER),
+				self.text.y,
+				self.text.w + self.text.x.min(GUTTER),
+				self.text.h,
+			);
+
+			if with_gutter.overlaps(&reserved) {
+				let carved = carve(&with_gutter, &reserved);
+				let gutter = carved.w.min(GUTTER);
+				self.text = Rect::new(carved.x + gutter, carved.y, carved.w - gutter, carved.h);
+			}
+		}
+
+		self
+	}
+
+	/// Returns whether there's room for the logo.
+	pub fn has_logo(&self) -> bool {
+		!self.logo.is_empty()
+	}
+
+	/// Returns the strip the sentinel occupies, which sits along the very
+	/// top of the top-right corner, well away from the cursor gutter.
+	fn sentinel(vbuf: &Vbuf) -> Rect {
+		Rect::new(
+			vbuf.width.saturating_sub(contention::SENTINEL_WIDTH),
+			0,
+			contention::SENTINEL_WIDTH.min(vbuf.width),
+			1.min(vbuf.height),
+		)
+	}
+
+	/// Computes the layout for the given video buffer from the configured
+	/// preset, without resolving overlaps.
+	fn from_preset(vbuf: &Vbuf, logo: &Logo, config: &Config) -> Self {
+		let sentinel = Self::sentinel(vbuf);
+		let edge = config.edge_margin();
+
+		// Without room for the logo, the text takes its place (rather than
+		// leaving room for a logo that's then dropped).
+		let (logo_w, logo_h) = if logo_fits(vbuf, logo.width() as u64, logo.height() as u64, edge) {
+			(logo.width() as u64, logo.height() as u64)
+		} else {
+			(0, 0)
+		};
+		// The space the logo takes up alongside the text, along with the
+		// given gap between them.
+		let logo_space = |size: u64, gap: u64| if size == 0 { 0 } else { size + gap };
+
+		match config.preset {
+			Preset::Full => {
+				let text_h = vbuf.height.saturating_sub(edge * 2);
+
+				let (logo, text) = match config.placement {
+					LogoPlacement::BottomRight => {
+						let text_left = edge + GUTTER;
+						let text_right =
+							vbuf.width.saturating_sub(logo_space(logo_w, GUTTER) + edge);
+
+						(
+							Rect::new(
+								vbuf.width.saturating_sub(logo_w + edge),
+								vbuf.height.saturating_sub(logo_h + edge),
+								logo_w,
+								logo_h,
+							),
+							Rect::new(
+								text_left,
+								edge,
+								text_right.saturating_sub(text_left),
+								text_h,
+							),
+						)
+					}
+					LogoPlacement::Center => {
+						let logo = Rect::new(
+							vbuf.width.saturating_sub(logo_w) / 2,
+							vbuf.height.saturating_sub(logo_h) / 2,
+							logo_w,
+							logo_h,
+						);
+						let text_top = if logo_h == 0 {
+							edge
+						} else {
+							logo.bottom() + MARGIN
+						};
+
+						(
+							logo,
+							Rect::new(
+								edge + GUTTER,
+								text_top,
+								vbuf.width.saturating_sub(edge * 2 + GUTTER),
+								vbuf.height.saturating_sub(text_top + edge),
+							),
+						)
+					}
+					LogoPlacement::TopLeft => {
+						let text_left = edge + logo_space(logo_w, MARGIN) + GUTTER;
+
+						(
+							Rect::new(edge, edge, logo_w, logo_h),
+							Rect::new(
+								text_left,
+								edge,
+								vbuf.width.saturating_sub(text_left + edge),
+								text_h,
+							),
+						)
+					}
+				};
+
+				Self {
+					logo,
+					text,
+					sentinel,
+				}
+			}
+			Preset::SidePanel => {
+				let panel_w = config
+					.panel_width
+					.min(vbuf.width.saturating_sub(edge * 2 + GUTTER));
+				let text_h = vbuf.height.saturating_sub(edge * 2);
+
+				// The area left over for the logo, once the panel is placed.
+				let (text, area) = match config.panel_side {
+					Side::Left => {
+						let text = Rect::new(edge + GUTTER, edge, panel_w, text_h);
+						let area_x = text.right() + MARGIN;
+						let area =
+							Rect::new(area_x, 0, vbuf.width.saturating_sub(area_x), vbuf.height);
+						(text, area)
+					}
+					Side::Right => {
+						let text_x = vbuf.width.saturating_sub(panel_w + edge);
+						let text = Rect::new(text_x, edge, panel_w, text_h);
+						let area =
+							Rect::new(0, 0, text_x.saturating_sub(GUTTER + MARGIN), vbuf.height);
+						(text, area)
+					}
+				};
+
+				Self {
+					logo: Rect::new(
+						area.x + area.w.saturating_sub(logo_w) / 2,
+						area.y + area.h.saturating_sub(logo_h) / 2,
+						logo_w,
+						logo_h,
+					),
+					text,
+					sentinel,
+				}
+			}
+		}
+	}
+}
+
+/// Returns whether a logo of the given size fits on the buffer, along
+/// with its margins (`edge` being the margin at the edge of the screen).
+fn logo_fits(vbuf: &Vbuf, logo_w: u64, logo_h: u64, edge: u64) -> bool {
+	vbuf.width >= logo_w + edge * 2 && vbuf.height >= logo_h + edge * 2
+}
+
+/// Returns the largest part of `rect` that lies entirely to one side
+/// (left, right, above or below) of `hole`.
+fn carve(rect: &Rect, hole: &Rect) -> Rect {
+	let candidates = [
+		// Left of the hole.
+		Rect::new(rect.x, rect.y, hole.x.saturating_sub(rect.x), rect.h),
+		// Right of the hole.
+		Rect::new(
+			hole.right().max(rect.x),
+			rect.y,
+			rect.right().saturating_sub(hole.right().max(rect.x)),
+			rect.h,
+		),
+		// Above the hole.
+		Rect::new(rect.x, rect.y, rect.w, hole.y.saturating_sub(rect.y)),
+		// Below the hole.
+		Rect::new(
+			rect.x,
+			hole.bottom().max(rect.y),
+			rect.w,
+			rect.bottom().saturating_sub(hole.bottom().max(rect.y)),
+		),
+	];
+
+	candidates
+		.into_iter()
+		.map(|candidate| candidate.intersect(rect))
+		.max_by_key(|candidate| candidate.w * candidate.h)
+		.unwrap_or(Rect::new(rect.x, rect.y, 0, 0))
+}
+
+#[cfg(all(test, feature = "mock-vbuf"))]
+mod tests;
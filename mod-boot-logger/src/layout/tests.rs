@@ -0,0 +1,117 @@
+//! Tests for the placement of the boot screen's elements, against a
+//! [`MockVbuf`].
+
+use core::fmt::Write;
+
+use super::{CONFIG, Config, GUTTER, Layout, LogoPlacement, Preset, Side};
+use crate::{
+	Rect,
+	console::TextConsole,
+	logo::Logo,
+	mock_vbuf::{MockFormat, MockVbuf},
+};
+
+/// Returns the configuration for a side panel of the given width, on the
+/// given side.
+fn side_panel(panel_width: u64, panel_side: Side) -> Config {
+	Config {
+		preset: Preset::SidePanel,
+		panel_width,
+		panel_side,
+		..CONFIG
+	}
+}
+
+#[test]
+fn confines_the_log_to_its_side_panel() {
+	for side in [Side::Left, Side::Right] {
+		let mock = MockVbuf::new(400, 300, MockFormat::Xrgb8888);
+		let vbuf = mock.vbuf();
+		let logo = Logo::for_buffer(vbuf);
+		let layout = Layout::new(vbuf, &logo, &side_panel(150, side));
+
+		assert!(layout.has_logo(), "{side:?}");
+		assert_eq!(layout.text.w, 150, "{side:?}");
+		assert!(!layout.text.overlaps(&layout.logo), "{side:?}");
+		match side {
+			Side::Left => assert_eq!(layout.text.x, CONFIG.edge_margin() + GUTTER),
+			Side::Right => assert_eq!(layout.text.right(), 400 - CONFIG.edge_margin()),
+		}
+
+		// Fill the panel (and then some, so that it scrolls) with text.
+		let mut console = TextConsole::new(vbuf, layout.text);
+		for i in 0..40 {
+			writeln!(
+				console,
+				"line {i}: the quick brown fox jumps over the lazy dog"
+			)
+			.unwrap();
+		}
+
+		// The text (and its cursor gutter) stays within the panel, so the
+		// logo's area is left untouched.
+		let panel = Rect::new(
+			layout.text.x - GUTTER,
+			layout.text.y,
+			layout.text.w + GUTTER,
+			layout.text.h,
+		);
+		assert!(mock.lit_count() > 0, "{side:?}");
+		for y in 0..300 {
+			for x in 0..400 {
+				if mock.is_lit(x, y) {
+					assert!(panel.contains(x, y), "({x}, {y}) on the {side:?}");
+				}
+			}
+		}
+	}
+}
+
+#[test]
+fn narrows_the_side_panel_to_fit_the_buffer() {
+	let mock = MockVbuf::new(120, 100, MockFormat::Xrgb8888);
+	let vbuf = mock.vbuf();
+	let logo = Logo::for_buffer(vbuf);
+	let layout = Layout::new(vbuf, &logo, &side_panel(1000, Side::Right));
+
+	assert!(layout.text.right() <= 120);
+	assert!(layout.text.w > 0);
+}
+
+#[test]
+fn keeps_every_placement_on_small_buffers() {
+	let placements = [
+		LogoPlacement::BottomRight,
+		LogoPlacement::Center,
+		LogoPlacement::TopLeft,
+	];
+
+	for placement in placements {
+		let config = Config {
+			preset: Preset::Full,
+			placement,
+			..CONFIG
+		};
+
+		for (w, h) in [(0, 0), (8, 8), (60, 200), (200, 60), (74, 74), (100, 90)] {
+			let mock = MockVbuf::new(w, h, MockFormat::Grey8);
+			let vbuf = mock.vbuf();
+			let layout = Layout::new(vbuf, &Logo::for_buffer(vbuf), &config);
+			let context = format!("{placement:?} on {w}x{h}");
+
+			let screen = Rect::of(vbuf);
+			if layout.has_logo() {
+				assert_eq!(layout.logo.intersect(&screen), layout.logo, "{context}");
+				assert!(!layout.text.overlaps(&layout.logo), "{context}");
+			} else {
+				// The text doesn't leave room for a logo that isn't there.
+				assert_eq!(layout.text.x, CONFIG.edge_margin() + GUTTER, "{context}");
+				assert!(layout.text.y <= CONFIG.edge_margin(), "{context}");
+			}
+
+			if !layout.text.is_empty() {
+				assert_eq!(layout.text.intersect(&screen), layout.text, "{context}");
+			}
+		}
+	}
+}
@@ -0,0 +1,162 @@
+//! Selects the resolution the Oro logo is drawn at, and draws it.
+//!
+//! The logo comes in several resolutions, each its own type. To pick one
+//! at runtime, [`Logo`] wraps whichever was chosen and forwards to it.
+
+use oro_logo_rle::{Command, OroLogo, OroLogo64x64, OroLogo128x128, OroLogo256x256, OroLogoData};
+
+use crate::{LOGO_PALETTE, Rect, Vbuf};
+
+/// The width of the largest logo variant, in pixels.
+pub const MAX_WIDTH: usize = OroLogo256x256::WIDTH;
+
+/// The height of the largest logo variant, in pixels.
+pub const MAX_HEIGHT: usize = OroLogo256x256::HEIGHT;
+
+/// The number of pixels in the largest logo variant.
+///
+/// Buffers holding a (decoded) logo frame must be at least this large.
+pub const MAX_PIXELS: usize = MAX_WIDTH * MAX_HEIGHT;
+
+/// The Oro logo, at one of its available resolutions.
+pub enum Logo {
+	/// The 64x64 logo.
+	Small(OroLogo<OroLogo64x64>),
+	/// The 128x128 logo.
+	Medium(OroLogo<OroLogo128x128>),
+	/// The 256x256 logo.
+	Large(OroLogo<OroLogo256x256>),
+}
+
+impl Logo {
+	/// Picks the largest logo that fits within a quarter of the buffer
+	/// (i.e. half its width and half its height).
+	///
+	/// Falls back to the smallest logo if none of them fit; whether even
+	/// that fits is up to the layout (see [`crate::layout::Layout::has_logo`]).
+	pub fn for_buffer(vbuf: &Vbuf) -> Self {
+		let fits = |w: usize, h: usize| w as u64 <= vbuf.width / 2 && h as u64 <= vbuf.height / 2;
+
+		if fits(OroLogo256x256::WIDTH, OroLogo256x256::HEIGHT) {
+			Self::Large(OroLogo::new())
+		} else if fits(OroLogo128x128::WIDTH, OroLogo128x128::HEIGHT) {
+			Self::Medium(OroLogo::new())
+		} else {
+			Self::Small(OroLogo::new())
+		}
+	}
+
+	/// Returns the logo's width, in pixels.
+	pub const fn width(&self) -> usize {
+		match self {
+			Self::Small(_) => OroLogo64x64::WIDTH,
+			Self::Medium(_) => OroLogo128x128::WIDTH,
+			Self::Large(_) => OroLogo256x256::WIDTH,
+		}
+	}
+
+	/// Returns the logo's height, in pixels.
+	pub const fn height(&self) -> usize {
+		match self {
+			Self::Small(_) => OroLogo64x64::HEIGHT,
+			Self::Medium(_) => OroLogo128x128::HEIGHT,
+			Self::Large(_) => OroLogo256x256::HEIGHT,
+		}
+	}
+
+	/// Returns the logo animation's frame rate, in frames per second.
+	pub const fn fps(&self) -> usize {
+		match self {
+			Self::Small(_) => OroLogo64x64::FPS,
+			Self::Medium(_) => OroLogo128x128::FPS,
+			Self::Large(_) => OroLogo256x256::FPS,
+		}
+	}
+
+	/// Draws the first frame of the logo, at its resolution, as a still
+	/// image with its top-left corner at `(x, y)` (see [`blit_logo`]).
+	pub fn blit(&self, vbuf: &Vbuf, x: u64, y: u64) {
+		match self {
+			Self::Small(_) => blit_logo::<OroLogo64x64>(vbuf, x, y),
+			Self::Medium(_) => blit_logo::<OroLogo128x128>(vbuf, x, y),
+			Self::Large(_) => blit_logo::<OroLogo256x256>(vbuf, x, y),
+		}
+	}
+}
+
+impl Iterator for Logo {
+	type Item = Command;
+
+	fn next(&mut self) -> Option<Command> {
+		match self {
+			Self::Small(logo) => logo.next(),
+			Self::Medium(logo) => logo.next(),
+			Self::Large(logo) => logo.next(),
+		}
+	}
+}
+
+/// Draws the first frame of the logo, at the resolution given by `D`, with
+/// its top-left corner at `(x, y)`.
+///
+/// This draws the logo as a still image, for use outside of the boot
+/// screen (which animates it; see [`crate::display::Display::draw_logo`]).
+/// The whole of the logo's box is drawn, unlit pixels included. Pixels
+/// falling outside of the buffer are clipped.
+pub fn blit_logo<D: OroLogoData>(vbuf: &Vbuf, x: u64, y: u64)
+where
+	OroLogo<D>: Iterator<Item = Command>,
+{
+	let clip = Rect::new(x, y, D::WIDTH as u64, D::HEIGHT as u64).intersect(&Rect::of(vbuf));
+
+	let plot = |off: usize, lightness: u8| {
+		let px = x + (off % D::WIDTH) as u64;
+		let py = y + (off / D::WIDTH) as u64;
+
+		if clip.contains(px, py) {
+			// SAFETY: The clip rectangle is within the buffer bounds.
+			unsafe {
+				vbuf.set_pixel_unchecked(px, py, LOGO_PALETTE[(lightness & 0b11) as usize]);
+			}
+		}
+	};
+
+	// The logo starts out blank, so whatever the first frame skips is unlit.
+	decode_frame(&mut OroLogo::<D>::new(), |off, count, lightness| {
+		for i in 0..count {
+			plot(off + i, lightness.unwrap_or(0));
+		}
+	});
+}
+
+/// Decodes the next frame of the logo from its draw commands, calling
+/// `each` with the offset, length and lightness of every run of pixels in
+/// the frame. Runs the frame skips (i.e. leaves as they were in the last
+/// frame) have no lightness.
+///
+/// Returns `false` if the commands ran out before the frame ended.
+pub fn decode_frame(
+	commands: &mut impl Iterator<Item = Command>,
+	mut each: impl FnMut(usize, usize, Option<u8>),
+) -> bool {
+	let mut off = 0usize;
+
+	for command in commands {
+		match command {
+			Command::End => return true,
+			Command::Draw(count, lightness) => {
+				each(off, count as usize, Some(lightness));
+				off += count as usize;
+			}
+			Command::Skip(count) => {
+				each(off, count as usize, None);
+				off += count as usize;
+			}
+		}
+	}
+
+	false
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,56 @@
+//! Tests for decoding the logo's draw commands.
+
+use oro_logo_rle::Command;
+
+use super::decode_frame;
+
+/// A run of pixels, as passed to [`decode_frame`]'s callback.
+type Run = (usize, usize, Option<u8>);
+
+/// Decodes a frame from `commands`, returning whether it ended and the
+/// runs it was made of.
+fn runs(commands: impl IntoIterator<Item = Command>) -> (bool, [Run; 4], usize) {
+	let mut runs = [(0, 0, None); 4];
+	let mut len = 0;
+
+	let ended = decode_frame(&mut commands.into_iter(), |off, count, lightness| {
+		runs[len] = (off, count, lightness);
+		len += 1;
+	});
+
+	(ended, runs, len)
+}
+
+#[test]
+fn runs_follow_on_from_each_other() {
+	let (ended, runs, len) = runs([
+		Command::Draw(3, 2),
+		Command::Skip(5),
+		Command::Draw(1, 1),
+		Command::End,
+	]);
+
+	assert!(ended);
+	assert_eq!(
+		&runs[..len],
+		&[(0, 3, Some(2)), (3, 5, None), (8, 1, Some(1))]
+	);
+}
+
+#[test]
+fn stops_at_the_end_of_the_frame() {
+	let mut commands = [Command::Draw(2, 3), Command::End, Command::Draw(4, 1)].into_iter();
+	let mut seen = 0;
+
+	assert!(decode_frame(&mut commands, |_, count, _| seen += count));
+	assert_eq!(seen, 2);
+	assert!(matches!(commands.next(), Some(Command::Draw(4, 1))));
+}
+
+#[test]
+fn running_out_of_commands_is_reported() {
+	let (ended, _, len) = runs([Command::Skip(2)]);
+
+	assert!(!ended);
+	assert_eq!(len, 1);
+}
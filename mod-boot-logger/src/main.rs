@@ -3,214 +3,302 @@
 //! This module does its best to provide graphical (or at least
 //! visual) logging output during the earlier boot stages of
 //! the Oro operating system.
-#![expect(clippy::many_single_char_names)]
 
-use oro::{
-	LazyIfaceId,
-	id::iface::{KERNEL_IFACE_QUERY_TYPE_META_V0, ROOT_BOOT_VBUF_V0, ROOT_DEBUG_OUT_V0},
-	key,
-	syscall::Error,
-	syscall_get, syscall_set,
+use core::{
+	fmt::Write,
+	sync::atomic::{Ordering, compiler_fence},
 };
-use oro_logo_rle::{Command, OroLogoData};
 
+use oro::{
+	id::iface::{KERNEL_IFACE_QUERY_TYPE_META_V0, ROOT_BOOT_VBUF_V0},
+	key, syscall_get,
+};
+mod ansi;
+mod backbuf;
+mod box_drawing;
+mod clock;
+mod console;
+mod contention;
+mod debug_out;
+mod diag;
+mod dirty;
+mod display;
 mod font_rasterizer;
-
-/// The Oro logo, aliased to a specific resolution.
+mod glyph_cache;
+mod headless;
+mod layout;
+mod logo;
+#[cfg(all(test, feature = "mock-vbuf"))]
+mod mock_vbuf;
+mod packed;
+mod prompt;
+mod scrollback;
+mod strbuf;
+mod utf8;
+mod vbuf;
+mod watermark;
+mod wave;
+mod widgets;
+
+use backbuf::BackBuffer;
+use debug_out::DebugReader;
+use diag::{FrameStats, SentinelMatcher};
+use display::{Display, Displays, MAX_DISPLAYS};
+use layout::Layout;
+use logo::Logo;
+use scrollback::Scrollback;
+use vbuf::{Color, Rect, Vbuf, acquire_video_buffer};
+use watermark::Watermark;
+
+/// The Oro logo at its smallest resolution, for uses that scale it
+/// themselves (see [`logo::Logo`] for the one drawn in the corner).
 type OroLogo = oro_logo_rle::OroLogo<oro_logo_rle::OroLogo64x64>;
 
-/// How many steps to fade in per frame.
-const FADE_IN_STEP: u8 = 2;
+/// How long the logo takes to fade in, in milliseconds.
+///
+/// The fade is driven by elapsed time rather than by frame count, so
+/// it takes the same time regardless of the frame rate.
+const FADE_IN_MS: u64 = 500;
+
+/// The curve the logo's fade-in follows (see [`fade_curve`]).
+const FADE_CURVE: FadeCurve = FadeCurve::Sine;
 
-/// Lightness values mapped to grey RGB values.
-const LIGHTNESSES: [u8; 4] = [0, 0x55, 0xAA, 0xFF];
+/// Whether the logo should slowly "breathe" (oscillate in brightness)
+/// once it has faded in and no log output has arrived for a while.
+const BREATHING_LOGO: bool = false;
+
+/// How long the log must be quiet before the logo starts breathing, in milliseconds.
+const BREATHING_IDLE_MS: u64 = 3000;
+
+/// The duration of a single breath (dim and back), in milliseconds.
+const BREATHING_PERIOD_MS: u64 = 4000;
 
-/// A video buffer object.
+/// How far the logo dims at the bottom of a breath.
+const BREATHING_DEPTH: u8 = 0x60;
+
+/// The duration of a single cursor pulse (dim, bright, and dim again), in milliseconds.
+const CURSOR_PERIOD_MS: u64 = 1500;
+
+/// The dimmest level the cursor reaches during its pulse.
+const CURSOR_MIN_LEVEL: u8 = 100;
+
+/// Whether to draw a border around the edge of the screen.
 ///
-/// This is a very basic representation of the internal kernel video buffer
-/// structures, and assumes a number of things (such as the buffer being
-/// RGB and 8 bits per channel).
-struct Vbuf {
-	/// The number of pixels per row.
-	///
-	/// **Note:** Do not assume `y * width * bytes_per_pixel` will give you
-	/// the correct base line offset. Padding bytes might be present.
-	/// Multiply `width * stride` instead (_not_ multiplying by `bytes_per_pixel`).
-	width: u64,
-	/// The number of rows.
-	height: u64,
-	/// The number of bytes per row. This may not be equal to `width * bytes_per_pixel`,
-	/// as padding bytes might be present.
-	stride: u64,
-	/// The number of _bits_ per pixel.
-	bits_per_pixel: u64,
-	/// The number of _bytes_ per pixel.
-	bytes_per_pixel: u64,
-	/// The number of bits per red channel within a pixel.
-	red_mask: u64,
-	/// The number of bits per green channel within a pixel.
-	green_mask: u64,
-	/// The number of bits per blue channel within a pixel.
-	blue_mask: u64,
-	/// The base virtual address of the video buffer.
-	data: *mut u8,
-}
+/// When disabled, the log and logo extend closer to the edges.
+const DRAW_BORDER: bool = true;
 
-/// The root ring debug output interface ID.
-static DEBUG_OUT_IFACE: LazyIfaceId<ROOT_DEBUG_OUT_V0> = LazyIfaceId::new();
-/// The root ring video buffer interface ID.
-static VBUF_IFACE: LazyIfaceId<ROOT_BOOT_VBUF_V0> = LazyIfaceId::new();
+/// The radius of the border's rounded corners, in pixels.
+///
+/// Zero gives the border square corners.
+const BORDER_RADIUS: u64 = 8;
 
-/// Attempts to fetch information for, and map in, a video buffer from the kernel
-/// given its index.
+/// Whether to draw a drop shadow down and to the right of the logo.
+const LOGO_SHADOW: bool = false;
+
+/// How far the logo's drop shadow is offset, in pixels.
+const LOGO_SHADOW_OFFSET: u64 = 1;
+
+/// The grey level of the logo's drop shadow.
 ///
-/// Returns an error if any of the syscalls fail.
-fn find_video_buffer(idx: u64) -> Result<Vbuf, (Error, u64)> {
-	// SAFETY: This is inherently unsafe but we're following the
-	// SAFETY: guidelines for syscalls.
-	unsafe {
-		let root_vbuf_iface = VBUF_IFACE
-			.get()
-			.expect("failed to retrieve root ring video buffer interface");
-
-		#[doc(hidden)]
-		macro_rules! get_vbuf_field {
-			($field:literal) => {{ syscall_get!(ROOT_BOOT_VBUF_V0, root_vbuf_iface, idx, key!($field),)? }};
-		}
+/// The shadow only ever darkens what's behind it, never lightens it.
+const LOGO_SHADOW_LEVEL: u8 = 0x22;
 
-		let vbuf_addr: u64 = 0x3C00_0000_0000 + idx * 0x1_0000_0000;
-
-		let bits_per_pixel = get_vbuf_field!("bit_pp");
-		let bytes_per_pixel = bits_per_pixel / 8;
-
-		Ok(Vbuf {
-			width: get_vbuf_field!("width"),
-			height: get_vbuf_field!("height"),
-			bits_per_pixel,
-			bytes_per_pixel,
-			stride: get_vbuf_field!("pitch"),
-			red_mask: get_vbuf_field!("red_size"),
-			green_mask: get_vbuf_field!("grn_size"),
-			blue_mask: get_vbuf_field!("blu_size"),
-			data: {
-				syscall_set!(
-					ROOT_BOOT_VBUF_V0,
-					root_vbuf_iface,
-					idx,
-					key!("!vmbase!"),
-					vbuf_addr
-				)?;
-
-				vbuf_addr as *mut u8
-			},
-		})
-	}
-}
+/// Whether to draw the logo's current frame, as decoded, in the bottom-left
+/// corner of the screen, for debugging the logo pipeline.
+const DEBUG_LOGO_FRAME: bool = false;
 
-impl Vbuf {
-	/// Sets a pixel to a grey level.
-	fn set_grey_pixel(&self, x: u64, y: u64, level: u8) {
-		if x >= self.width || y >= self.height {
-			return;
-		}
+/// Whether to draw a large, faint copy of the logo behind the log text.
+const LOGO_WATERMARK: bool = false;
 
-		unsafe {
-			self.set_grey_pixel_unchecked(x, y, level);
-		}
-	}
+/// Whether to start each display's console with a line describing its
+/// video buffer (its geometry and pixel format), ahead of the log.
+const ANNOTATE_VBUFS: bool = false;
 
-	/// Sets a pixel to a grey level, without checking bounds.
-	///
-	/// # Safety
-	/// Does not check if `x` or `x` are beyond the bounds of the buffer.
-	unsafe fn set_grey_pixel_unchecked(&self, x: u64, y: u64, level: u8) {
-		unsafe {
-			#[expect(clippy::cast_possible_wrap)]
-			let base = self
-				.data
-				.offset(((y * self.stride) + (x * self.bytes_per_pixel)) as isize);
-			*base = level;
-			*(base.offset(1)) = level;
-			*(base.offset(2)) = level;
-		}
-	}
+/// How long an incomplete log line is held back before it's drawn anyway,
+/// in milliseconds.
+const LINE_FLUSH_MS: u64 = 250;
 
-	/// Draws a vertical line.
-	fn draw_vline(&self, x: u64, y1: u64, y2: u64, level: u8) {
-		if x >= self.width || y1 >= self.height {
-			return;
-		}
+/// The most words (of up to 8 bytes each) of debug output read per frame.
+///
+/// Whatever's left stays queued in the root ring for the next frame, so a
+/// burst of logs is drawn over a few frames rather than holding up the
+/// logo's animation for however long it takes to draw it all. The default,
+/// 2 KiB per frame (a couple dozen full lines), takes a few milliseconds
+/// to draw at most, leaving most of a frame for the animation, and at 60
+/// frames per second still drains 120 KiB of logs a second, far more than
+/// a boot produces.
+const MAX_DEBUG_WORDS_PER_FRAME: usize = 256;
+
+/// How often each video buffer's geometry is checked for changes (e.g.
+/// when the kernel switches the display to its native resolution), in
+/// milliseconds.
+const GEOMETRY_CHECK_MS: u64 = 1000;
+
+/// Whether to offer an interactive prompt on the console, if an input
+/// interface is available.
+const BOOT_PROMPT: bool = false;
+
+/// The sequence in the debug output stream with which the kernel signals
+/// that it's panicked, switching to the panic screen (see
+/// [`LoggerMode::Panic`]). Whatever follows it is taken to be the panic
+/// message.
+///
+/// Formatted as an OSC escape sequence, as with the diagnostic overlay's
+/// (see [`diag::SENTINEL`]), so that it's unlikely to appear by accident.
+const PANIC_SENTINEL: &[u8] = b"\x1B]oro-panic\x07";
 
-		let y2 = y2.clamp(y1, self.height - 1);
+/// How many times larger the panic screen's text is than the log's.
+const PANIC_TEXT_SCALE: usize = 2;
 
-		for y in y1..=y2 {
-			// SAFETY: We properly check the bounds of the draw above.
-			unsafe {
-				self.set_grey_pixel_unchecked(x, y, level);
-			}
-		}
-	}
+/// The most frames to render per second, regardless of how fast the machine is.
+///
+/// Lowering this trades animation smoothness for time spent idle.
+const MAX_FPS: u64 = 60;
 
-	/// Draws a horizontal line.
-	fn draw_hline(&self, x1: u64, x2: u64, y: u64, level: u8) {
-		if x1 >= self.width || y >= self.height {
-			return;
-		}
+/// Lightness values mapped to grey RGB values.
+const LIGHTNESSES: [u8; 4] = [0, 0x55, 0xAA, 0xFF];
 
-		let x2 = x2.clamp(x1, self.width - 1);
+/// Whether to draw the logo in grey, rather than tinted with [`LOGO_TINT`].
+const LOGO_GREY: bool = false;
 
-		for x in x1..=x2 {
-			// SAFETY: We properly check the bounds of the draw above.
-			unsafe {
-				self.set_grey_pixel_unchecked(x, y, level);
-			}
-		}
-	}
+/// The color the logo is tinted with, at its full lightness.
+const LOGO_TINT: Color = Color {
+	r: 0xB4,
+	g: 0x8C,
+	b: 0xFF,
+};
 
-	/// Draws a box.
-	fn draw_box(&self, x1: u64, y1: u64, x2: u64, y2: u64, level: u8) {
-		self.draw_hline(x1, x2, y1, level);
-		self.draw_hline(x1, x2, y2, level);
-		self.draw_vline(x1, y1, y2, level);
-		self.draw_vline(x2, y1, y2, level);
-	}
+/// The logo's lightness values mapped to the colors they're drawn in.
+const LOGO_PALETTE: [Color; 4] = {
+	let tint = if LOGO_GREY {
+		Color::grey(0xFF)
+	} else {
+		LOGO_TINT
+	};
 
-	/// Fills an area with a level.
-	fn fill_box(&self, x1: u64, y1: u64, x2: u64, y2: u64, level: u8) {
-		if x1 >= self.width || y1 >= self.height {
-			return;
-		}
+	[
+		tint.scaled(LIGHTNESSES[0]),
+		tint.scaled(LIGHTNESSES[1]),
+		tint.scaled(LIGHTNESSES[2]),
+		tint.scaled(LIGHTNESSES[3]),
+	]
+};
 
-		let x2 = x2.clamp(x1, self.width - 1);
-		let y2 = y2.clamp(y1, self.height - 1);
+/// The shapes of curve the logo's fade-in can follow.
+#[derive(Clone, Copy)]
+#[expect(dead_code)]
+enum FadeCurve {
+	/// Brightens at a constant rate throughout.
+	Linear,
+	/// Eases in and out, along the rising half of a sine wave.
+	Sine,
+	/// Eases in and out, along a cubic ("smoothstep") curve, which is a
+	/// little sharper in the middle than [`Self::Sine`].
+	Cubic,
+}
 
-		for y in y1..=y2 {
-			for x in x1..=x2 {
-				// SAFETY: We properly check the bounds of the draw above.
-				unsafe {
-					self.set_grey_pixel_unchecked(x, y, level);
-				}
-			}
+/// Maps a linear step through the fade-in (from `0` at its start to
+/// `255` at its end) to how far the logo has actually brightened, as
+/// per [`FADE_CURVE`].
+///
+/// Every curve starts at `0` and ends at exactly `255`.
+fn fade_curve(step: u8) -> u8 {
+	match FADE_CURVE {
+		FadeCurve::Linear => step,
+		FadeCurve::Sine => wave::SINE[usize::from(step).div_ceil(2)],
+		FadeCurve::Cubic => {
+			// 3t² - 2t³, with t scaled to 0..=255.
+			let t = u32::from(step);
+			(t * t * (3 * 255 - 2 * t) / (255 * 255)) as u8
 		}
 	}
 }
 
-// Sleeps between a frame.
-//
-// NOTE(qix-): Temporary function. Please do not copy into your modules.
-#[doc(hidden)]
-fn sleep_between_frame() {
-	// TODO(qix-): Implement a proper sleep syscall.
-	for _ in 0..1_000_000 {
-		unsafe {
-			core::arch::asm!("nop");
+/// Returns how far the logo should be dimmed `t` milliseconds into
+/// its fade-in.
+///
+/// Starts fully dimmed and eases (see [`fade_curve`]) to full brightness
+/// (zero) once [`FADE_IN_MS`] have elapsed.
+fn fade_in_dim(t: u64) -> u8 {
+	let step = if t >= FADE_IN_MS {
+		255
+	} else {
+		t * 255 / FADE_IN_MS
+	};
+	u8::MAX - fade_curve(step as u8)
+}
+
+/// Returns how far the logo should be dimmed `t` milliseconds into
+/// a breathing cycle.
+///
+/// Starts (and ends) at full brightness and eases to [`BREATHING_DEPTH`]
+/// halfway through the period.
+fn breathing_dim(t: u64) -> u8 {
+	wave::sample(
+		&wave::SINE,
+		t,
+		BREATHING_PERIOD_MS,
+		u64::from(BREATHING_DEPTH),
+	) as u8
+}
+
+/// Returns the cursor's brightness `t` milliseconds into its pulse.
+///
+/// This is a pure function of time; it pulses between [`CURSOR_MIN_LEVEL`]
+/// and full brightness once every [`CURSOR_PERIOD_MS`].
+fn cursor_brightness(t: u64) -> u8 {
+	let range = u64::from(u8::MAX - CURSOR_MIN_LEVEL);
+	CURSOR_MIN_LEVEL + wave::sample(&wave::TRIANGLE, t, CURSOR_PERIOD_MS, range) as u8
+}
+
+/// Polls for a single character of keyboard input.
+///
+/// **Note:** The kernel doesn't yet expose an input interface to the root ring,
+/// so this never yields anything and the boot prompt stays inactive.
+fn poll_input() -> Option<char> {
+	None
+}
+
+/// Returns the duration of each frame, in milliseconds, when rendering
+/// an animation with the given frame rate (capped to [`MAX_FPS`]).
+const fn frame_interval_ms(fps: u64) -> u64 {
+	let fps = if fps > MAX_FPS { MAX_FPS } else { fps };
+	1000 / if fps == 0 { 1 } else { fps }
+}
+
+/// Maps video buffer `index`, checking that its pixel format is one
+/// that can be drawn to.
+///
+/// Returns `None` (having printed why) if the buffer can't be used.
+fn open_video_buffer(index: u64) -> Option<Vbuf> {
+	println!("looking for vbuf {index}...");
+
+	let vbuf = match acquire_video_buffer(index) {
+		Ok((vbuf, source)) => {
+			println!("found vbuf {index} (source: {source:?})");
+			vbuf
+		}
+		Err(err) => {
+			println!("vbuf {index} {err}");
+			return None;
 		}
+	};
+
+	if let Err(reason) = vbuf.check_supported() {
+		println!("vbuf {index} {reason}");
+		return None;
+	}
+
+	if !vbuf.color {
+		println!("vbuf {index} is grey-only");
 	}
+
+	Some(vbuf)
 }
 
 fn main() {
 	// SAFETY: Just a query, always safe.
-	match unsafe {
+	let count = match unsafe {
 		syscall_get!(
 			KERNEL_IFACE_QUERY_TYPE_META_V0,
 			KERNEL_IFACE_QUERY_TYPE_META_V0,
@@ -220,6 +308,7 @@ fn main() {
 	} {
 		Ok(ifaces) => {
 			println!("ring has {ifaces} ROOT_BOOT_VBUF_V0 interface(s)");
+			ifaces
 		}
 		Err((err, ext)) => {
 			println!(
@@ -228,238 +317,385 @@ fn main() {
 			);
 			return;
 		}
+	};
+
+	if count > MAX_DISPLAYS as u64 {
+		println!("only the first {MAX_DISPLAYS} video buffers will be drawn to");
 	}
 
-	println!("looking for vbuf 0...");
+	// Buffers that can't be used are skipped, rather than giving up on all of them.
+	let mut vbufs: [Option<Vbuf>; MAX_DISPLAYS] = core::array::from_fn(|index| {
+		let index = index as u64;
+		if index < count {
+			open_video_buffer(index)
+		} else {
+			None
+		}
+	});
 
-	let vbuf = match find_video_buffer(0) {
-		Ok(vbuf) => {
-			println!("found vbuf 0");
-			vbuf
+	let mut session = Session::new();
+	// The log, as carried over from one call to `draw` to the next.
+	let mut scrollback = Scrollback::new();
+
+	loop {
+		if vbufs.iter().all(Option::is_none) {
+			println!("no usable video buffer; echoing the log instead");
+			headless::run();
 		}
-		Err((err, ext)) => {
-			println!("failed to find vbuf 0: {err:?}[{:?}]", ::oro::Key(&ext));
-			return;
+
+		let interrupted = draw(&vbufs, &mut session, &mut scrollback);
+
+		// Buffers lost to contention are never drawn to again.
+		for (vbuf, &lost) in vbufs.iter_mut().zip(&session.lost) {
+			if lost {
+				*vbuf = None;
+			}
 		}
-	};
 
-	if (vbuf.bits_per_pixel & 0b111) != 0 {
-		println!("vbuf 0 is not byte-aligned");
-		return;
+		match interrupted {
+			Interrupted::Exhausted => return,
+			Interrupted::Lost => {
+				println!("no video buffers left to draw to; echoing the log instead");
+				// The buffers have all been unmapped by now (see above).
+				headless::run();
+			}
+			Interrupted::Resized(index) => {
+				println!("vbuf {index} changed geometry; mapping it again");
+				remap(&mut vbufs[index], || open_video_buffer(index as u64));
+			}
+		}
 	}
+}
 
-	if vbuf.red_mask != 8 {
-		println!("vbuf 0 red channel is not 8 bits");
-		return;
-	}
+/// Maps a video buffer again (with `open`), once its geometry has changed.
+fn remap(vbuf: &mut Option<Vbuf>, open: impl FnOnce() -> Option<Vbuf>) {
+	// The old mapping has to be released before the buffer can be mapped
+	// again, so it's dropped first.
+	*vbuf = None;
+	*vbuf = open();
+}
 
-	if vbuf.green_mask != 8 {
-		println!("vbuf 0 green channel is not 8 bits");
-		return;
-	}
+/// Why [`draw`] stopped drawing to the video buffers.
+enum Interrupted {
+	/// The logo ran out of draw commands (which shouldn't happen).
+	Exhausted,
+	/// Every buffer has been lost to contention.
+	Lost,
+	/// The geometry of the buffer with the given index has changed, so it
+	/// has to be mapped again (and everything drawn to it laid out again).
+	Resized(usize),
+}
 
-	if vbuf.blue_mask != 8 {
-		println!("vbuf 0 blue channel is not 8 bits");
-		return;
-	}
+/// What the logger is showing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum LoggerMode {
+	/// The boot screen: the animated logo, alongside the log.
+	Boot,
+	/// The panic screen, once the kernel has panicked (see
+	/// [`PANIC_SENTINEL`]): the logo, still, above the panic message
+	/// (see [`Display::enter_panic`]).
+	Panic,
+}
 
-	vbuf.draw_box(3, 3, vbuf.width - 3, vbuf.height - 3, 0x77);
+/// The state of the frame loop, which carries over from one call to
+/// [`draw`] to the next.
+struct Session {
+	/// What's being shown.
+	mode:           LoggerMode,
+	/// Watches the debug output for the diagnostic overlay's sentinel.
+	sentinel:       SentinelMatcher,
+	/// Watches the debug output for the [`PANIC_SENTINEL`].
+	panic_sentinel: SentinelMatcher,
+	/// Reads the debug output.
+	debug_out:      DebugReader,
+	/// The number of frames drawn.
+	frames:         u64,
+	/// The time since drawing started, in milliseconds.
+	now_ms:         u64,
+	/// When log output last arrived, in milliseconds.
+	idle_since_ms:  u64,
+	/// Whether the text has been repainted since the logo faded in.
+	text_repainted: bool,
+	/// When the buffers' geometry is next checked, in milliseconds.
+	next_check_ms:  u64,
+	/// Which buffers have been lost to contention.
+	lost:           [bool; MAX_DISPLAYS],
+	/// Which buffers have been described in their console (see
+	/// [`ANNOTATE_VBUFS`]). The description is carried over in the
+	/// scrollback, so it isn't written again when a buffer is set up afresh.
+	annotated:      [bool; MAX_DISPLAYS],
+	/// Where time is measured from, if there's a clock to measure it with.
+	started_ms:     Option<u64>,
+}
 
-	let left = vbuf.width - (OroLogo::WIDTH as u64) - 5;
-	let top = vbuf.height - (OroLogo::HEIGHT as u64) - 5;
+impl Session {
+	/// Creates the frame loop's state, starting the clock.
+	fn new() -> Self {
+		Self {
+			mode:           LoggerMode::Boot,
+			sentinel:       SentinelMatcher::new(diag::SENTINEL),
+			panic_sentinel: SentinelMatcher::new(PANIC_SENTINEL),
+			debug_out:      DebugReader::new(),
+			frames:         0,
+			now_ms:         0,
+			idle_since_ms:  0,
+			text_repainted: false,
+			next_check_ms:  GEOMETRY_CHECK_MS,
+			lost:           [false; MAX_DISPLAYS],
+			annotated:      [false; MAX_DISPLAYS],
+			started_ms:     clock::monotonic_ms(),
+		}
+	}
+}
 
-	let text_right: usize = vbuf.width as usize - 15 - OroLogo::WIDTH;
-	let text_left: usize = 15;
-	let text_top: usize = 5;
-	let text_bottom: usize = vbuf.height as usize - 5;
+/// Draws the boot screen to every video buffer given, until something
+/// interrupts it.
+///
+/// Everything drawn to the buffers is set up afresh on each call, with
+/// the log restored from `scrollback`; when interrupted by a change in a
+/// buffer's geometry, `scrollback` is updated with the log so far.
+fn draw(
+	vbufs: &[Option<Vbuf>; MAX_DISPLAYS],
+	session: &mut Session,
+	scrollback: &mut Scrollback,
+) -> Interrupted {
+	let backs = BackBuffer::for_buffers(vbufs);
+
+	// The watermarks have to outlive the displays whose consoles draw them,
+	// so they're made up front (each display then works out the same layout).
+	let watermarks: [Option<Watermark>; MAX_DISPLAYS] = core::array::from_fn(|index| {
+		if !LOGO_WATERMARK {
+			return None;
+		}
 
-	let mut iter = OroLogo::new();
+		let vbuf = vbufs[index].as_ref()?;
+		let canvas = backs[index].as_ref().map_or(vbuf, BackBuffer::vbuf);
+		Watermark::new(&Layout::new(canvas, &Logo::for_buffer(canvas), &layout::CONFIG).text)
+	});
+
+	let mut displays = Displays::new();
+	for (index, slot) in displays.iter_mut().enumerate() {
+		*slot = vbufs[index].as_ref().map(|vbuf| {
+			Display::new(
+				index as u64,
+				vbuf,
+				backs[index].as_ref(),
+				watermarks[index].as_ref(),
+				&layout::CONFIG,
+			)
+		});
+	}
 
-	let mut fade_in = 255u8;
+	// Frames are paced for the fastest logo, so that none of them stutter.
+	let frame_interval = frame_interval_ms(
+		displays
+			.iter()
+			.flatten()
+			.map(Display::logo_fps)
+			.max()
+			.unwrap_or(MAX_FPS),
+	);
+
+	// After a panic, the buffers are laid out afresh as panic screens, with
+	// the panic message (as carried over in `scrollback`) restored to them.
+	if session.mode == LoggerMode::Panic {
+		for display in displays.iter_mut().flatten() {
+			display.enter_panic();
+		}
+	}
 
-	let mut text_x: usize = 0;
-	let mut text_y: usize = 0;
+	if scrollback.len() > 0 {
+		for display in displays.iter_mut().flatten() {
+			display.console().restore(scrollback);
+		}
+	}
 
-	let mut cursor_y = 0;
-	let mut last_cursor_y = 0;
-	let mut cursor_level = (101u8..=255u8)
-		.chain((100u8..=254u8).rev())
-		.cycle()
-		.step_by(7);
+	if ANNOTATE_VBUFS && session.mode == LoggerMode::Boot {
+		for ((display, vbuf), annotated) in
+			displays.iter_mut().zip(vbufs).zip(&mut session.annotated)
+		{
+			if let (Some(display), Some(vbuf), false) = (display, vbuf, *annotated) {
+				let index = display.index();
+				let _ = writeln!(display.console(), "vbuf {index}: {}", vbuf.describe());
+				*annotated = true;
+			}
+		}
+	}
 
 	loop {
-		let mut off = 0usize;
+		let frame_started_ms = clock::monotonic_ms();
+		let now_ms = session.now_ms;
+
+		let fade_in = fade_in_dim(now_ms);
+
+		// How much to darken the logo this frame; either the fade-in,
+		// or (once idle) the breathing animation.
+		let idle_ms = now_ms - session.idle_since_ms;
+		let dim = if fade_in > 0 {
+			fade_in
+		} else if BREATHING_LOGO && idle_ms >= BREATHING_IDLE_MS {
+			breathing_dim(idle_ms - BREATHING_IDLE_MS)
+		} else {
+			0
+		};
+
+		// The panic screen's logo is drawn once, when it's entered.
+		let mut exhausted = false;
+		if session.mode == LoggerMode::Boot {
+			for display in displays.iter_mut().flatten() {
+				exhausted |= !display.draw_logo(now_ms, dim);
+			}
+		}
 
-		#[doc(hidden)]
-		static mut OFF_SCREEN: [u8; (OroLogo::WIDTH * OroLogo::HEIGHT) / 4] =
-			[0; { (OroLogo::WIDTH * OroLogo::HEIGHT) / 4 }];
+		if exhausted {
+			println!("Oro logo exhausted commands (shouldn't happen)");
+			for display in displays.iter_mut().flatten() {
+				display.console().finish();
+			}
+			return Interrupted::Exhausted;
+		}
 
-		fade_in = fade_in.saturating_sub(FADE_IN_STEP);
+		// The logo's fade may have drawn over the text as it went, so the
+		// text is repainted once, as soon as the logo is fully faded in.
+		if fade_in == 0 && !session.text_repainted {
+			session.text_repainted = true;
 
-		loop {
-			match iter.next() {
-				None => {
-					println!("Oro logo exhausted commands (shouldn't happen)");
-					return;
-				}
+			for display in displays.iter_mut().flatten() {
+				display.console().redraw();
+			}
+		}
 
-				Some(Command::End) => break,
-
-				Some(Command::Draw(count, lightness)) => {
-					if fade_in > 0 {
-						// We need to draw first to the off-screen buffer,
-						// then blit it to the screen with the multiplier.
-						for i in 0..count {
-							let off = off + i as usize;
-							let byte_off = off / 4;
-							let bit_off = (off % 4) * 2;
-							unsafe {
-								OFF_SCREEN[byte_off] = OFF_SCREEN[byte_off] & !(0b11 << bit_off)
-									| ((lightness & 0b11) << bit_off);
-							}
+		// Now rasterize the root ring logs.
+		let sentinel = &mut session.sentinel;
+		let panic_sentinel = &mut session.panic_sentinel;
+		let mode = &mut session.mode;
+		let received = session
+			.debug_out
+			.drain_up_to(MAX_DEBUG_WORDS_PER_FRAME, |b| {
+				let toggled = sentinel.feed(b, |b| {
+					let panicked = panic_sentinel.feed(b, |b| {
+						for display in displays.iter_mut().flatten() {
+							display.console().feed_byte(b);
 						}
-					} else {
-						// Otherwise, we can draw directly.
-						let color = LIGHTNESSES[(lightness & 0b11) as usize];
-
-						for i in 0..count {
-							let off = off + i as usize;
-							let x = off % OroLogo::WIDTH;
-							let y = off / OroLogo::WIDTH;
-							let x = x as u64 + left;
-							let y = y as u64 + top;
-							vbuf.set_grey_pixel(x, y, color);
+					});
+
+					// The switch is made straight away, so that none of the
+					// panic message goes to the boot screen.
+					if panicked && *mode == LoggerMode::Boot {
+						println!("kernel panicked; switching to the panic screen");
+						*mode = LoggerMode::Panic;
+
+						for display in displays.iter_mut().flatten() {
+							display.enter_panic();
 						}
 					}
+				});
 
-					off += count as usize;
+				if toggled {
+					for display in displays.iter_mut().flatten() {
+						display.toggle_diag(now_ms);
+					}
 				}
+			});
 
-				Some(Command::Skip(count)) => {
-					off += count as usize;
-				}
-			}
+		if received {
+			session.idle_since_ms = now_ms;
 		}
 
-		// If we're fading in, we need to blit the off-screen buffer to the screen.
-		if fade_in > 0 {
-			let mut off = 0usize;
-
-			for _ in 0..OroLogo::HEIGHT {
-				for _ in 0..OroLogo::WIDTH {
-					let byte_off = off / 4;
-					let bit_off = (off % 4) * 2;
-					let lightness = unsafe { OFF_SCREEN[byte_off] >> bit_off } & 0b11;
-					let color = LIGHTNESSES[lightness as usize];
-					let color = color.saturating_sub(fade_in);
-
-					let x = off % OroLogo::WIDTH;
-					let y = off / OroLogo::WIDTH;
-					let x = x as u64 + left;
-					let y = y as u64 + top;
-					vbuf.set_grey_pixel(x, y, color);
-
-					off += 1;
+		if BOOT_PROMPT {
+			while let Some(c) = poll_input() {
+				// Every display's prompt is fed the same input, so they all
+				// complete the same line; it's only reported once.
+				let mut line_reported = false;
+
+				for display in displays.iter_mut().flatten() {
+					match display.feed_prompt(c) {
+						Some(line) if !line_reported => {
+							println!("boot prompt: {line}");
+							line_reported = true;
+						}
+						_ => {}
+					}
 				}
 			}
 		}
 
-		// Now rasterize the root ring logs.
-		if let Some(debug_iface) = DEBUG_OUT_IFACE.get() {
-			loop {
-				// SAFETY: This is always safe.
-				let Ok(r) =
-					(unsafe { syscall_get!(ROOT_DEBUG_OUT_V0, debug_iface, 0, key!("ring_u64")) })
-				else {
-					break;
-				};
-
-				if r == 0 {
-					break;
-				}
+		let stats = FrameStats {
+			frames: session.frames,
+			now_ms,
+			interval_ms: frame_interval,
+		};
 
-				for shift in (0..=(64 - 8)).rev().step_by(8) {
-					let b = ((r >> shift) & 0xFF) as u8;
-					if b == 0 {
-						break;
-					}
-					let c = b as char;
+		for slot in displays.iter_mut() {
+			let Some(display) = slot else {
+				continue;
+			};
 
-					if c == '\n' {
-						text_x = 0;
-						text_y += 1;
+			let console = display.console();
 
-						if ((text_y + 1) * font_rasterizer::LINE_HEIGHT) >= text_bottom as usize {
-							text_y = 0;
-						}
-
-						continue;
-					}
-
-					if text_x >= text_right {
-						continue;
-					}
-
-					let iter = font_rasterizer::render_glyph(c)
-						.or_else(|| font_rasterizer::render_glyph('?'))
-						.expect("missing glyph");
-
-					let xoff = text_x;
-					let width = iter.width();
+			if now_ms - session.idle_since_ms >= LINE_FLUSH_MS {
+				console.flush_line();
+			}
 
-					if width > 0 {
-						text_x += width as usize;
-					}
+			console.set_time(clock::now(now_ms));
+			console.blink_highlight(now_ms);
+			console.scroll_marquees(now_ms);
 
-					if xoff == 0 {
-						// First write of the line; clear it.
-						let left = text_left;
-						let right = text_right;
-						let top = text_top + (text_y * font_rasterizer::LINE_HEIGHT);
-						let bottom = top + font_rasterizer::LINE_HEIGHT;
-						vbuf.fill_box(left as u64, top as u64, right as u64, bottom as u64, 0);
-						cursor_y = text_y;
-					}
+			// Now the cursor.
+			console.draw_cursor(cursor_brightness(now_ms));
 
-					for (x, y, v) in iter {
-						let x = text_left + x + xoff;
-						let y = text_top + y + (text_y * font_rasterizer::LINE_HEIGHT);
-						if x < text_right && y < text_bottom {
-							vbuf.set_grey_pixel(x as u64, y as u64, v);
-						}
-					}
-				}
+			if display.finish_frame(&stats) {
+				println!(
+					"vbuf {} is being drawn to by someone else; no longer drawing to it",
+					display.index()
+				);
+				session.lost[display.index() as usize] = true;
+				*slot = None;
 			}
 		}
 
-		// Now the cursor.
-		let cursor_left = 5;
-		let cursor_top = cursor_y * font_rasterizer::LINE_HEIGHT + text_top;
-		let cursor_bottom = cursor_top + font_rasterizer::LINE_HEIGHT;
-		let cursor_right = 10;
-
-		if last_cursor_y != cursor_y {
-			// Clear the old cursor
-			let cursor_top = last_cursor_y * font_rasterizer::LINE_HEIGHT + text_top;
-			let cursor_bottom = cursor_top + font_rasterizer::LINE_HEIGHT;
-			vbuf.fill_box(
-				cursor_left,
-				cursor_top as u64,
-				cursor_right,
-				cursor_bottom as u64,
-				0,
-			);
-			last_cursor_y = cursor_y;
+		if displays.iter().all(Option::is_none) {
+			return Interrupted::Lost;
 		}
 
-		vbuf.fill_box(
-			cursor_left,
-			cursor_top as u64,
-			cursor_right,
-			cursor_bottom as u64,
-			cursor_level.next().unwrap_or(255),
-		);
+		// Checking a buffer's geometry takes a few syscalls, so it's
+		// only done every so often.
+		if now_ms >= session.next_check_ms {
+			session.next_check_ms = now_ms + GEOMETRY_CHECK_MS;
+
+			let resized = displays
+				.iter()
+				.flatten()
+				.map(|display| display.index() as usize)
+				.find(|&index| vbufs[index].as_ref().is_some_and(Vbuf::geometry_changed));
+
+			if let Some(index) = resized {
+				// Every display shows the same log, so any of them will do.
+				if let Some(display) = displays.iter_mut().flatten().next() {
+					scrollback.clone_from(display.console().scrollback());
+				}
 
-		sleep_between_frame(/*1000 / OroLogo::FPS as u64*/);
+				return Interrupted::Resized(index);
+			}
+		}
+
+		// Make sure the whole frame has been written out before moving on.
+		compiler_fence(Ordering::SeqCst);
+
+		// Only sleep for whatever's left of the frame (if the time spent
+		// drawing it can be measured).
+		let elapsed_ms = frame_started_ms
+			.zip(clock::monotonic_ms())
+			.map_or(0, |(started, now)| now - started);
+		clock::sleep_ms(frame_interval.saturating_sub(elapsed_ms));
+
+		session.now_ms = session
+			.started_ms
+			.zip(clock::monotonic_ms())
+			.map_or(now_ms + frame_interval, |(started, now)| now - started);
+		session.frames += 1;
 	}
 }
+
+#[cfg(test)]
+mod tests;
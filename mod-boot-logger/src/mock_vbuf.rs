@@ -0,0 +1,183 @@
+//! Implements a video buffer backed by ordinary memory, for testing the
+//! drawing primitives on the host.
+//!
+//! The primitives write to raw device memory, so there's nothing to check
+//! them against on the boot target. A [`MockVbuf`] owns a plain block of
+//! memory with whatever geometry and pixel format a test asks for, and
+//! hands out a [`Vbuf`] drawing into it, whose pixels are then read back
+//! (straight from memory, rather than through the primitives under test).
+//!
+//! Only built for tests with the `mock-vbuf` feature, since the memory
+//! comes from the heap, which the boot target doesn't have.
+
+use crate::{Rect, Vbuf, vbuf::RawFramebuffer};
+
+/// The pixel formats a [`MockVbuf`] can have.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MockFormat {
+	/// A single 8-bit grey channel.
+	Grey8,
+	/// 15 bits per pixel (padded to two bytes): 5 bits each of red (in the
+	/// high bits), green and blue.
+	Rgb555,
+	/// 16 bits per pixel: 5 bits of red (in the high bits), 6 of green
+	/// and 5 of blue.
+	Rgb565,
+	/// 24 bits per pixel, a byte per channel, with blue in the low byte.
+	Rgb888,
+	/// 32 bits per pixel, a byte per channel, with blue in the low byte and
+	/// a spare (alpha or padding) byte at the top.
+	Xrgb8888,
+}
+
+impl MockFormat {
+	/// Returns the number of bits per pixel.
+	const fn bits_per_pixel(self) -> u64 {
+		match self {
+			Self::Grey8 => 8,
+			Self::Rgb555 => 15,
+			Self::Rgb565 => 16,
+			Self::Rgb888 => 24,
+			Self::Xrgb8888 => 32,
+		}
+	}
+
+	/// Returns the size and shift of the red, green and blue channels,
+	/// or zeroes for a grey buffer.
+	const fn channels(self) -> [(u64, u64); 3] {
+		match self {
+			Self::Grey8 => [(0, 0); 3],
+			Self::Rgb555 => [(5, 10), (5, 5), (5, 0)],
+			Self::Rgb565 => [(5, 11), (6, 5), (5, 0)],
+			Self::Rgb888 | Self::Xrgb8888 => [(8, 16), (8, 8), (8, 0)],
+		}
+	}
+
+	/// Returns the bits of a pixel that hold its color.
+	const fn color_bits(self) -> u32 {
+		match self {
+			Self::Grey8 => 0xFF,
+			Self::Rgb555 => 0x7FFF,
+			Self::Rgb565 => 0xFFFF,
+			Self::Rgb888 | Self::Xrgb8888 => 0xFF_FFFF,
+		}
+	}
+}
+
+/// A video buffer drawing into memory owned by the mock, for tests.
+pub struct MockVbuf {
+	/// The video buffer, drawing into `memory`.
+	///
+	/// Declared first so that it's dropped before the memory it points to.
+	vbuf:   Vbuf,
+	/// The pixel format of the buffer.
+	format: MockFormat,
+	/// The buffer's memory. It's never resized, so `vbuf` stays valid.
+	memory: Vec<u8>,
+}
+
+impl MockVbuf {
+	/// Creates a black `width` by `height` buffer in the given format,
+	/// with no padding between rows.
+	pub fn new(width: u64, height: u64, format: MockFormat) -> Self {
+		Self::with_padding(width, height, format, 0)
+	}
+
+	/// Creates a black `width` by `height` buffer in the given format, with
+	/// `padding` bytes at the end of each row (so that the stride is more
+	/// than the width of the pixels).
+	pub fn with_padding(width: u64, height: u64, format: MockFormat, padding: u64) -> Self {
+		let pitch = width * format.bits_per_pixel().div_ceil(8) + padding;
+		let mut memory = vec![0; (pitch * height) as usize];
+		let [
+			(red_size, red_shift),
+			(green_size, green_shift),
+			(blue_size, blue_shift),
+		] = format.channels();
+
+		let raw = RawFramebuffer {
+			width,
+			height,
+			pitch,
+			bits_per_pixel: format.bits_per_pixel(),
+			red_size,
+			green_size,
+			blue_size,
+			red_shift,
+			green_shift,
+			blue_shift,
+			opaque: true,
+			base: memory.as_mut_ptr(),
+		};
+
+		// SAFETY: The memory is `height * pitch` bytes long, and is neither
+		// SAFETY: moved nor resized for as long as the buffer exists.
+		let vbuf = unsafe { Vbuf::from_raw(&raw) }.expect("mock pixel formats are at least a byte");
+
+		Self {
+			vbuf,
+			format,
+			memory,
+		}
+	}
+
+	/// Returns the video buffer, to draw into.
+	pub fn vbuf(&self) -> &Vbuf {
+		&self.vbuf
+	}
+
+	/// Returns the raw value of the pixel at `(x, y)`, as stored in memory
+	/// (i.e. little-endian), spare bits and all.
+	pub fn raw_pixel(&self, x: u64, y: u64) -> u32 {
+		assert!(
+			Rect::of(&self.vbuf).contains(x, y),
+			"({x}, {y}) is outside of the buffer"
+		);
+
+		let bytes_per_pixel = self.vbuf.bytes_per_pixel as usize;
+		let start = (y * self.vbuf.stride) as usize + x as usize * bytes_per_pixel;
+
+		self.memory[start..start + bytes_per_pixel]
+			.iter()
+			.rev()
+			.fold(0, |value, &byte| (value << 8) | u32::from(byte))
+	}
+
+	/// Returns whether the pixel at `(x, y)` has any of its color bits set
+	/// (i.e. isn't black).
+	pub fn is_lit(&self, x: u64, y: u64) -> bool {
+		self.raw_pixel(x, y) & self.format.color_bits() != 0
+	}
+
+	/// Returns the number of pixels that aren't black.
+	pub fn lit_count(&self) -> usize {
+		(0..self.vbuf.height)
+			.flat_map(|y| (0..self.vbuf.width).map(move |x| (x, y)))
+			.filter(|&(x, y)| self.is_lit(x, y))
+			.count()
+	}
+
+	/// Returns the padding bytes at the end of row `y`.
+	pub fn padding(&self, y: u64) -> &[u8] {
+		let start = (y * self.vbuf.stride + self.vbuf.width * self.vbuf.bytes_per_pixel) as usize;
+		let end = ((y + 1) * self.vbuf.stride) as usize;
+		&self.memory[start..end]
+	}
+
+	/// Renders the buffer as text, with a `#` for each pixel that isn't
+	/// black and a `.` for each that is, one line per row.
+	///
+	/// Meant for comparing shapes against a picture of what's expected.
+	pub fn render(&self) -> String {
+		let mut out = String::new();
+
+		for y in 0..self.vbuf.height {
+			for x in 0..self.vbuf.width {
+				out.push(if self.is_lit(x, y) { '#' } else { '.' });
+			}
+			out.push('\n');
+		}
+
+		out
+	}
+}
@@ -0,0 +1,40 @@
+//! Implements a buffer of packed 2-bit samples.
+
+/// A fixed-size buffer of 2-bit samples, packed four to a byte (least
+/// significant bits first).
+///
+/// `BYTES` is the size of the buffer in bytes; it holds four times as
+/// many samples.
+pub struct Packed2bpp<const BYTES: usize> {
+	/// The packed samples.
+	bytes: [u8; BYTES],
+}
+
+impl<const BYTES: usize> Packed2bpp<BYTES> {
+	/// The number of samples in the buffer.
+	pub const LEN: usize = BYTES * 4;
+
+	/// Creates a new buffer, with every sample zero.
+	pub const fn new() -> Self {
+		Self { bytes: [0; BYTES] }
+	}
+
+	/// Returns the sample at index `i`.
+	///
+	/// # Panics
+	/// Panics if `i` is out of bounds.
+	pub fn get(&self, i: usize) -> u8 {
+		(self.bytes[i / 4] >> ((i % 4) * 2)) & 0b11
+	}
+
+	/// Sets the sample at index `i` to `v` (of which only the low two
+	/// bits are kept).
+	///
+	/// # Panics
+	/// Panics if `i` is out of bounds.
+	pub fn set(&mut self, i: usize, v: u8) {
+		let shift = (i % 4) * 2;
+		let byte = &mut self.bytes[i / 4];
+		*byte = (*byte & !(0b11 << shift)) | ((v & 0b11) << shift);
+	}
+}
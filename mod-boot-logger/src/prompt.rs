@@ -0,0 +1,74 @@
+//! Implements a minimal line editor for an interactive boot prompt.
+
+use crate::console::TextConsole;
+
+/// The maximum length of a prompt line, in bytes.
+const MAX_LINE: usize = 128;
+
+/// A single-line editor that echoes typed characters to a console.
+///
+/// Supports backspace (`\x08` or `\x7F`) and enter (`\r` or `\n`);
+/// other control characters are ignored.
+pub struct LineEditor {
+	/// The UTF-8 encoded line typed so far.
+	buf:  [u8; MAX_LINE],
+	/// The number of bytes of `buf` in use.
+	len:  usize,
+	/// Whether the line was completed by the previous call to [`Self::feed`].
+	done: bool,
+}
+
+impl LineEditor {
+	/// Creates a new, empty line editor.
+	pub const fn new() -> Self {
+		Self {
+			buf:  [0; MAX_LINE],
+			len:  0,
+			done: false,
+		}
+	}
+
+	/// Returns the line typed so far.
+	fn line(&self) -> &str {
+		// SAFETY: Only whole characters are ever pushed to or popped from the buffer.
+		unsafe { core::str::from_utf8_unchecked(&self.buf[..self.len]) }
+	}
+
+	/// Feeds a typed character to the editor, echoing it to `console`.
+	///
+	/// Returns the completed line once enter is pressed. Characters that
+	/// would overflow the line are dropped.
+	pub fn feed(&mut self, c: char, console: &mut TextConsole) -> Option<&str> {
+		if self.done {
+			self.len = 0;
+			self.done = false;
+		}
+
+		match c {
+			'\r' | '\n' => {
+				console.newline();
+				self.done = true;
+				return Some(self.line());
+			}
+			'\x08' | '\x7F' => {
+				if let Some(last) = self.line().chars().next_back() {
+					self.len -= last.len_utf8();
+					console.erase_char(last);
+				}
+			}
+			c if c.is_control() => {}
+			c => {
+				if self.len + c.len_utf8() <= MAX_LINE {
+					c.encode_utf8(&mut self.buf[self.len..]);
+					self.len += c.len_utf8();
+					console.write_char(c);
+				}
+			}
+		}
+
+		None
+	}
+}
+
+#[cfg(all(test, feature = "mock-vbuf"))]
+mod tests;
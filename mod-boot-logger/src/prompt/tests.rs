@@ -0,0 +1,89 @@
+//! Tests for the line editor, fed synthetic keystrokes.
+
+use super::{LineEditor, MAX_LINE};
+use crate::{
+	Rect,
+	console::TextConsole,
+	mock_vbuf::{MockFormat, MockVbuf},
+};
+
+/// Feeds each character of `keys` to the editor, returning the lines
+/// it completed.
+fn type_keys(editor: &mut LineEditor, console: &mut TextConsole<'_>, keys: &str) -> Vec<String> {
+	keys.chars()
+		.filter_map(|c| editor.feed(c, console).map(String::from))
+		.collect()
+}
+
+/// Returns the text held in a console's scrollback.
+fn echoed(console: &TextConsole<'_>) -> String {
+	let scrollback = console.scrollback();
+	(0..scrollback.len()).map(|i| scrollback.get(i).0).collect()
+}
+
+#[test]
+fn completes_lines_on_enter() {
+	let mock = MockVbuf::new(320, 64, MockFormat::Xrgb8888);
+	let mut console = TextConsole::new(mock.vbuf(), Rect::of(mock.vbuf()));
+	let mut editor = LineEditor::new();
+
+	assert_eq!(
+		type_keys(&mut editor, &mut console, "boot\rmenu\n"),
+		["boot", "menu"]
+	);
+	assert_eq!(type_keys(&mut editor, &mut console, "\r"), [""]);
+}
+
+#[test]
+fn echoes_typed_characters() {
+	let mock = MockVbuf::new(320, 64, MockFormat::Xrgb8888);
+	let mut console = TextConsole::new(mock.vbuf(), Rect::of(mock.vbuf()));
+	let mut editor = LineEditor::new();
+
+	type_keys(&mut editor, &mut console, "héllo");
+	assert_eq!(echoed(&console), "héllo");
+	assert!(mock.lit_count() > 0);
+}
+
+#[test]
+fn erases_whole_characters_on_backspace() {
+	let mock = MockVbuf::new(320, 64, MockFormat::Xrgb8888);
+	let mut console = TextConsole::new(mock.vbuf(), Rect::of(mock.vbuf()));
+	let mut editor = LineEditor::new();
+
+	let lines = type_keys(&mut editor, &mut console, "ab€\x08c\x7F\x7Fd\r");
+	assert_eq!(lines, ["ad"]);
+}
+
+#[test]
+fn ignores_backspace_on_an_empty_line() {
+	let mock = MockVbuf::new(320, 64, MockFormat::Xrgb8888);
+	let mut console = TextConsole::new(mock.vbuf(), Rect::of(mock.vbuf()));
+	let mut editor = LineEditor::new();
+
+	assert_eq!(type_keys(&mut editor, &mut console, "\x08\x7Fa\r"), ["a"]);
+}
+
+#[test]
+fn ignores_other_control_characters() {
+	let mock = MockVbuf::new(320, 64, MockFormat::Xrgb8888);
+	let mut console = TextConsole::new(mock.vbuf(), Rect::of(mock.vbuf()));
+	let mut editor = LineEditor::new();
+
+	assert_eq!(
+		type_keys(&mut editor, &mut console, "a\tb\x1B\x00c\r"),
+		["abc"]
+	);
+}
+
+#[test]
+fn drops_characters_past_the_end_of_the_line() {
+	let mock = MockVbuf::new(320, 64, MockFormat::Xrgb8888);
+	let mut console = TextConsole::new(mock.vbuf(), Rect::of(mock.vbuf()));
+	let mut editor = LineEditor::new();
+
+	// A two-byte character that would straddle the end is dropped whole.
+	let keys = "x".repeat(MAX_LINE - 1) + "é" + "yz\r";
+	let lines = type_keys(&mut editor, &mut console, &keys);
+	assert_eq!(lines, ["x".repeat(MAX_LINE - 1) + "y"]);
+}
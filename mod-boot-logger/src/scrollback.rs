@@ -0,0 +1,93 @@
+//! Implements the scrollback buffer, which remembers the most recent
+//! text written to a console so that it can be redrawn.
+
+use crate::ansi::Style;
+
+/// The number of characters the scrollback buffer holds.
+///
+/// Once full, each new character replaces the oldest one.
+pub const SCROLLBACK_CHARS: usize = 4096;
+
+/// A ring buffer of the most recent characters written to a console,
+/// along with the styles they were written in.
+#[derive(Clone)]
+pub struct Scrollback {
+	/// The characters, starting at `start` and wrapping around.
+	chars:  [char; SCROLLBACK_CHARS],
+	/// The style each character in `chars` was written in.
+	styles: [Style; SCROLLBACK_CHARS],
+	/// The index of the oldest character.
+	start:  usize,
+	/// The number of characters held.
+	len:    usize,
+}
+
+impl Scrollback {
+	/// Creates a new, empty scrollback buffer.
+	pub const fn new() -> Self {
+		Self {
+			chars:  ['\0'; SCROLLBACK_CHARS],
+			styles: [Style::DEFAULT; SCROLLBACK_CHARS],
+			start:  0,
+			len:    0,
+		}
+	}
+
+	/// Returns the number of characters held.
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	/// Returns the `i`th oldest character held, along with its style.
+	///
+	/// # Panics
+	/// Panics if `i` is out of bounds.
+	pub fn get(&self, i: usize) -> (char, Style) {
+		assert!(i < self.len, "scrollback index out of bounds");
+		let i = (self.start + i) % SCROLLBACK_CHARS;
+		(self.chars[i], self.styles[i])
+	}
+
+	/// Adds a character, replacing the oldest one if the buffer is full.
+	pub fn push(&mut self, c: char, style: Style) {
+		let i = (self.start + self.len) % SCROLLBACK_CHARS;
+		self.chars[i] = c;
+		self.styles[i] = style;
+
+		if self.len == SCROLLBACK_CHARS {
+			self.start = (self.start + 1) % SCROLLBACK_CHARS;
+		} else {
+			self.len += 1;
+		}
+	}
+
+	/// Removes the newest character, if any.
+	pub fn pop(&mut self) {
+		self.len = self.len.saturating_sub(1);
+	}
+
+	/// Removes every character.
+	pub fn clear(&mut self) {
+		self.start = 0;
+		self.len = 0;
+	}
+
+	/// Returns the index of the first character of the last `lines` lines
+	/// (counting the current, unterminated one).
+	///
+	/// Returns `0` if fewer lines than that are held.
+	pub fn start_of_last_lines(&self, lines: u64) -> usize {
+		let mut seen = 0;
+
+		for i in (0..self.len).rev() {
+			if self.get(i).0 == '\n' {
+				seen += 1;
+				if seen == lines {
+					return i + 1;
+				}
+			}
+		}
+
+		0
+	}
+}
@@ -0,0 +1,58 @@
+//! Implements a fixed-capacity string buffer for formatting
+//! text without an allocator.
+
+use core::fmt;
+
+/// A fixed-capacity, stack-allocated string that can be written to with
+/// [`write!`].
+///
+/// Writes that would overflow the buffer are truncated (at a character
+/// boundary) and reported as an error.
+pub struct StrBuf<const N: usize> {
+	/// The UTF-8 encoded contents.
+	buf: [u8; N],
+	/// The number of bytes of `buf` in use.
+	len: usize,
+}
+
+impl<const N: usize> StrBuf<N> {
+	/// Creates a new, empty string buffer.
+	pub const fn new() -> Self {
+		Self {
+			buf: [0; N],
+			len: 0,
+		}
+	}
+
+	/// Returns the contents of the buffer.
+	pub fn as_str(&self) -> &str {
+		// SAFETY: Only whole characters are ever written to the buffer.
+		unsafe { core::str::from_utf8_unchecked(&self.buf[..self.len]) }
+	}
+
+	/// Returns whether the buffer is empty.
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	/// Empties the buffer.
+	pub fn clear(&mut self) {
+		self.len = 0;
+	}
+}
+
+impl<const N: usize> fmt::Write for StrBuf<N> {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		for c in s.chars() {
+			let len = c.len_utf8();
+			if self.len + len > N {
+				return Err(fmt::Error);
+			}
+
+			c.encode_utf8(&mut self.buf[self.len..]);
+			self.len += len;
+		}
+
+		Ok(())
+	}
+}
@@ -0,0 +1,104 @@
+//! Tests for the logger's top-level helpers.
+
+#[cfg(feature = "mock-vbuf")]
+use core::sync::atomic::{AtomicBool, Ordering};
+
+#[cfg(feature = "mock-vbuf")]
+use oro::syscall::Error;
+
+#[cfg(feature = "mock-vbuf")]
+use super::remap;
+use super::{
+	BREATHING_DEPTH, BREATHING_PERIOD_MS, CURSOR_MIN_LEVEL, CURSOR_PERIOD_MS, FADE_IN_MS, MAX_FPS,
+	breathing_dim, cursor_brightness, fade_in_dim, frame_interval_ms,
+};
+#[cfg(feature = "mock-vbuf")]
+use crate::mock_vbuf::{MockFormat, MockVbuf};
+
+#[test]
+fn cursor_brightness_repeats_every_period() {
+	for t in (0..CURSOR_PERIOD_MS).step_by(7) {
+		let brightness = cursor_brightness(t);
+		assert_eq!(cursor_brightness(t + CURSOR_PERIOD_MS), brightness, "{t}ms");
+		assert_eq!(
+			cursor_brightness(t + 10 * CURSOR_PERIOD_MS),
+			brightness,
+			"{t}ms"
+		);
+	}
+}
+
+#[test]
+fn cursor_brightness_stays_within_its_range() {
+	let levels = (0..CURSOR_PERIOD_MS).map(cursor_brightness);
+	assert!(levels.clone().all(|level| level >= CURSOR_MIN_LEVEL));
+	assert_eq!(levels.clone().min(), Some(CURSOR_MIN_LEVEL));
+	assert_eq!(levels.max(), Some(u8::MAX));
+}
+
+#[test]
+fn breathing_dim_stays_within_its_depth() {
+	let dims = (0..2 * BREATHING_PERIOD_MS).map(breathing_dim);
+	assert!(dims.clone().all(|dim| dim <= BREATHING_DEPTH));
+
+	// Each breath starts out undimmed, and dims fully halfway through.
+	assert_eq!(breathing_dim(0), 0);
+	assert_eq!(breathing_dim(BREATHING_PERIOD_MS), 0);
+	assert_eq!(breathing_dim(BREATHING_PERIOD_MS / 2), BREATHING_DEPTH);
+}
+
+#[test]
+fn breathing_dim_varies_over_the_period() {
+	let quarter = BREATHING_PERIOD_MS / 4;
+	let dims = [0, 1, 2, 3].map(|i| breathing_dim(i * quarter));
+
+	// It deepens over the first half of the breath, and eases back over
+	// the second.
+	assert!(dims[0] < dims[1] && dims[1] < dims[2], "{dims:?}");
+	assert!(dims[2] > dims[3] && dims[3] > 0, "{dims:?}");
+	assert_eq!(breathing_dim(quarter), breathing_dim(3 * quarter));
+}
+
+#[test]
+fn fades_in_over_exactly_its_duration() {
+	assert_eq!(fade_in_dim(0), u8::MAX);
+	assert!((0..FADE_IN_MS).all(|t| fade_in_dim(t) > 0));
+	assert_eq!(fade_in_dim(FADE_IN_MS), 0);
+	assert_eq!(fade_in_dim(FADE_IN_MS * 10), 0);
+}
+
+#[test]
+fn caps_the_frame_rate() {
+	assert_eq!(frame_interval_ms(20), 50);
+	assert_eq!(frame_interval_ms(MAX_FPS), 1000 / MAX_FPS);
+	assert_eq!(frame_interval_ms(MAX_FPS + 1), 1000 / MAX_FPS);
+	assert_eq!(frame_interval_ms(MAX_FPS * 10), 1000 / MAX_FPS);
+	// A frame rate of zero is taken as one frame a second.
+	assert_eq!(frame_interval_ms(0), 1000);
+}
+
+#[test]
+#[cfg(feature = "mock-vbuf")]
+fn remapping_releases_the_old_mapping_first() {
+	static MAPPED: AtomicBool = AtomicBool::new(true);
+	#[expect(clippy::unnecessary_wraps)]
+	fn release(_iface: u64, _idx: u64) -> Result<(), (Error, u64)> {
+		MAPPED.store(false, Ordering::Relaxed);
+		Ok(())
+	}
+
+	let old = MockVbuf::new(4, 4, MockFormat::Grey8);
+	let new = MockVbuf::new(8, 8, MockFormat::Grey8);
+	// SAFETY: The mock outlives the buffer.
+	let mut vbuf = Some(unsafe { old.vbuf().with_memory(old.vbuf().data) }.mapped_as(0, release));
+
+	remap(&mut vbuf, || {
+		// The buffer can't be mapped twice over.
+		assert!(!MAPPED.swap(true, Ordering::Relaxed));
+		// SAFETY: The mock outlives the buffer.
+		Some(unsafe { new.vbuf().with_memory(new.vbuf().data) })
+	});
+
+	assert!(MAPPED.load(Ordering::Relaxed));
+	assert_eq!(vbuf.map(|vbuf| vbuf.width), Some(8));
+}
@@ -0,0 +1,80 @@
+//! Implements an incremental UTF-8 decoder for the incoming log stream.
+
+/// Decodes a stream of UTF-8 bytes into characters, one byte at a time.
+///
+/// Malformed input (stray continuation bytes, invalid lead bytes, truncated,
+/// overlong or otherwise invalid sequences) decodes to U+FFFD REPLACEMENT
+/// CHARACTER rather than being dropped.
+pub struct Utf8Decoder {
+	/// The code point decoded so far.
+	code_point: u32,
+	/// The number of continuation bytes still expected.
+	remaining:  u8,
+	/// The smallest code point the current sequence may encode (any
+	/// smaller and the sequence is overlong).
+	min:        u32,
+}
+
+impl Utf8Decoder {
+	/// Creates a new decoder.
+	pub const fn new() -> Self {
+		Self {
+			code_point: 0,
+			remaining:  0,
+			min:        0,
+		}
+	}
+
+	/// Feeds a byte to the decoder, passing any characters it completes to `out`.
+	pub fn push(&mut self, b: u8, mut out: impl FnMut(char)) {
+		if self.remaining > 0 {
+			if b & 0xC0 == 0x80 {
+				self.code_point = (self.code_point << 6) | u32::from(b & 0x3F);
+				self.remaining -= 1;
+
+				if self.remaining == 0 {
+					let c = Some(self.code_point)
+						.filter(|&cp| cp >= self.min)
+						.and_then(char::from_u32);
+					out(c.unwrap_or(char::REPLACEMENT_CHARACTER));
+				}
+
+				return;
+			}
+
+			// The sequence was cut short; the byte starts something new.
+			self.remaining = 0;
+			out(char::REPLACEMENT_CHARACTER);
+		}
+
+		let (code_point, remaining, min) = match b {
+			0x00..=0x7F => {
+				out(char::from(b));
+				return;
+			}
+			0xC0..=0xDF => (b & 0x1F, 1, 0x80),
+			0xE0..=0xEF => (b & 0x0F, 2, 0x800),
+			0xF0..=0xF7 => (b & 0x07, 3, 0x1_0000),
+			_ => {
+				out(char::REPLACEMENT_CHARACTER);
+				return;
+			}
+		};
+
+		self.code_point = u32::from(code_point);
+		self.remaining = remaining;
+		self.min = min;
+	}
+
+	/// Ends the stream, passing a replacement character to `out` if it
+	/// ended partway through a sequence.
+	pub fn finish(&mut self, mut out: impl FnMut(char)) {
+		if self.remaining > 0 {
+			self.remaining = 0;
+			out(char::REPLACEMENT_CHARACTER);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests;
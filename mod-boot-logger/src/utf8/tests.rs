@@ -0,0 +1,115 @@
+//! Tests for the UTF-8 decoder.
+
+use super::Utf8Decoder;
+use crate::debug_out::unpack_word;
+
+/// Decodes `bytes` in one go, finishing the stream at the end.
+fn decode(bytes: &[u8]) -> String {
+	let mut decoder = Utf8Decoder::new();
+	let mut out = String::new();
+
+	for &b in bytes {
+		decoder.push(b, |c| out.push(c));
+	}
+	decoder.finish(|c| out.push(c));
+
+	out
+}
+
+/// Packs up to eight bytes into a word, as the debug output does.
+fn pack_word(bytes: &[u8]) -> u64 {
+	assert!(bytes.len() <= 8);
+	bytes
+		.iter()
+		.enumerate()
+		.fold(0, |word, (i, &b)| word | (u64::from(b) << (56 - i * 8)))
+}
+
+#[test]
+fn decodes_valid_text() {
+	assert_eq!(decode("héllo, wörld €𝄞".as_bytes()), "héllo, wörld €𝄞");
+}
+
+#[test]
+fn decodes_sequences_split_across_words() {
+	let text = "abcdefg€ and 𝄞";
+	let bytes = text.as_bytes();
+
+	// The euro sign's three bytes straddle the first two words, and the
+	// clef's four bytes straddle the last two.
+	let words = [
+		pack_word(&bytes[..8]),
+		pack_word(&bytes[8..16]),
+		pack_word(&bytes[16..]),
+	];
+	assert!(str::from_utf8(&bytes[..8]).is_err());
+
+	let mut decoder = Utf8Decoder::new();
+	let mut out = String::new();
+	for word in words {
+		unpack_word(word, |b| decoder.push(b, |c| out.push(c)));
+	}
+	decoder.finish(|c| out.push(c));
+
+	assert_eq!(out, text);
+}
+
+#[test]
+fn replaces_invalid_bytes() {
+	assert_eq!(decode(b"a\xFFb"), "a\u{FFFD}b");
+	assert_eq!(decode(b"a\x80b"), "a\u{FFFD}b");
+	assert_eq!(decode(b"\xF8\x88\x80\x80\x80"), "\u{FFFD}".repeat(5));
+}
+
+#[test]
+fn replaces_sequences_cut_short() {
+	assert_eq!(decode(b"a\xE2\x82b"), "a\u{FFFD}b");
+	assert_eq!(decode(b"a\xE2\x82"), "a\u{FFFD}");
+	assert_eq!(decode(b"\xF0\x9D\x84"), "\u{FFFD}");
+}
+
+#[test]
+fn replaces_overlong_encodings() {
+	// Both encode `/`, which only takes one byte.
+	assert_eq!(decode(b"\xC0\xAF"), "\u{FFFD}");
+	assert_eq!(decode(b"\xE0\x80\xAF"), "\u{FFFD}");
+	// Encodes U+20AC (the euro sign), which only takes three bytes.
+	assert_eq!(decode(b"\xF0\x82\x82\xAC"), "\u{FFFD}");
+}
+
+#[test]
+fn replaces_surrogates() {
+	assert_eq!(decode(b"\xED\xA0\x80"), "\u{FFFD}");
+}
+
+#[test]
+fn finishes_cleanly_between_sequences() {
+	let mut decoder = Utf8Decoder::new();
+	let mut out = String::new();
+	for &b in "€".as_bytes() {
+		decoder.push(b, |c| out.push(c));
+	}
+	decoder.finish(|c| out.push(c));
+	decoder.finish(|c| out.push(c));
+
+	assert_eq!(out, "€");
+}
+
+#[test]
+fn decodes_box_drawing_split_across_ring_words() {
+	// The log is read from the ring eight bytes at a time, and each
+	// box-drawing character is three bytes long, so the third straddles
+	// the first two words.
+	let text = "┌──┐ panic ┌──┐";
+	let mut decoder = Utf8Decoder::new();
+	let mut out = String::new();
+
+	for word in text.as_bytes().chunks(8) {
+		for &b in word {
+			decoder.push(b, |c| out.push(c));
+		}
+	}
+	decoder.finish(|c| out.push(c));
+
+	assert_eq!(out, text);
+}
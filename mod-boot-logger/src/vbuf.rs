@@ -0,0 +1,1818 @@
+//! Implements the video buffer abstraction and its drawing primitives.
+
+use core::fmt;
+
+use oro::{
+	LazyIfaceId, id::iface::ROOT_BOOT_VBUF_V0, key, syscall::Error, syscall_get, syscall_set,
+};
+
+use crate::{backbuf::BackBuffer, dirty::DirtyTracker};
+
+/// A video buffer object.
+///
+/// The buffer is memory-mapped device memory, so every access to it
+/// is volatile; otherwise stores could be coalesced, reordered, or
+/// elided entirely by the compiler. Back buffers (see [`BackBuffer`]) live
+/// in ordinary memory, but share the same representation.
+///
+/// This is a very basic representation of the internal kernel video buffer
+/// structures, and assumes a number of things (such as the buffer being
+/// either RGB with at most 8 bits per channel, or a single 8-bit grey channel).
+pub struct Vbuf {
+	/// The number of pixels per row.
+	///
+	/// **Note:** Do not assume `y * width * bytes_per_pixel` will give you
+	/// the correct base line offset. Padding bytes might be present.
+	/// Multiply `width * stride` instead (_not_ multiplying by `bytes_per_pixel`).
+	pub width: u64,
+	/// The number of rows.
+	pub height: u64,
+	/// The number of bytes per row. This may not be equal to `width * bytes_per_pixel`,
+	/// as padding bytes might be present.
+	pub stride: u64,
+	/// The number of _bits_ per pixel.
+	pub bits_per_pixel: u64,
+	/// The number of _bytes_ per pixel. Rounded up, so e.g. a 15-bit
+	/// (RGB555) pixel takes two, with its top bit unused.
+	pub bytes_per_pixel: u64,
+	/// The number of bits per red channel within a pixel.
+	pub red_mask: u64,
+	/// The number of bits per green channel within a pixel.
+	pub green_mask: u64,
+	/// The number of bits per blue channel within a pixel.
+	pub blue_mask: u64,
+	/// The bit position of the red channel within a pixel.
+	pub red_shift: u64,
+	/// The bit position of the green channel within a pixel.
+	pub green_shift: u64,
+	/// The bit position of the blue channel within a pixel.
+	pub blue_shift: u64,
+	/// Whether the bits of each pixel not used by a color channel (e.g. an
+	/// alpha or padding byte) are set, rather than cleared, when drawing.
+	///
+	/// Set unless the kernel reports that the buffer has no alpha channel,
+	/// so that output stays visible on compositors that honor alpha.
+	pub opaque: bool,
+	/// Whether the buffer can show color.
+	///
+	/// Determined by whether the buffer reports red, green and blue channels.
+	/// Buffers that can't show color must have a single 8-bit channel per
+	/// pixel, which is treated as a grey ramp; colors drawn to them are
+	/// converted to grey.
+	pub color: bool,
+	/// The base virtual address of the video buffer.
+	pub data: *mut u8,
+	/// The regions drawn to since the buffer was last presented
+	/// (see [`Vbuf::present`]).
+	///
+	/// This only matters for back buffers, but it's tracked for every
+	/// buffer so that the drawing primitives needn't care which they're
+	/// drawing to.
+	damage: DirtyTracker,
+	/// How the buffer was mapped in, if it was mapped through the video
+	/// buffer interface (in which case it's unmapped when dropped).
+	mapping: Option<Mapping>,
+}
+
+/// Releases a video buffer's mapping, given the video buffer interface's
+/// ID and the index of the buffer.
+type Release = fn(u64, u64) -> Result<(), (Error, u64)>;
+
+/// A video buffer mapped in through the video buffer interface, which is
+/// unmapped when dropped.
+struct Mapping {
+	/// The video buffer interface's ID.
+	iface:   u64,
+	/// The index of the video buffer.
+	idx:     u64,
+	/// Releases the mapping (normally [`unmap`]).
+	release: Release,
+}
+
+impl Drop for Mapping {
+	fn drop(&mut self) {
+		// There's no one to hand a failure to, so it's reported instead.
+		if let Err((err, ext)) = (self.release)(self.iface, self.idx) {
+			println!(
+				"vbuf {} could not be unmapped: {err:?}[{:?}]",
+				self.idx,
+				oro::Key(&ext)
+			);
+		}
+	}
+}
+
+/// Releases the mapping of video buffer `idx`, the inverse of mapping it
+/// in [`find_video_buffer`]: setting its base back to zero.
+fn unmap(iface: u64, idx: u64) -> Result<(), (Error, u64)> {
+	// SAFETY: Only called once the buffer's mapping is dropped, so nothing
+	// SAFETY: is left to draw to it.
+	unsafe { syscall_set!(ROOT_BOOT_VBUF_V0, iface, idx, key!("!vmbase!"), 0) }
+}
+
+/// Whether to dither colors when reducing them to a buffer's channel
+/// depth (e.g. on 16-bit buffers), which hides banding in gradients
+/// at the cost of some extra work per pixel.
+const DITHER: bool = false;
+
+/// A 4x4 Bayer (ordered dithering) matrix, with thresholds from `0` to `15`.
+const BAYER_4X4: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// Reduces an 8-bit channel level to `bits` (at most 8) bits, dithering
+/// it (based on the pixel's position) if `dither` is set.
+pub fn quantize(level: u8, bits: u32, x: u64, y: u64, dither: bool) -> u16 {
+	let shift = 8 - bits;
+	let level = u16::from(level);
+
+	let level = if dither {
+		let threshold = u16::from(BAYER_4X4[(y % 4) as usize][(x % 4) as usize]);
+		(level + ((threshold << shift) >> 4)).min(255)
+	} else {
+		level
+	};
+
+	level >> shift
+}
+
+/// Fills `len` bytes starting at `ptr` with copies of `pixel` (at most
+/// four bytes long), with volatile writes.
+///
+/// Writes whole (aligned) words wherever it can, which is far quicker
+/// than writing each byte of each pixel on its own.
+///
+/// # Safety
+/// All `len` bytes starting at `ptr` must be valid for writes.
+unsafe fn fill_pixels(ptr: *mut u8, len: usize, pixel: &[u8]) {
+	// Pixels of up to four bytes evenly divide 24 bytes, so the words
+	// written repeat every three.
+	const PERIOD: usize = 3;
+
+	let head = ptr.align_offset(align_of::<u64>()).min(len);
+	let words = (len - head) / size_of::<u64>();
+	let tail = head + words * size_of::<u64>();
+
+	let byte_at = |i: usize| pixel[i % pixel.len()];
+	let pattern: [u64; PERIOD] = core::array::from_fn(|k| {
+		let start = head + k * size_of::<u64>();
+		u64::from_ne_bytes(core::array::from_fn(|j| byte_at(start + j)))
+	});
+
+	// SAFETY: Every write lies within the `len` bytes, and the words
+	// SAFETY: start at an aligned offset.
+	unsafe {
+		for i in 0..head {
+			ptr.add(i).write_volatile(byte_at(i));
+		}
+
+		// The head brings the pointer to a word boundary.
+		#[expect(clippy::cast_ptr_alignment)]
+		let word_ptr = ptr.add(head).cast::<u64>();
+		// A period at a time, so as not to look each word up by remainder.
+		let whole = words - words % PERIOD;
+		for i in (0..whole).step_by(PERIOD) {
+			word_ptr.add(i).write_volatile(pattern[0]);
+			word_ptr.add(i + 1).write_volatile(pattern[1]);
+			word_ptr.add(i + 2).write_volatile(pattern[2]);
+		}
+		for i in whole..words {
+			word_ptr.add(i).write_volatile(pattern[i - whole]);
+		}
+
+		for i in tail..len {
+			ptr.add(i).write_volatile(byte_at(i));
+		}
+	}
+}
+
+/// A color, as red, green and blue levels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Color {
+	/// The red level.
+	pub r: u8,
+	/// The green level.
+	pub g: u8,
+	/// The blue level.
+	pub b: u8,
+}
+
+impl Color {
+	/// Returns the grey color with the given level.
+	pub const fn grey(level: u8) -> Self {
+		Self {
+			r: level,
+			g: level,
+			b: level,
+		}
+	}
+
+	/// Returns the color scaled by `level` (`0` giving black, and `255`
+	/// the color itself).
+	pub const fn scaled(self, level: u8) -> Self {
+		const fn scale(channel: u8, level: u8) -> u8 {
+			((channel as u16 * level as u16) / 255) as u8
+		}
+
+		Self {
+			r: scale(self.r, level),
+			g: scale(self.g, level),
+			b: scale(self.b, level),
+		}
+	}
+
+	/// Returns the color blended over `dst` with the given opacity, as
+	/// with [`blend`] (per channel).
+	pub fn blended_over(self, dst: Self, alpha: u8) -> Self {
+		Self {
+			r: blend(self.r, dst.r, alpha),
+			g: blend(self.g, dst.g, alpha),
+			b: blend(self.b, dst.b, alpha),
+		}
+	}
+
+	/// Returns the perceived brightness of the color, as a grey level.
+	pub fn luma(self) -> u8 {
+		// ITU-R BT.601 weights, scaled to sum to 256.
+		((u16::from(self.r) * 77 + u16::from(self.g) * 150 + u16::from(self.b) * 29) >> 8) as u8
+	}
+}
+
+/// An axis-aligned rectangle, in pixels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rect {
+	/// The left edge.
+	pub x: u64,
+	/// The top edge.
+	pub y: u64,
+	/// The width. A zero width means the rectangle is empty.
+	pub w: u64,
+	/// The height. A zero height means the rectangle is empty.
+	pub h: u64,
+}
+
+impl Rect {
+	/// Creates a new rectangle.
+	pub const fn new(x: u64, y: u64, w: u64, h: u64) -> Self {
+		Self { x, y, w, h }
+	}
+
+	/// Returns the smallest rectangle covering both corners (inclusive of
+	/// both), which may be given in either order.
+	///
+	/// A corner at `u64::MAX` falls just outside of the rectangle, which
+	/// can't reach that far; it's off any buffer regardless.
+	pub fn from_corners(x1: u64, y1: u64, x2: u64, y2: u64) -> Self {
+		let (x, y) = (x1.min(x2), y1.min(y2));
+		Self::new(
+			x,
+			y,
+			x1.abs_diff(x2).saturating_add(1),
+			y1.abs_diff(y2).saturating_add(1),
+		)
+	}
+
+	/// Returns a rectangle covering the entirety of the given video buffer.
+	pub const fn of(vbuf: &Vbuf) -> Self {
+		Self::new(0, 0, vbuf.width, vbuf.height)
+	}
+
+	/// Returns the right edge (exclusive), saturating at `u64::MAX`.
+	pub const fn right(&self) -> u64 {
+		self.x.saturating_add(self.w)
+	}
+
+	/// Returns the bottom edge (exclusive), saturating at `u64::MAX`.
+	pub const fn bottom(&self) -> u64 {
+		self.y.saturating_add(self.h)
+	}
+
+	/// Returns whether the rectangle covers no pixels.
+	pub const fn is_empty(&self) -> bool {
+		self.w == 0 || self.h == 0
+	}
+
+	/// Returns whether the given point lies within the rectangle.
+	pub const fn contains(&self, x: u64, y: u64) -> bool {
+		x >= self.x && x < self.right() && y >= self.y && y < self.bottom()
+	}
+
+	/// Returns whether two rectangles share any pixels.
+	pub fn overlaps(&self, other: &Self) -> bool {
+		!self.intersect(other).is_empty()
+	}
+
+	/// Returns the smallest rectangle covering both rectangles.
+	///
+	/// Empty rectangles cover nothing, so they're ignored.
+	pub fn union(&self, other: &Self) -> Self {
+		if self.is_empty() {
+			return *other;
+		}
+
+		if other.is_empty() {
+			return *self;
+		}
+
+		let x = self.x.min(other.x);
+		let y = self.y.min(other.y);
+		let right = self.right().max(other.right());
+		let bottom = self.bottom().max(other.bottom());
+		Self::new(x, y, right - x, bottom - y)
+	}
+
+	/// Returns the rectangle shrunk by `by` pixels on every side.
+	///
+	/// Rectangles too small to shrink that far become empty.
+	pub const fn inset(&self, by: u64) -> Self {
+		Self::new(
+			self.x + by,
+			self.y + by,
+			self.w.saturating_sub(by * 2),
+			self.h.saturating_sub(by * 2),
+		)
+	}
+
+	/// Returns the intersection of two rectangles.
+	///
+	/// If they do not overlap, the returned rectangle is empty.
+	pub fn intersect(&self, other: &Self) -> Self {
+		let x = self.x.max(other.x);
+		let y = self.y.max(other.y);
+		let right = self.right().min(other.right());
+		let bottom = self.bottom().min(other.bottom());
+		Self::new(x, y, right.saturating_sub(x), bottom.saturating_sub(y))
+	}
+}
+
+/// Blends `src` over `dst` with the given opacity (`0` being fully
+/// transparent, `255` being fully opaque).
+pub fn blend(src: u8, dst: u8, alpha: u8) -> u8 {
+	let alpha = u16::from(alpha);
+	((u16::from(src) * alpha + u16::from(dst) * (255 - alpha)) / 255) as u8
+}
+
+/// The root ring video buffer interface ID.
+static VBUF_IFACE: LazyIfaceId<ROOT_BOOT_VBUF_V0> = LazyIfaceId::new();
+
+/// An error returned when a video buffer could not be acquired.
+///
+/// Displays as the end of a sentence about the buffer (e.g.
+/// `"vbuf 0 {err}"`).
+#[derive(Debug)]
+pub enum VbufError {
+	/// No source of video buffers is available (e.g. the video buffer
+	/// interface isn't present).
+	NoSource,
+	/// Mapping the buffer in failed.
+	Map {
+		/// The error returned by the kernel.
+		err: Error,
+		/// The extended error value returned by the kernel.
+		ext: u64,
+	},
+	/// Querying a field of the video buffer interface failed.
+	Query {
+		/// The field that was being queried.
+		field: VbufField,
+		/// The error returned by the kernel.
+		err:   Error,
+		/// The extended error value returned by the kernel.
+		ext:   u64,
+	},
+	/// The buffer reports fewer than 8 bits per pixel, which cannot
+	/// be addressed bytewise. Holds the reported bits per pixel.
+	UnsupportedBpp(u64),
+}
+
+impl fmt::Display for VbufError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::NoSource => write!(f, "is not available"),
+			Self::Map { err, ext } => {
+				write!(f, "could not be mapped: {err:?}[{:?}]", oro::Key(ext))
+			}
+			Self::Query { field, err, ext } => {
+				write!(
+					f,
+					"field {field:?} could not be queried: {err:?}[{:?}]",
+					oro::Key(ext)
+				)
+			}
+			Self::UnsupportedBpp(bpp) => write!(f, "has unsupported bits per pixel: {bpp}"),
+		}
+	}
+}
+
+/// A known field of a [`ROOT_BOOT_VBUF_V0`] interface.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VbufField {
+	/// The number of pixels per row.
+	Width,
+	/// The number of rows.
+	Height,
+	/// The number of bytes per row.
+	Pitch,
+	/// The number of bits per pixel.
+	BitsPerPixel,
+	/// The number of bits in the red channel.
+	RedSize,
+	/// The number of bits in the green channel.
+	GreenSize,
+	/// The number of bits in the blue channel.
+	BlueSize,
+	/// The bit position of the red channel.
+	RedShift,
+	/// The bit position of the green channel.
+	GreenShift,
+	/// The bit position of the blue channel.
+	BlueShift,
+	/// The number of bits in the alpha channel.
+	AlphaSize,
+}
+
+impl VbufField {
+	/// Returns the interface key of the field.
+	pub fn key(self) -> u64 {
+		match self {
+			Self::Width => key!("width"),
+			Self::Height => key!("height"),
+			Self::Pitch => key!("pitch"),
+			Self::BitsPerPixel => key!("bit_pp"),
+			Self::RedSize => key!("red_size"),
+			Self::GreenSize => key!("grn_size"),
+			Self::BlueSize => key!("blu_size"),
+			Self::RedShift => key!("red_shft"),
+			Self::GreenShift => key!("grn_shft"),
+			Self::BlueShift => key!("blu_shft"),
+			Self::AlphaSize => key!("alp_size"),
+		}
+	}
+}
+
+/// Reads a single field of the video buffer with the given index.
+pub fn read_field(iface: u64, idx: u64, field: VbufField) -> Result<u64, VbufError> {
+	read_field_with(field, |key| {
+		// SAFETY: This is a read-only query; it has no side effects.
+		unsafe { syscall_get!(ROOT_BOOT_VBUF_V0, iface, idx, key) }
+	})
+}
+
+/// Reads a single field using the given query, which is passed the
+/// field's interface key.
+///
+/// Split out of [`read_field`] so that failures can be simulated.
+fn read_field_with(
+	field: VbufField,
+	query: impl FnOnce(u64) -> Result<u64, (Error, u64)>,
+) -> Result<u64, VbufError> {
+	query(field.key()).map_err(|(err, ext)| VbufError::Query { field, err, ext })
+}
+
+/// Where a video buffer was acquired from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VbufSource {
+	/// The root ring's boot video buffer interface ([`ROOT_BOOT_VBUF_V0`]).
+	BootVbuf,
+	/// A raw framebuffer handed over by the firmware, outside of the
+	/// video buffer interface.
+	Firmware,
+}
+
+/// The raw description of a framebuffer, from which a [`Vbuf`] is built
+/// regardless of where it came from.
+pub struct RawFramebuffer {
+	/// The number of pixels per row.
+	pub width:          u64,
+	/// The number of rows.
+	pub height:         u64,
+	/// The number of bytes per row.
+	pub pitch:          u64,
+	/// The number of _bits_ per pixel.
+	pub bits_per_pixel: u64,
+	/// The number of bits in the red channel.
+	pub red_size:       u64,
+	/// The number of bits in the green channel.
+	pub green_size:     u64,
+	/// The number of bits in the blue channel.
+	pub blue_size:      u64,
+	/// The bit position of the red channel.
+	pub red_shift:      u64,
+	/// The bit position of the green channel.
+	pub green_shift:    u64,
+	/// The bit position of the blue channel.
+	pub blue_shift:     u64,
+	/// Whether the pixels' unused bits should be set (see [`Vbuf::opaque`]).
+	pub opaque:         bool,
+	/// The (already mapped) base address of the framebuffer.
+	pub base:           *mut u8,
+}
+
+impl Vbuf {
+	/// Builds a video buffer from a raw framebuffer description.
+	///
+	/// Returns an error if the buffer's pixels are smaller than a byte.
+	///
+	/// # Safety
+	/// The framebuffer must be mapped, writable, and at least
+	/// `height * pitch` bytes long for as long as the video buffer is used.
+	pub unsafe fn from_raw(raw: &RawFramebuffer) -> Result<Self, VbufError> {
+		if raw.bits_per_pixel < 8 {
+			return Err(VbufError::UnsupportedBpp(raw.bits_per_pixel));
+		}
+
+		Ok(Self {
+			width: raw.width,
+			height: raw.height,
+			stride: raw.pitch,
+			bits_per_pixel: raw.bits_per_pixel,
+			bytes_per_pixel: raw.bits_per_pixel.div_ceil(8),
+			red_mask: raw.red_size,
+			green_mask: raw.green_size,
+			blue_mask: raw.blue_size,
+			red_shift: raw.red_shift,
+			green_shift: raw.green_shift,
+			blue_shift: raw.blue_shift,
+			opaque: raw.opaque,
+			// A buffer without (all of) its color channels is taken to be
+			// grey, however many bits per pixel it has; a 16-bit grey buffer
+			// must not be mistaken for e.g. RGB565.
+			color: raw.red_size > 0 && raw.green_size > 0 && raw.blue_size > 0,
+			data: raw.base,
+			damage: DirtyTracker::new(),
+			mapping: None,
+		})
+	}
+
+	/// Returns whether the kernel now reports a different width, height or
+	/// pitch for the buffer than it was mapped with (e.g. because the
+	/// display's mode was changed), in which case it must be mapped again.
+	///
+	/// Always `false` for buffers that weren't mapped through the video
+	/// buffer interface, or whose geometry can't be queried.
+	pub fn geometry_changed(&self) -> bool {
+		let Some(&Mapping { iface, idx, .. }) = self.mapping.as_ref() else {
+			return false;
+		};
+
+		[
+			(VbufField::Width, self.width),
+			(VbufField::Height, self.height),
+			(VbufField::Pitch, self.stride),
+		]
+		.into_iter()
+		.any(|(field, mapped)| read_field(iface, idx, field).is_ok_and(|value| value != mapped))
+	}
+
+	/// Creates a buffer with the same geometry and pixel format as this one,
+	/// but backed by other memory (e.g. a [`BackBuffer`]).
+	///
+	/// # Safety
+	/// `data` must be writable, and at least `height * stride` bytes long
+	/// for as long as the returned buffer is used.
+	pub unsafe fn with_memory(&self, data: *mut u8) -> Self {
+		Self {
+			width: self.width,
+			height: self.height,
+			stride: self.stride,
+			bits_per_pixel: self.bits_per_pixel,
+			bytes_per_pixel: self.bytes_per_pixel,
+			red_mask: self.red_mask,
+			green_mask: self.green_mask,
+			blue_mask: self.blue_mask,
+			red_shift: self.red_shift,
+			green_shift: self.green_shift,
+			blue_shift: self.blue_shift,
+			opaque: self.opaque,
+			color: self.color,
+			data,
+			damage: DirtyTracker::new(),
+			mapping: None,
+		}
+	}
+
+	/// Marks the buffer as having been mapped in as video buffer `idx`,
+	/// with `release` called in place of unmapping it once it's dropped.
+	#[cfg(all(test, feature = "mock-vbuf"))]
+	pub fn mapped_as(mut self, idx: u64, release: Release) -> Self {
+		self.mapping = Some(Mapping {
+			iface: 0,
+			idx,
+			release,
+		});
+		self
+	}
+}
+
+/// Acquires a video buffer from the first source that provides one.
+///
+/// The boot video buffer interface is tried first; if it isn't present,
+/// a firmware-provided framebuffer is tried instead. Errors other than
+/// a missing source are returned immediately rather than falling back,
+/// since they indicate a present-but-broken buffer.
+pub fn acquire_video_buffer(idx: u64) -> Result<(Vbuf, VbufSource), VbufError> {
+	acquire_from(idx, find_video_buffer, find_firmware_framebuffer)
+}
+
+/// Acquires a video buffer as with [`acquire_video_buffer`], from the
+/// given boot video buffer and firmware sources.
+///
+/// Split out of [`acquire_video_buffer`] so that the sources can be simulated.
+fn acquire_from(
+	idx: u64,
+	boot_vbuf: impl FnOnce(u64) -> Result<Vbuf, VbufError>,
+	firmware: impl FnOnce(u64) -> Result<Vbuf, VbufError>,
+) -> Result<(Vbuf, VbufSource), VbufError> {
+	match boot_vbuf(idx) {
+		Ok(vbuf) => Ok((vbuf, VbufSource::BootVbuf)),
+		Err(VbufError::NoSource) => firmware(idx).map(|vbuf| (vbuf, VbufSource::Firmware)),
+		Err(err) => Err(err),
+	}
+}
+
+/// Attempts to fetch information for, and map in, a video buffer from the kernel
+/// given its index.
+///
+/// Returns [`VbufError::NoSource`] if the video buffer interface isn't present,
+/// or another error if any of the syscalls fail or the buffer's pixels are
+/// smaller than a byte (in which case the buffer is never mapped).
+///
+/// The buffer is unmapped once the returned [`Vbuf`] is dropped (or
+/// straight away, if it turns out to be unusable), so it has to be dropped
+/// before the buffer is mapped again (e.g. after its geometry changed).
+pub fn find_video_buffer(idx: u64) -> Result<Vbuf, VbufError> {
+	let root_vbuf_iface = VBUF_IFACE.get().ok_or(VbufError::NoSource)?;
+
+	let field = |field| read_field(root_vbuf_iface, idx, field);
+
+	let vbuf_addr: u64 = 0x3C00_0000_0000 + idx * 0x1_0000_0000;
+
+	let bits_per_pixel = field(VbufField::BitsPerPixel)?;
+	if bits_per_pixel < 8 {
+		return Err(VbufError::UnsupportedBpp(bits_per_pixel));
+	}
+
+	let red_size = field(VbufField::RedSize)?;
+	let green_size = field(VbufField::GreenSize)?;
+	let blue_size = field(VbufField::BlueSize)?;
+
+	// Older kernels don't report the channels' positions; they
+	// always lay them out with blue in the lowest bits.
+	let shift = |shift_field, default| field(shift_field).unwrap_or(default);
+
+	let mut raw = RawFramebuffer {
+		width: field(VbufField::Width)?,
+		height: field(VbufField::Height)?,
+		pitch: field(VbufField::Pitch)?,
+		bits_per_pixel,
+		red_size,
+		green_size,
+		blue_size,
+		red_shift: shift(VbufField::RedShift, blue_size + green_size),
+		green_shift: shift(VbufField::GreenShift, blue_size),
+		blue_shift: shift(VbufField::BlueShift, 0),
+		// Only a buffer known to have no alpha channel has its spare bits cleared.
+		opaque: field(VbufField::AlphaSize).map_or(true, |size| size > 0),
+		base: core::ptr::null_mut(),
+	};
+
+	// SAFETY: This is inherently unsafe but we're following the
+	// SAFETY: guidelines for syscalls.
+	unsafe {
+		syscall_set!(
+			ROOT_BOOT_VBUF_V0,
+			root_vbuf_iface,
+			idx,
+			key!("!vmbase!"),
+			vbuf_addr
+		)
+		.map_err(|(err, ext)| VbufError::Map { err, ext })?;
+	}
+
+	let mapping = Mapping {
+		iface: root_vbuf_iface,
+		idx,
+		release: unmap,
+	};
+
+	raw.base = vbuf_addr as *mut u8;
+
+	// SAFETY: The kernel has just mapped the buffer at `vbuf_addr`.
+	let mut vbuf = unsafe { Vbuf::from_raw(&raw) }?;
+	vbuf.mapping = Some(mapping);
+
+	Ok(vbuf)
+}
+
+/// Attempts to find a framebuffer handed over by the firmware (e.g. a
+/// GOP or multiboot framebuffer) without going through the video buffer
+/// interface.
+///
+/// Returns [`VbufError::NoSource`] if there is no such framebuffer.
+///
+/// **Note:** The kernel doesn't yet expose the firmware's framebuffer to the
+/// root ring other than through the boot video buffer interface, so this
+/// never finds one. Once it does, this should describe it with a
+/// [`RawFramebuffer`] and build the buffer with [`Vbuf::from_raw`].
+pub fn find_firmware_framebuffer(_idx: u64) -> Result<Vbuf, VbufError> {
+	Err(VbufError::NoSource)
+}
+
+/// Why a video buffer's pixel format can't be drawn to.
+///
+/// Returned by [`Vbuf::check_supported`]. Displays as the end of a
+/// sentence about the buffer, as with [`VbufError`].
+#[derive(Debug)]
+pub enum UnsupportedFormat {
+	/// A color channel is empty, wider than 8 bits, or doesn't fit in
+	/// the pixel.
+	ChannelLayout {
+		/// The name of the channel.
+		channel:        &'static str,
+		/// The number of bits in the channel.
+		size:           u64,
+		/// The bit position of the channel.
+		shift:          u64,
+		/// The reported bits per pixel.
+		bits_per_pixel: u64,
+	},
+	/// The buffer is grey, but not 8 bits per pixel. Holds the reported
+	/// bits per pixel.
+	GreyDepth(u64),
+}
+
+impl fmt::Display for UnsupportedFormat {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::ChannelLayout {
+				channel,
+				size,
+				shift,
+				bits_per_pixel,
+			} => {
+				write!(
+					f,
+					"{channel} channel has an unsupported layout ({size} bits at {shift} of a \
+					 {bits_per_pixel}-bit pixel)"
+				)
+			}
+			Self::GreyDepth(bpp) => {
+				write!(
+					f,
+					"has an unsupported grey pixel format ({bpp} bits per pixel)"
+				)
+			}
+		}
+	}
+}
+
+/// Returned by [`Vbuf::blit_grey`] and [`Vbuf::blit_rgb`] when the
+/// image data isn't the size its dimensions call for.
+#[derive(Debug)]
+pub struct BlitSizeMismatch {
+	/// The number of bytes the image's dimensions call for.
+	pub expected: usize,
+	/// The number of bytes given.
+	pub actual:   usize,
+}
+
+impl fmt::Display for BlitSizeMismatch {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"image data is {} bytes, but its dimensions call for {}",
+			self.actual, self.expected
+		)
+	}
+}
+
+/// A human-readable description of a video buffer's geometry and pixel format.
+///
+/// Returned by [`Vbuf::describe`].
+pub struct Description<'a>(&'a Vbuf);
+
+impl fmt::Display for Description<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let vbuf = self.0;
+		write!(
+			f,
+			"{}x{} {}bpp pitch={} rgb={}@{}/{}@{}/{}@{}{}",
+			vbuf.width,
+			vbuf.height,
+			vbuf.bits_per_pixel,
+			vbuf.stride,
+			vbuf.red_mask,
+			vbuf.red_shift,
+			vbuf.green_mask,
+			vbuf.green_shift,
+			vbuf.blue_mask,
+			vbuf.blue_shift,
+			if vbuf.color { "" } else { " (grey)" }
+		)
+	}
+}
+
+impl Vbuf {
+	/// Returns a human-readable description of the buffer's
+	/// geometry and pixel format.
+	pub fn describe(&self) -> Description<'_> {
+		Description(self)
+	}
+
+	/// Checks that the buffer's pixel format is one that can be drawn to.
+	///
+	/// That's either 8-bit grey, or color with each channel at most 8 bits
+	/// wide and lying within the first 32 bits of the pixel. The pixel
+	/// needn't be a whole number of bytes (e.g. 15-bit RGB555); it's
+	/// padded out to the next byte.
+	pub fn check_supported(&self) -> Result<(), UnsupportedFormat> {
+		if !self.color {
+			return if self.bits_per_pixel == 8 {
+				Ok(())
+			} else {
+				Err(UnsupportedFormat::GreyDepth(self.bits_per_pixel))
+			};
+		}
+
+		let channels = [
+			("red", self.red_mask, self.red_shift),
+			("green", self.green_mask, self.green_shift),
+			("blue", self.blue_mask, self.blue_shift),
+		];
+
+		for (channel, size, shift) in channels {
+			if size == 0 || size > 8 || shift + size > self.bits_per_pixel.min(32) {
+				return Err(UnsupportedFormat::ChannelLayout {
+					channel,
+					size,
+					shift,
+					bits_per_pixel: self.bits_per_pixel,
+				});
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Sets a pixel to a grey level.
+	pub fn set_grey_pixel(&self, x: u64, y: u64, level: u8) {
+		self.set_pixel(x, y, Color::grey(level));
+	}
+
+	/// Returns a pointer to the first byte of a pixel.
+	///
+	/// # Safety
+	/// Does not check if `x` or `y` are beyond the bounds of the buffer.
+	unsafe fn pixel_ptr(&self, x: u64, y: u64) -> *mut u8 {
+		#[expect(clippy::cast_possible_wrap)]
+		unsafe {
+			self.data
+				.offset(((y * self.stride) + (x * self.bytes_per_pixel)) as isize)
+		}
+	}
+
+	/// Sets a pixel to a grey level, without checking bounds.
+	///
+	/// # Safety
+	/// Does not check if `x` or `x` are beyond the bounds of the buffer.
+	pub unsafe fn set_grey_pixel_unchecked(&self, x: u64, y: u64, level: u8) {
+		// SAFETY: The caller upholds the same contract.
+		unsafe {
+			self.set_pixel_unchecked(x, y, Color::grey(level));
+		}
+	}
+
+	/// Sets a pixel to a color, converting it to grey if the buffer
+	/// can't show color.
+	pub fn set_pixel(&self, x: u64, y: u64, color: Color) {
+		if Rect::of(self).contains(x, y) {
+			// SAFETY: We've checked the bounds above.
+			unsafe {
+				self.set_pixel_unchecked(x, y, color);
+			}
+		}
+	}
+
+	/// Sets a pixel to a color, converting it to grey if the buffer
+	/// can't show color, without checking bounds.
+	///
+	/// # Safety
+	/// Does not check if `x` or `y` are beyond the bounds of the buffer.
+	pub unsafe fn set_pixel_unchecked(&self, x: u64, y: u64, color: Color) {
+		self.mark_damaged(&Rect::new(x, y, 1, 1));
+
+		unsafe {
+			if self.color {
+				self.write_pixel(x, y, self.pack(x, y, color));
+			} else {
+				self.pixel_ptr(x, y).write_volatile(color.luma());
+			}
+		}
+	}
+
+	/// Records that a region of the buffer has been (or is about to be)
+	/// drawn to, so that it's copied out when presented.
+	///
+	/// The drawing primitives record every pixel they modify, so this is
+	/// never required; marking a region up front just lets the per-pixel
+	/// records that follow take the fast path.
+	pub fn mark_damaged(&self, rect: &Rect) {
+		self.damage.mark(rect);
+	}
+
+	/// Copies everything drawn to a back buffer since it was last presented
+	/// out to this buffer, with volatile writes.
+	///
+	/// Only the damaged regions are copied, so frames that change little are
+	/// cheap to present. The back buffer must have been created for this
+	/// buffer (see [`BackBuffer::new`]).
+	pub fn present(&self, back: &BackBuffer) {
+		let back = back.vbuf();
+
+		for rect in back.damage.take() {
+			let rect = rect.intersect(&Rect::of(self));
+			if rect.is_empty() {
+				continue;
+			}
+
+			let len = (rect.w * self.bytes_per_pixel) as usize;
+
+			for y in rect.y..rect.bottom() {
+				let offset = (y * self.stride + rect.x * self.bytes_per_pixel) as usize;
+
+				// SAFETY: Both buffers share the same geometry, and the row
+				// SAFETY: lies within the (clipped) rectangle.
+				unsafe {
+					let src = back.data.add(offset);
+					let dst = self.data.add(offset);
+					for i in 0..len {
+						dst.add(i).write_volatile(src.add(i).read_volatile());
+					}
+				}
+			}
+		}
+	}
+
+	/// Packs a color into a pixel value, reducing each channel to its
+	/// depth (and dithering it based on the pixel's position, if enabled)
+	/// and placing it at its position. Any other bits are set or cleared
+	/// according to [`Self::opaque`].
+	fn pack(&self, x: u64, y: u64, color: Color) -> u32 {
+		let channel = |level, size: u64, shift: u64| {
+			u32::from(quantize(level, size as u32, x, y, DITHER)) << shift
+		};
+
+		let mut value = channel(color.r, self.red_mask, self.red_shift)
+			| channel(color.g, self.green_mask, self.green_shift)
+			| channel(color.b, self.blue_mask, self.blue_shift);
+
+		if self.opaque {
+			let mask = |size: u64, shift: u64| ((1u32 << size) - 1) << shift;
+			let used = mask(self.red_mask, self.red_shift)
+				| mask(self.green_mask, self.green_shift)
+				| mask(self.blue_mask, self.blue_shift);
+			let all = u32::MAX >> (32 - self.bits_per_pixel.min(32));
+			value |= all & !used;
+		}
+
+		value
+	}
+
+	/// Writes a packed pixel value (see [`Self::pack`]).
+	///
+	/// # Safety
+	/// Does not check if `x` or `y` are beyond the bounds of the buffer.
+	unsafe fn write_pixel(&self, x: u64, y: u64, value: u32) {
+		// SAFETY: The caller upholds the same contract.
+		unsafe {
+			self.write_pixel_at(self.pixel_ptr(x, y), value);
+		}
+	}
+
+	/// Writes a packed pixel value to the pixel starting at `ptr`.
+	///
+	/// # Safety
+	/// `ptr` must point to the first byte of a pixel within the buffer.
+	unsafe fn write_pixel_at(&self, ptr: *mut u8, value: u32) {
+		let bytes = value.to_le_bytes();
+		let len = (self.bytes_per_pixel as usize).min(bytes.len());
+
+		unsafe {
+			for (i, &byte) in bytes[..len].iter().enumerate() {
+				ptr.add(i).write_volatile(byte);
+			}
+		}
+	}
+
+	/// Returns the bytes of a pixel of the given color, if they're the
+	/// same wherever it's drawn.
+	///
+	/// Returns `None` if the pixel's value depends on its position (see
+	/// [`DITHER`]), or if it's too wide to be packed.
+	fn fixed_pixel(&self, color: Color) -> Option<([u8; 4], usize)> {
+		if !self.color {
+			return Some(([color.luma(); 4], 1));
+		}
+
+		if DITHER || self.bytes_per_pixel > 4 {
+			return None;
+		}
+
+		Some((
+			self.pack(0, 0, color).to_le_bytes(),
+			self.bytes_per_pixel as usize,
+		))
+	}
+
+	/// Reads a packed pixel value.
+	///
+	/// # Safety
+	/// Does not check if `x` or `y` are beyond the bounds of the buffer.
+	unsafe fn read_pixel(&self, x: u64, y: u64) -> u32 {
+		let mut bytes = [0; 4];
+		let len = (self.bytes_per_pixel as usize).min(bytes.len());
+
+		unsafe {
+			let base = self.pixel_ptr(x, y);
+			for (i, byte) in bytes[..len].iter_mut().enumerate() {
+				*byte = base.add(i).read_volatile();
+			}
+		}
+
+		u32::from_le_bytes(bytes)
+	}
+
+	/// Reads a pixel back as a grey level (the average of its channels).
+	///
+	/// Returns `None` if the pixel lies outside of the buffer.
+	pub fn get_grey_pixel(&self, x: u64, y: u64) -> Option<u8> {
+		if !Rect::of(self).contains(x, y) {
+			return None;
+		}
+
+		// SAFETY: We've checked the bounds above.
+		Some(unsafe { self.get_grey_pixel_unchecked(x, y) })
+	}
+
+	/// Reads a pixel back as a grey level (the average of its channels),
+	/// without checking bounds.
+	///
+	/// # Safety
+	/// Does not check if `x` or `y` are beyond the bounds of the buffer.
+	pub unsafe fn get_grey_pixel_unchecked(&self, x: u64, y: u64) -> u8 {
+		if !self.color {
+			return unsafe { self.pixel_ptr(x, y).read_volatile() };
+		}
+
+		let color = unsafe { self.get_pixel_unchecked(x, y) };
+		let sum = u16::from(color.r) + u16::from(color.g) + u16::from(color.b);
+		(sum / 3) as u8
+	}
+
+	/// Reads a pixel back as a color.
+	///
+	/// Pixels of buffers that can't show color are read back as grey.
+	/// Returns `None` if the pixel lies outside of the buffer.
+	pub fn get_pixel(&self, x: u64, y: u64) -> Option<Color> {
+		if !Rect::of(self).contains(x, y) {
+			return None;
+		}
+
+		// SAFETY: We've checked the bounds above.
+		Some(unsafe { self.get_pixel_unchecked(x, y) })
+	}
+
+	/// Reads a pixel back as a color, without checking bounds.
+	///
+	/// # Safety
+	/// Does not check if `x` or `y` are beyond the bounds of the buffer.
+	pub unsafe fn get_pixel_unchecked(&self, x: u64, y: u64) -> Color {
+		if !self.color {
+			return Color::grey(unsafe { self.pixel_ptr(x, y).read_volatile() });
+		}
+
+		let value = unsafe { self.read_pixel(x, y) };
+
+		// Expands a channel back out to 8 bits.
+		let channel = |size: u64, shift: u64| {
+			let max = (1u32 << size) - 1;
+			(((value >> shift) & max) * 255 / max) as u8
+		};
+
+		Color {
+			r: channel(self.red_mask, self.red_shift),
+			g: channel(self.green_mask, self.green_shift),
+			b: channel(self.blue_mask, self.blue_shift),
+		}
+	}
+
+	/// Blends a color over a pixel with the given opacity (`0` being fully
+	/// transparent, `255` being fully opaque), as with [`blend`].
+	///
+	/// Pixels falling outside of the buffer are clipped.
+	pub fn blend_pixel(&self, x: u64, y: u64, color: Color, alpha: u8) {
+		if !Rect::of(self).contains(x, y) {
+			return;
+		}
+
+		// SAFETY: We've checked the bounds above.
+		unsafe {
+			let dst = self.get_pixel_unchecked(x, y);
+			self.set_pixel_unchecked(x, y, color.blended_over(dst, alpha));
+		}
+	}
+
+	/// Draws a vertical line from `y1` to `y2`, inclusive of both.
+	///
+	/// As with all of the drawing primitives that take a pair of corners
+	/// (see [`Rect::from_corners`]), the ends may be given in either order;
+	/// the line always spans from one to the other, so is never empty.
+	/// Pixels falling outside of the buffer are clipped.
+	pub fn draw_vline(&self, x: u64, y1: u64, y2: u64, level: u8) {
+		self.draw_vline_color(x, y1, y2, Color::grey(level));
+	}
+
+	/// Draws a vertical line in a color.
+	///
+	/// See [`Self::draw_vline`].
+	pub fn draw_vline_color(&self, x: u64, y1: u64, y2: u64, color: Color) {
+		self.fill_rect_color(&Rect::from_corners(x, y1, x, y2), color);
+	}
+
+	/// Draws a horizontal line from `x1` to `x2`, inclusive of both.
+	///
+	/// See [`Self::draw_vline`].
+	pub fn draw_hline(&self, x1: u64, x2: u64, y: u64, level: u8) {
+		self.draw_hline_color(x1, x2, y, Color::grey(level));
+	}
+
+	/// Draws a horizontal line in a color.
+	///
+	/// See [`Self::draw_vline`].
+	pub fn draw_hline_color(&self, x1: u64, x2: u64, y: u64, color: Color) {
+		self.fill_rect_color(&Rect::from_corners(x1, y, x2, y), color);
+	}
+
+	/// Draws a straight line between two points (inclusive of both), a
+	/// pixel per step along its longer axis, as Bresenham's algorithm does.
+	///
+	/// The line is clipped to the buffer before it's drawn (see
+	/// [`walk_line`]), so only its pixels within the buffer are walked,
+	/// however far outside of it its ends lie.
+	#[cfg_attr(not(all(test, feature = "mock-vbuf")), expect(dead_code))]
+	pub fn draw_line(&self, x0: u64, y0: u64, x1: u64, y1: u64, level: u8) {
+		walk_line((x0, y0), (x1, y1), self.width, self.height, |x, y| {
+			// SAFETY: Only points within the buffer are walked.
+			unsafe {
+				self.set_grey_pixel_unchecked(x, y, level);
+			}
+		});
+	}
+
+	/// Draws an anti-aliased line between two points (inclusive of both),
+	/// using Xiaolin Wu's algorithm.
+	///
+	/// Rather than overwriting them, each pixel the line passes through is
+	/// blended towards `level` by how much of it the line covers (so the
+	/// buffer is read back; see [`Self::get_grey_pixel`]). As with
+	/// [`Self::draw_line`], the line is clipped to the buffer before it's
+	/// drawn (see [`LineSteps::clip`]), however far outside of it its ends lie.
+	#[cfg_attr(not(all(test, feature = "mock-vbuf")), expect(dead_code))]
+	pub fn draw_line_aa(&self, x0: u64, y0: u64, x1: u64, y1: u64, level: u8) {
+		// Blends `coverage` of the line into a pixel.
+		let plot = |x: u64, y: u64, coverage: u8| {
+			if let Some(dst) = self.get_grey_pixel(x, y) {
+				// SAFETY: The pixel was just read, so lies within the buffer.
+				unsafe {
+					self.set_grey_pixel_unchecked(x, y, blend(level, dst, coverage));
+				}
+			}
+		};
+
+		// Only the steps whose (rounded) points lie within the buffer are
+		// walked, as with `draw_line`.
+		let line = LineSteps::new((x0, y0), (x1, y1), self.width, self.height);
+		let Some((first, last)) = line.clip() else {
+			return;
+		};
+
+		for k in first..=last {
+			let major = line.major.along(k);
+			// The line crosses the step `frac` 256ths of the way from the
+			// pixel `whole` pixels along the minor axis to the next one
+			// (which lies within the line's ends whenever `frac` isn't 0),
+			// so its coverage is split between the two.
+			let (whole, frac) = line.moved(k);
+			let near = line.minor.along(whole);
+
+			let plot_minor = |minor: u64, coverage: u8| {
+				if line.x_major {
+					plot(major, minor, coverage);
+				} else {
+					plot(minor, major, coverage);
+				}
+			};
+
+			plot_minor(near, 255 - frac);
+			if frac > 0 {
+				plot_minor(line.minor.along(whole + 1), frac);
+			}
+		}
+	}
+
+	/// Draws a box between two corners (inclusive of both, in either order).
+	#[cfg_attr(not(all(test, feature = "mock-vbuf")), expect(dead_code))]
+	pub fn draw_box(&self, x1: u64, y1: u64, x2: u64, y2: u64, level: u8) {
+		self.draw_rect(&Rect::from_corners(x1, y1, x2, y2), level);
+	}
+
+	/// Draws the outline of a rectangle.
+	///
+	/// As with [`Self::fill_rect`], the rectangle's right and bottom edges
+	/// are exclusive, so an empty rectangle draws nothing.
+	pub fn draw_rect(&self, rect: &Rect, level: u8) {
+		self.draw_rect_color(rect, Color::grey(level));
+	}
+
+	/// Draws the outline of a rectangle in a color.
+	///
+	/// See [`Self::draw_rect`].
+	pub fn draw_rect_color(&self, rect: &Rect, color: Color) {
+		if rect.is_empty() {
+			return;
+		}
+
+		let (right, bottom) = (rect.right() - 1, rect.bottom() - 1);
+		self.draw_hline_color(rect.x, right, rect.y, color);
+		self.draw_hline_color(rect.x, right, bottom, color);
+		self.draw_vline_color(rect.x, rect.y, bottom, color);
+		self.draw_vline_color(right, rect.y, bottom, color);
+	}
+
+	/// Draws a box with rounded corners, each a quarter circle of the
+	/// given radius (drawn with the midpoint circle algorithm).
+	///
+	/// The radius is clamped to half the box's smaller dimension; a zero
+	/// radius draws the same box as [`Self::draw_box`]. Pixels falling
+	/// outside of the buffer are clipped.
+	pub fn draw_round_box(&self, x1: u64, y1: u64, x2: u64, y2: u64, radius: u64, level: u8) {
+		// As with the other boxes, the corners may be given in either order.
+		let (x1, x2) = (x1.min(x2), x1.max(x2));
+		let (y1, y2) = (y1.min(y2), y1.max(y2));
+		let radius = radius.min((x2 - x1) / 2).min((y2 - y1) / 2);
+
+		// The centers of the corners' circles.
+		let (left, right) = (x1 + radius, x2 - radius);
+		let (top, bottom) = (y1 + radius, y2 - radius);
+
+		self.draw_hline(left, right, y1, level);
+		self.draw_hline(left, right, y2, level);
+		self.draw_vline(x1, top, bottom, level);
+		self.draw_vline(x2, top, bottom, level);
+
+		// Plots a point of each corner's arc, `dx` and `dy` outwards
+		// from its center.
+		let plot = |dx: u64, dy: u64| {
+			self.set_grey_pixel(left - dx, top - dy, level);
+			self.set_grey_pixel(right + dx, top - dy, level);
+			self.set_grey_pixel(left - dx, bottom + dy, level);
+			self.set_grey_pixel(right + dx, bottom + dy, level);
+		};
+
+		// Mirror each point of one octant of the circle into the other
+		// octant of the quarter.
+		circle_octant(radius, |dx, dy| {
+			plot(dx, dy);
+			plot(dy, dx);
+		});
+	}
+
+	/// Draws a box in a color.
+	///
+	/// See [`Self::draw_box`].
+	#[cfg_attr(not(all(test, feature = "mock-vbuf")), expect(dead_code))]
+	pub fn draw_box_color(&self, x1: u64, y1: u64, x2: u64, y2: u64, color: Color) {
+		self.draw_rect_color(&Rect::from_corners(x1, y1, x2, y2), color);
+	}
+
+	/// Fills an area between two corners (inclusive of both, in either order)
+	/// with a level.
+	#[cfg_attr(not(all(test, feature = "mock-vbuf")), expect(dead_code))]
+	pub fn fill_box(&self, x1: u64, y1: u64, x2: u64, y2: u64, level: u8) {
+		self.fill_box_color(x1, y1, x2, y2, Color::grey(level));
+	}
+
+	/// Fills an area with a color.
+	///
+	/// See [`Self::fill_box`].
+	pub fn fill_box_color(&self, x1: u64, y1: u64, x2: u64, y2: u64, color: Color) {
+		self.fill_rect_color(&Rect::from_corners(x1, y1, x2, y2), color);
+	}
+
+	/// Copies a `w` by `h` block of pixels from `(src_x, src_y)` to `(dst_x, dst_y)`.
+	///
+	/// The source and destination may overlap; the pixels are copied in
+	/// whichever order leaves the source intact until it's been read. Both
+	/// are clipped to the buffer, with the block shrunk to fit whichever
+	/// extends further beyond it.
+	pub fn copy_rect(&self, src_x: u64, src_y: u64, dst_x: u64, dst_y: u64, w: u64, h: u64) {
+		let w = w
+			.min(self.width.saturating_sub(src_x))
+			.min(self.width.saturating_sub(dst_x));
+		let h = h
+			.min(self.height.saturating_sub(src_y))
+			.min(self.height.saturating_sub(dst_y));
+		if w == 0 || h == 0 {
+			return;
+		}
+
+		self.mark_damaged(&Rect::new(dst_x, dst_y, w, h));
+
+		let len = (w * self.bytes_per_pixel) as usize;
+
+		let copy_row = |row: u64| {
+			// SAFETY: Both rows lie within the (clipped) block.
+			unsafe {
+				let src = self.pixel_ptr(src_x, src_y + row);
+				let dst = self.pixel_ptr(dst_x, dst_y + row);
+
+				// Moving right within the same row must copy right-to-left,
+				// or else the source is overwritten before it's read.
+				if dst > src {
+					for i in (0..len).rev() {
+						dst.add(i).write_volatile(src.add(i).read_volatile());
+					}
+				} else {
+					for i in 0..len {
+						dst.add(i).write_volatile(src.add(i).read_volatile());
+					}
+				}
+			}
+		};
+
+		// Likewise, moving down must copy bottom-to-top.
+		if dst_y > src_y {
+			(0..h).rev().for_each(copy_row);
+		} else {
+			(0..h).for_each(copy_row);
+		}
+	}
+
+	/// Moves the contents of a rectangle up by `dy` rows, within the rectangle.
+	///
+	/// The bottom `dy` rows of the rectangle are left as they were.
+	pub fn scroll_up(&self, rect: &Rect, dy: u64) {
+		let rect = rect.intersect(&Rect::of(self));
+		if rect.is_empty() || dy == 0 || dy >= rect.h {
+			return;
+		}
+
+		self.copy_rect(rect.x, rect.y + dy, rect.x, rect.y, rect.w, rect.h - dy);
+	}
+
+	/// Fills a rectangle with a level.
+	///
+	/// Unlike [`Self::fill_box`], the rectangle's right and bottom edges
+	/// are exclusive, so an empty rectangle draws nothing. The rectangle is
+	/// clipped to the buffer.
+	pub fn fill_rect(&self, rect: &Rect, level: u8) {
+		self.fill_rect_color(rect, Color::grey(level));
+	}
+
+	/// Fills a rectangle with a color.
+	///
+	/// See [`Self::fill_rect`].
+	pub fn fill_rect_color(&self, rect: &Rect, color: Color) {
+		let rect = rect.intersect(&Rect::of(self));
+		if rect.is_empty() {
+			return;
+		}
+
+		self.mark_damaged(&rect);
+
+		// Unless it's dithered, every pixel is the same, so the pixel is
+		// packed once and each row filled with copies of it, a word at a time.
+		if let Some((pixel, len)) = self.fixed_pixel(color) {
+			let row_len = (rect.w * self.bytes_per_pixel) as usize;
+
+			for y in rect.y..rect.bottom() {
+				// SAFETY: The rectangle has been clipped to the buffer above.
+				unsafe {
+					fill_pixels(self.pixel_ptr(rect.x, y), row_len, &pixel[..len]);
+				}
+			}
+
+			return;
+		}
+
+		// Otherwise, each pixel is packed and written in turn, walking a
+		// pointer along each row (and down to the next) rather than working
+		// out each pixel's address afresh.
+		let stride = self.stride as usize;
+		let bytes_per_pixel = self.bytes_per_pixel as usize;
+
+		// SAFETY: The rectangle has been clipped to the buffer above, so
+		// SAFETY: every pixel written lies within it. The pointers step (with
+		// SAFETY: wrapping arithmetic) past its last pixel and row, but
+		// SAFETY: aren't written to once there.
+		unsafe {
+			let mut row = self.pixel_ptr(rect.x, rect.y);
+
+			for y in rect.y..rect.bottom() {
+				let mut pixel = row;
+
+				for x in rect.x..rect.right() {
+					self.write_pixel_at(pixel, self.pack(x, y, color));
+					pixel = pixel.wrapping_add(bytes_per_pixel);
+				}
+
+				row = row.wrapping_add(stride);
+			}
+		}
+	}
+
+	/// Draws a 1-bit packed bitmap (e.g. an icon) with its top-left corner at `(x, y)`.
+	///
+	/// Each row of `bits` is packed most-significant bit first, and padded
+	/// to a whole number of bytes. Set bits are blended over whatever is
+	/// behind them in `fg`, with the given opacity (as with
+	/// [`Self::blend_pixel`]); unset bits are transparent and leave whatever
+	/// is behind them untouched.
+	///
+	/// Pixels falling outside of the buffer are clipped, as are any rows
+	/// for which `bits` is too short.
+	#[expect(clippy::too_many_arguments)]
+	pub fn draw_bitmap(&self, x: u64, y: u64, w: u64, h: u64, bits: &[u8], fg: Color, alpha: u8) {
+		if w == 0 || h == 0 {
+			return;
+		}
+
+		let row_bytes = w.div_ceil(8) as usize;
+		let clip = Rect::new(x, y, w, h).intersect(&Rect::of(self));
+
+		for (row, row_bits) in bits.chunks_exact(row_bytes).take(h as usize).enumerate() {
+			let py = y + row as u64;
+			if py < clip.y || py >= clip.bottom() {
+				continue;
+			}
+
+			for col in 0..w {
+				let px = x + col;
+				if px < clip.x || px >= clip.right() {
+					continue;
+				}
+
+				if row_bits[(col / 8) as usize] & (0x80 >> (col % 8)) != 0 {
+					self.blend_pixel(px, py, fg, alpha);
+				}
+			}
+		}
+	}
+
+	/// Copies a `w` by `h` image of 8-bit grey levels, one byte per pixel
+	/// row by row, into the buffer with its top-left corner at `(x, y)`.
+	///
+	/// Pixels falling outside of the buffer are clipped. Nothing is drawn
+	/// unless `src` is exactly `w * h` bytes.
+	#[cfg_attr(not(all(test, feature = "mock-vbuf")), expect(dead_code))]
+	pub fn blit_grey(
+		&self,
+		x: u64,
+		y: u64,
+		w: u64,
+		h: u64,
+		src: &[u8],
+	) -> Result<(), BlitSizeMismatch> {
+		self.blit(x, y, w, h, src, 1, |px| Color::grey(px[0]))
+	}
+
+	/// Copies a `w` by `h` image of 8-bit RGB pixels, three bytes (red,
+	/// green then blue) per pixel row by row, into the buffer with its
+	/// top-left corner at `(x, y)`.
+	///
+	/// The pixels are converted to the buffer's format (or to grey, if it
+	/// can't show color). Pixels falling outside of the buffer are clipped.
+	/// Nothing is drawn unless `src` is exactly `w * h * 3` bytes.
+	#[cfg_attr(not(all(test, feature = "mock-vbuf")), expect(dead_code))]
+	pub fn blit_rgb(
+		&self,
+		x: u64,
+		y: u64,
+		w: u64,
+		h: u64,
+		src: &[u8],
+	) -> Result<(), BlitSizeMismatch> {
+		self.blit(x, y, w, h, src, 3, |px| {
+			Color {
+				r: px[0],
+				g: px[1],
+				b: px[2],
+			}
+		})
+	}
+
+	/// Copies an image of `channels` bytes per pixel into the buffer,
+	/// converting each pixel's bytes with `pixel`.
+	///
+	/// See [`Self::blit_grey`] and [`Self::blit_rgb`].
+	#[expect(clippy::too_many_arguments)]
+	fn blit(
+		&self,
+		x: u64,
+		y: u64,
+		w: u64,
+		h: u64,
+		src: &[u8],
+		channels: usize,
+		pixel: impl Fn(&[u8]) -> Color,
+	) -> Result<(), BlitSizeMismatch> {
+		let stride = w as usize * channels;
+		let expected = stride * h as usize;
+		if src.len() != expected {
+			return Err(BlitSizeMismatch {
+				expected,
+				actual: src.len(),
+			});
+		}
+
+		let clip = Rect::new(x, y, w, h).intersect(&Rect::of(self));
+
+		for py in clip.y..clip.bottom() {
+			let row = &src[(py - y) as usize * stride..][..stride];
+
+			for px in clip.x..clip.right() {
+				let offset = (px - x) as usize * channels;
+
+				// SAFETY: We clipped to the buffer bounds above.
+				unsafe {
+					self.set_pixel_unchecked(px, py, pixel(&row[offset..offset + channels]));
+				}
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Draws the outline of a circle of radius `r` centered on `(cx, cy)`,
+	/// using the midpoint circle algorithm.
+	///
+	/// Pixels falling outside of the buffer (on any side) are clipped, so a
+	/// circle near an edge or corner draws only its visible arc. A zero
+	/// radius draws a single pixel.
+	#[cfg_attr(not(all(test, feature = "mock-vbuf")), expect(dead_code))]
+	pub fn draw_circle(&self, cx: u64, cy: u64, r: u64, level: u8) {
+		let plot = |x: Option<u64>, y: Option<u64>| {
+			if let (Some(x), Some(y)) = (x, y) {
+				self.set_grey_pixel(x, y, level);
+			}
+		};
+
+		circle_octant(r, |dx, dy| {
+			// Mirror the point into each of the circle's eight octants.
+			for (dx, dy) in [(dx, dy), (dy, dx)] {
+				plot(cx.checked_add(dx), cy.checked_add(dy));
+				plot(cx.checked_sub(dx), cy.checked_add(dy));
+				plot(cx.checked_add(dx), cy.checked_sub(dy));
+				plot(cx.checked_sub(dx), cy.checked_sub(dy));
+			}
+		});
+	}
+
+	/// Fills a circle of radius `r` centered on `(cx, cy)`.
+	///
+	/// Covers the same pixels as [`Self::draw_circle`] does, and everything
+	/// within them. As with it, pixels falling outside of the buffer are
+	/// clipped, and a zero radius draws a single pixel.
+	#[cfg_attr(not(all(test, feature = "mock-vbuf")), expect(dead_code))]
+	pub fn fill_circle(&self, cx: u64, cy: u64, r: u64, level: u8) {
+		// Fills the row `dy` above and below the center, `dx` either side of it.
+		let span = |dx: u64, dy: u64| {
+			let (x1, x2) = (cx.saturating_sub(dx), cx.saturating_add(dx));
+
+			for y in [cy.checked_add(dy), cy.checked_sub(dy)]
+				.into_iter()
+				.flatten()
+			{
+				self.draw_hline(x1, x2, y, level);
+			}
+		};
+
+		circle_octant(r, |dx, dy| {
+			span(dx, dy);
+			span(dy, dx);
+		});
+	}
+}
+
+/// Walks one octant of a circle of radius `r` with the midpoint circle
+/// algorithm, from its rightmost point (`(r, 0)`) up to the diagonal,
+/// calling `each` with every point's offset from the center.
+///
+/// Mirroring each point (i.e. swapping and negating its offsets) gives
+/// the rest of the circle.
+fn circle_octant(r: u64, mut each: impl FnMut(u64, u64)) {
+	#[expect(clippy::cast_possible_wrap)]
+	let (mut x, mut y, mut err) = (r as i64, 0i64, 1 - r as i64);
+
+	while x >= y {
+		each(x.unsigned_abs(), y.unsigned_abs());
+
+		y += 1;
+		if err < 0 {
+			err += 2 * y + 1;
+		} else {
+			x -= 1;
+			err += 2 * (y - x) + 1;
+		}
+	}
+}
+
+/// Walks the points of a straight line from `from` to `to` (inclusive of
+/// both) that lie within a `width` by `height` area, calling `each` with
+/// every one.
+///
+/// The line is stepped a pixel at a time along its longer (major) axis,
+/// with the other (minor) coordinate rounded to the nearest pixel at each
+/// step, as Bresenham's algorithm does. Before anything is walked, the
+/// steps are clipped to those that land within the area (see
+/// [`LineSteps::clip`]), so a line whose ends lie far outside of the area
+/// costs no more than its visible part.
+fn walk_line(
+	from: (u64, u64),
+	to: (u64, u64),
+	width: u64,
+	height: u64,
+	mut each: impl FnMut(u64, u64),
+) {
+	let line = LineSteps::new(from, to, width, height);
+	let Some((first, last)) = line.clip() else {
+		return;
+	};
+
+	for k in first..=last {
+		let (a, b) = (line.major.along(k), line.minor.along(line.offset(k)));
+		if line.x_major { each(a, b) } else { each(b, a) }
+	}
+}
+
+/// An axis a line is stepped along (see [`LineSteps`]).
+#[derive(Clone, Copy)]
+struct LineAxis {
+	/// The line's starting coordinate along the axis.
+	start: u64,
+	/// Whether the line heads towards larger coordinates along the axis.
+	up:    bool,
+	/// The size of the area along the axis, which coordinates must be
+	/// less than.
+	limit: u64,
+}
+
+impl LineAxis {
+	/// Returns the coordinate along the axis after moving `by`.
+	fn along(self, by: u64) -> u64 {
+		if self.up {
+			self.start + by
+		} else {
+			self.start - by
+		}
+	}
+
+	/// Returns the range of moves along the axis, from its start, that
+	/// keep the coordinate within its limit (if any do).
+	fn within(self) -> Option<(u64, u64)> {
+		let last = self.limit.checked_sub(1)?;
+		if self.up {
+			Some((0, last.checked_sub(self.start)?))
+		} else {
+			Some((self.start.saturating_sub(last), self.start))
+		}
+	}
+}
+
+/// A straight line from one point to another (inclusive of both), as
+/// stepped a pixel at a time along its longer (major) axis, within a
+/// bounded area.
+///
+/// The minor coordinate is worked out afresh at each step, in 128-bit
+/// arithmetic, so nothing overflows however far apart the ends are.
+struct LineSteps {
+	/// Whether the major axis is the x axis.
+	x_major: bool,
+	/// The major axis.
+	major:   LineAxis,
+	/// The minor axis.
+	minor:   LineAxis,
+	/// The number of steps along the major axis, from one end to the other.
+	steps:   u64,
+	/// How far the minor coordinate moves from one end to the other.
+	rise:    u64,
+}
+
+impl LineSteps {
+	/// Steps the line from `from` to `to` within a `width` by `height` area.
+	fn new(from: (u64, u64), to: (u64, u64), width: u64, height: u64) -> Self {
+		let (dx, dy) = (from.0.abs_diff(to.0), from.1.abs_diff(to.1));
+		let x_major = dx >= dy;
+
+		let x_axis = LineAxis {
+			start: from.0,
+			up:    to.0 >= from.0,
+			limit: width,
+		};
+		let y_axis = LineAxis {
+			start: from.1,
+			up:    to.1 >= from.1,
+			limit: height,
+		};
+
+		if x_major {
+			Self {
+				x_major,
+				major: x_axis,
+				minor: y_axis,
+				steps: dx,
+				rise: dy,
+			}
+		} else {
+			Self {
+				x_major,
+				major: y_axis,
+				minor: x_axis,
+				steps: dy,
+				rise: dx,
+			}
+		}
+	}
+
+	/// Returns how far the minor coordinate has moved after `k` steps, as
+	/// whole pixels and the 256ths of a pixel past them (rounded down).
+	fn moved(&self, k: u64) -> (u64, u8) {
+		if self.steps == 0 {
+			return (0, 0);
+		}
+
+		let moved = u128::from(k) * u128::from(self.rise);
+		let steps = u128::from(self.steps);
+		(
+			(moved / steps) as u64,
+			((moved % steps) * 256 / steps) as u8,
+		)
+	}
+
+	/// Returns how far the minor coordinate has moved after `k` steps,
+	/// rounded to the nearest pixel (with halves rounded away from the
+	/// start).
+	fn offset(&self, k: u64) -> u64 {
+		if self.steps == 0 {
+			return 0;
+		}
+
+		let moved = u128::from(k) * u128::from(self.rise);
+		let steps = u128::from(self.steps);
+		(moved / steps + u128::from((moved % steps) * 2 >= steps)) as u64
+	}
+
+	/// Returns the range of steps (inclusive of both ends) whose points,
+	/// as rounded by [`Self::offset`], lie within the area, if any do.
+	///
+	/// This is much as Liang-Barsky clipping clips a line's parameter.
+	fn clip(&self) -> Option<(u64, u64)> {
+		// Returns the first step at which `pred` holds, given that once it
+		// does, it holds for every step after.
+		let first_step = |pred: &dyn Fn(u64) -> bool| -> Option<u64> {
+			if !pred(self.steps) {
+				return None;
+			}
+
+			let (mut lo, mut hi) = (0, self.steps);
+			while lo < hi {
+				let mid = lo + (hi - lo) / 2;
+				if pred(mid) {
+					hi = mid;
+				} else {
+					lo = mid + 1;
+				}
+			}
+
+			Some(lo)
+		};
+
+		let (major_lo, major_hi) = self.major.within()?;
+		let (minor_lo, minor_hi) = self.minor.within()?;
+
+		// The minor coordinate only ever moves one way, so the steps that
+		// keep it within its limit form a range, found by searching for its
+		// ends.
+		let first = first_step(&|k| self.offset(k) >= minor_lo)?;
+		let last = match first_step(&|k| self.offset(k) > minor_hi) {
+			Some(0) => return None,
+			Some(past) => past - 1,
+			None => self.steps,
+		};
+
+		let first = first.max(major_lo);
+		let last = last.min(major_hi).min(self.steps);
+		(first <= last).then_some((first, last))
+	}
+}
+
+#[cfg(all(test, feature = "mock-vbuf"))]
+mod tests;
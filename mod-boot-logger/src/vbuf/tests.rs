@@ -0,0 +1,960 @@
+//! Tests for the drawing primitives, against a [`MockVbuf`].
+
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use oro::syscall::Error;
+
+use super::{
+	Color, RawFramebuffer, Rect, Vbuf, VbufError, VbufField, VbufSource, acquire_from, blend,
+	quantize, read_field_with,
+};
+use crate::mock_vbuf::{MockFormat, MockVbuf};
+
+/// Joins the rows of a picture (as drawn by [`MockVbuf::render`]).
+fn picture(rows: &[&str]) -> String {
+	rows.iter().flat_map(|row| [*row, "\n"]).collect()
+}
+
+#[test]
+fn packs_24_bit_pixels() {
+	let mock = MockVbuf::new(2, 1, MockFormat::Rgb888);
+	mock.vbuf().set_pixel(
+		1,
+		0,
+		Color {
+			r: 0x12,
+			g: 0x34,
+			b: 0x56,
+		},
+	);
+
+	assert_eq!(mock.raw_pixel(0, 0), 0);
+	assert_eq!(mock.raw_pixel(1, 0), 0x12_3456);
+}
+
+#[test]
+fn packs_32_bit_pixels_with_an_opaque_spare_byte() {
+	let mock = MockVbuf::new(1, 1, MockFormat::Xrgb8888);
+	mock.vbuf().set_pixel(
+		0,
+		0,
+		Color {
+			r: 0x12,
+			g: 0x34,
+			b: 0x56,
+		},
+	);
+
+	assert_eq!(mock.raw_pixel(0, 0), 0xFF12_3456);
+}
+
+#[test]
+fn packs_16_bit_pixels() {
+	let mock = MockVbuf::new(3, 1, MockFormat::Rgb565);
+	let vbuf = mock.vbuf();
+	vbuf.set_pixel(
+		0,
+		0,
+		Color {
+			r: 0xFF,
+			g: 0,
+			b: 0,
+		},
+	);
+	vbuf.set_pixel(
+		1,
+		0,
+		Color {
+			r: 0,
+			g: 0xFF,
+			b: 0,
+		},
+	);
+	vbuf.set_pixel(
+		2,
+		0,
+		Color {
+			r: 0,
+			g: 0,
+			b: 0xFF,
+		},
+	);
+
+	assert_eq!(mock.raw_pixel(0, 0), 0xF800);
+	assert_eq!(mock.raw_pixel(1, 0), 0x07E0);
+	assert_eq!(mock.raw_pixel(2, 0), 0x001F);
+}
+
+#[test]
+fn packs_15_bit_pixels_into_two_bytes() {
+	let mock = MockVbuf::new(3, 1, MockFormat::Rgb555);
+	let vbuf = mock.vbuf();
+	assert_eq!(vbuf.bytes_per_pixel, 2);
+	assert!(vbuf.check_supported().is_ok());
+
+	vbuf.set_pixel(
+		0,
+		0,
+		Color {
+			r: 0xFF,
+			g: 0,
+			b: 0,
+		},
+	);
+	vbuf.set_pixel(
+		1,
+		0,
+		Color {
+			r: 0,
+			g: 0xFF,
+			b: 0,
+		},
+	);
+	vbuf.set_pixel(
+		2,
+		0,
+		Color {
+			r: 0,
+			g: 0,
+			b: 0xFF,
+		},
+	);
+
+	// The top bit isn't part of the pixel, so stays clear.
+	assert_eq!(mock.raw_pixel(0, 0), 0x7C00);
+	assert_eq!(mock.raw_pixel(1, 0), 0x03E0);
+	assert_eq!(mock.raw_pixel(2, 0), 0x001F);
+}
+
+#[test]
+fn converts_colors_to_grey() {
+	let mock = MockVbuf::new(2, 1, MockFormat::Grey8);
+	mock.vbuf().set_pixel(0, 0, Color::grey(0x80));
+	mock.vbuf().set_pixel(
+		1,
+		0,
+		Color {
+			r: 0xFF,
+			g: 0,
+			b: 0,
+		},
+	);
+
+	assert_eq!(mock.raw_pixel(0, 0), 0x80);
+	assert_eq!(
+		mock.raw_pixel(1, 0),
+		u32::from(
+			Color {
+				r: 0xFF,
+				g: 0,
+				b: 0,
+			}
+			.luma()
+		)
+	);
+}
+
+#[test]
+fn reads_back_what_was_written() {
+	let mock = MockVbuf::new(2, 2, MockFormat::Xrgb8888);
+	let vbuf = mock.vbuf();
+	let color = Color {
+		r: 0x12,
+		g: 0x34,
+		b: 0x56,
+	};
+	vbuf.set_pixel(1, 1, color);
+
+	assert_eq!(vbuf.get_pixel(1, 1), Some(color));
+	assert_eq!(vbuf.get_pixel(0, 0), Some(Color::grey(0)));
+	assert_eq!(vbuf.get_pixel(2, 0), None);
+}
+
+#[test]
+fn blends_by_alpha() {
+	assert_eq!(blend(0xFF, 0x00, 0xFF), 0xFF);
+	assert_eq!(blend(0xFF, 0x00, 0x00), 0x00);
+	assert_eq!(blend(0xFF, 0x00, 0x80), 0x80);
+	assert_eq!(blend(0x00, 0xFF, 0x80), 0x7F);
+	assert_eq!(blend(0x40, 0x40, 0x33), 0x40);
+}
+
+#[test]
+fn blends_pixels_over_what_is_there() {
+	let mock = MockVbuf::new(1, 1, MockFormat::Rgb888);
+	let vbuf = mock.vbuf();
+	vbuf.set_pixel(
+		0,
+		0,
+		Color {
+			r: 0xFF,
+			g: 0,
+			b: 0,
+		},
+	);
+	vbuf.blend_pixel(
+		0,
+		0,
+		Color {
+			r: 0,
+			g: 0,
+			b: 0xFF,
+		},
+		0x80,
+	);
+
+	assert_eq!(
+		vbuf.get_pixel(0, 0),
+		Some(Color {
+			r: 0x7F,
+			g: 0,
+			b: 0x80,
+		})
+	);
+}
+
+#[test]
+fn draws_lines_inclusive_of_both_ends() {
+	let mock = MockVbuf::new(5, 3, MockFormat::Grey8);
+	mock.vbuf().draw_hline(1, 3, 0, 0xFF);
+	mock.vbuf().draw_vline(4, 0, 2, 0xFF);
+
+	assert_eq!(
+		mock.render(),
+		picture(&[
+			".####", //
+			"....#", "....#",
+		])
+	);
+}
+
+#[test]
+fn draws_inverted_spans_the_same_way_round() {
+	let forwards = MockVbuf::new(6, 5, MockFormat::Grey8);
+	forwards.vbuf().draw_hline(1, 4, 0, 0xFF);
+	forwards.vbuf().fill_box(1, 2, 3, 4, 0xFF);
+
+	let backwards = MockVbuf::new(6, 5, MockFormat::Grey8);
+	backwards.vbuf().draw_hline(4, 1, 0, 0xFF);
+	backwards.vbuf().fill_box(3, 4, 1, 2, 0xFF);
+
+	assert_eq!(backwards.render(), forwards.render());
+	assert_eq!(forwards.lit_count(), 4 + 9);
+}
+
+#[test]
+fn draws_nothing_for_empty_rects() {
+	let mock = MockVbuf::new(4, 4, MockFormat::Grey8);
+	mock.vbuf().fill_rect(&Rect::new(1, 1, 0, 2), 0xFF);
+	mock.vbuf().fill_rect(&Rect::new(1, 1, 2, 0), 0xFF);
+	mock.vbuf().draw_rect(&Rect::new(1, 1, 0, 0), 0xFF);
+
+	assert_eq!(mock.lit_count(), 0);
+}
+
+#[test]
+fn draws_a_single_pixel_for_a_single_point() {
+	let mock = MockVbuf::new(3, 3, MockFormat::Grey8);
+	mock.vbuf().fill_box(1, 1, 1, 1, 0xFF);
+	mock.vbuf().draw_box(1, 1, 1, 1, 0xFF);
+
+	assert_eq!(mock.lit_count(), 1);
+	assert!(mock.is_lit(1, 1));
+}
+
+#[test]
+fn clips_boxes_to_the_buffer() {
+	let mock = MockVbuf::new(4, 3, MockFormat::Grey8);
+	mock.vbuf().draw_box(2, 1, 100, 100, 0xFF);
+	mock.vbuf().fill_box(u64::MAX - 1, 0, u64::MAX, 0, 0xFF);
+
+	assert_eq!(
+		mock.render(),
+		picture(&[
+			"....", //
+			"..##", "..#.",
+		])
+	);
+}
+
+#[test]
+fn leaves_row_padding_alone() {
+	let mock = MockVbuf::with_padding(3, 2, MockFormat::Rgb888, 5);
+	mock.vbuf().fill_box(0, 0, 10, 10, 0xFF);
+
+	assert_eq!(mock.lit_count(), 6);
+	assert_eq!(mock.padding(0), &[0; 5]);
+	assert_eq!(mock.padding(1), &[0; 5]);
+}
+
+#[test]
+fn fills_colored_rects_within_padded_rows() {
+	let mock = MockVbuf::with_padding(4, 3, MockFormat::Rgb888, 3);
+	let color = Color {
+		r: 0x12,
+		g: 0x34,
+		b: 0x56,
+	};
+	mock.vbuf().fill_rect_color(&Rect::new(1, 1, 10, 10), color);
+
+	assert_eq!(
+		mock.render(),
+		picture(&[
+			"....", //
+			".###", ".###",
+		])
+	);
+	assert_eq!(mock.raw_pixel(3, 2), 0x12_3456);
+	for y in 0..3 {
+		assert_eq!(mock.padding(y), &[0; 3]);
+	}
+}
+
+#[test]
+fn draws_shallow_lines_without_gaps() {
+	let mock = MockVbuf::new(8, 3, MockFormat::Grey8);
+	mock.vbuf().draw_line(0, 0, 7, 2, 0xFF);
+
+	assert_eq!(
+		mock.render(),
+		picture(&[
+			"##......", //
+			"..####..", "......##",
+		])
+	);
+}
+
+#[test]
+fn draws_steep_lines_without_gaps() {
+	let mock = MockVbuf::new(3, 8, MockFormat::Grey8);
+	mock.vbuf().draw_line(2, 7, 0, 0, 0xFF);
+
+	// One pixel per row.
+	for y in 0..8 {
+		assert_eq!((0..3).filter(|&x| mock.is_lit(x, y)).count(), 1, "row {y}");
+	}
+	assert!(mock.is_lit(0, 0));
+	assert!(mock.is_lit(2, 7));
+}
+
+#[test]
+fn draws_diagonal_lines() {
+	let mock = MockVbuf::new(4, 4, MockFormat::Grey8);
+	mock.vbuf().draw_line(3, 0, 0, 3, 0xFF);
+
+	assert_eq!(
+		mock.render(),
+		picture(&[
+			"...#", //
+			"..#.", ".#..", "#...",
+		])
+	);
+}
+
+#[test]
+fn clips_lines_leaving_the_buffer() {
+	let mock = MockVbuf::new(5, 3, MockFormat::Grey8);
+	mock.vbuf().draw_line(100, 1, 2, 1, 0xFF);
+
+	assert_eq!(
+		mock.render(),
+		picture(&[
+			".....", //
+			"..###", ".....",
+		])
+	);
+}
+
+#[test]
+fn clips_lines_with_both_ends_outside_of_the_buffer() {
+	let mock = MockVbuf::new(8, 8, MockFormat::Grey8);
+	mock.vbuf().draw_line(10, 0, 0, 10, 0xFF);
+
+	assert_eq!(
+		mock.render(),
+		picture(&[
+			"........", //
+			"........", "........", ".......#", "......#.", ".....#..", "....#...", "...#....",
+		])
+	);
+}
+
+#[test]
+fn clips_lines_to_the_pixels_the_whole_line_would_draw() {
+	// Each line is drawn whole to a large buffer, and clipped to a small one
+	// (as its top-left corner); the small buffer should show the same pixels.
+	let lines = [
+		(3, 40, 40, 9),
+		(40, 9, 3, 40),
+		(0, 27, 50, 2),
+		(13, 60, 2, 0),
+		(20, 20, 0, 0),
+		(47, 5, 5, 6),
+		(1, 50, 17, 1),
+	];
+
+	for (x0, y0, x1, y1) in lines {
+		let whole = MockVbuf::new(64, 64, MockFormat::Grey8);
+		whole.vbuf().draw_line(x0, y0, x1, y1, 0xFF);
+
+		let clipped = MockVbuf::new(16, 12, MockFormat::Grey8);
+		clipped.vbuf().draw_line(x0, y0, x1, y1, 0xFF);
+
+		for y in 0..12 {
+			for x in 0..16 {
+				assert_eq!(
+					clipped.is_lit(x, y),
+					whole.is_lit(x, y),
+					"({x}, {y}) of ({x0}, {y0}) to ({x1}, {y1})"
+				);
+			}
+		}
+	}
+}
+
+#[test]
+fn clips_lines_with_extreme_ends() {
+	let mock = MockVbuf::new(6, 4, MockFormat::Grey8);
+	let vbuf = mock.vbuf();
+
+	// These would take forever to walk (or overflow) if they weren't
+	// clipped first.
+	vbuf.draw_line(0, 0, u64::MAX, u64::MAX, 0xFF);
+	vbuf.draw_line(u64::MAX, 3, 2, 3, 0xFF);
+	vbuf.draw_line(u64::MAX, 0, 0, u64::MAX, 0xFF);
+	vbuf.draw_line(u64::MAX, u64::MAX, u64::MAX - 1, 0, 0xFF);
+
+	assert_eq!(
+		mock.render(),
+		picture(&[
+			"#.....", //
+			".#....", "..#...", "..####",
+		])
+	);
+}
+
+#[test]
+fn draws_single_pixel_lines() {
+	let mock = MockVbuf::new(3, 3, MockFormat::Grey8);
+	mock.vbuf().draw_line(1, 2, 1, 2, 0xFF);
+	mock.vbuf().draw_line(7, 7, 7, 7, 0xFF);
+
+	assert_eq!(
+		mock.render(),
+		picture(&[
+			"...", //
+			"...", ".#.",
+		])
+	);
+}
+
+#[test]
+fn draws_axis_aligned_anti_aliased_lines_at_full_coverage() {
+	let mock = MockVbuf::new(6, 3, MockFormat::Grey8);
+	mock.vbuf().draw_line_aa(100, 1, 2, 1, 0xFF);
+	mock.vbuf().draw_line_aa(0, 0, 0, 2, 0xFF);
+
+	assert_eq!(
+		mock.render(),
+		picture(&[
+			"#.....", //
+			"#.####", "#.....",
+		])
+	);
+	assert_eq!(mock.raw_pixel(5, 1), 0xFF);
+}
+
+#[test]
+fn splits_anti_aliased_lines_between_neighboring_pixels() {
+	let mock = MockVbuf::new(9, 6, MockFormat::Grey8);
+	mock.vbuf().draw_line_aa(8, 4, 0, 0, 0xFF);
+
+	// Each column's coverage adds up to one whole pixel, whichever two
+	// pixels it's split between.
+	for x in 0..9 {
+		let total: u32 = (0..6).map(|y| mock.raw_pixel(x, y)).sum();
+		assert!(
+			(0xFE..=0x100).contains(&total),
+			"column {x} sums to {total:#x}"
+		);
+	}
+	assert_eq!(mock.raw_pixel(0, 0), 0xFF);
+	assert_eq!(mock.raw_pixel(8, 4), 0xFF);
+	assert!(mock.raw_pixel(1, 0) > 0 && mock.raw_pixel(1, 1) > 0);
+}
+
+#[test]
+fn clips_anti_aliased_lines_with_ends_off_the_buffer() {
+	// As with `draw_line`, the small buffer should show the same pixels as
+	// the large one, which holds the whole line.
+	let lines = [
+		(3, 40, 40, 9),
+		(0, 27, 50, 2),
+		(13, 60, 2, 0),
+		(47, 5, 5, 6),
+	];
+
+	for (x0, y0, x1, y1) in lines {
+		let whole = MockVbuf::new(64, 64, MockFormat::Grey8);
+		whole.vbuf().draw_line_aa(x0, y0, x1, y1, 0xFF);
+
+		let clipped = MockVbuf::new(16, 12, MockFormat::Grey8);
+		clipped.vbuf().draw_line_aa(x0, y0, x1, y1, 0xFF);
+
+		for y in 0..12 {
+			for x in 0..16 {
+				assert_eq!(
+					clipped.raw_pixel(x, y),
+					whole.raw_pixel(x, y),
+					"({x}, {y}) of ({x0}, {y0}) to ({x1}, {y1})"
+				);
+			}
+		}
+	}
+
+	// These would overflow (or take forever to walk) if they weren't
+	// clipped first.
+	let mock = MockVbuf::new(6, 4, MockFormat::Grey8);
+	let vbuf = mock.vbuf();
+	vbuf.draw_line_aa(0, 0, u64::MAX, u64::MAX, 0xFF);
+	vbuf.draw_line_aa(u64::MAX, 3, 2, 3, 0xFF);
+	vbuf.draw_line_aa(u64::MAX, 0, 0, u64::MAX, 0xFF);
+	vbuf.draw_line_aa(0, u64::MAX, u64::MAX, u64::MAX - 1, 0xFF);
+
+	assert_eq!(
+		mock.render(),
+		picture(&[
+			"#.....", //
+			".#....", "..#...", "..####",
+		])
+	);
+}
+
+#[test]
+fn rounds_box_corners() {
+	let mock = MockVbuf::new(9, 7, MockFormat::Grey8);
+	mock.vbuf().draw_round_box(0, 0, 8, 6, 2, 0xFF);
+
+	assert_eq!(
+		mock.render(),
+		picture(&[
+			".#######.", //
+			"#.......#",
+			"#.......#",
+			"#.......#",
+			"#.......#",
+			"#.......#",
+			".#######.",
+		])
+	);
+}
+
+#[test]
+fn draws_square_corners_without_a_radius() {
+	let round = MockVbuf::new(6, 5, MockFormat::Grey8);
+	round.vbuf().draw_round_box(5, 4, 0, 0, 0, 0xFF);
+
+	let square = MockVbuf::new(6, 5, MockFormat::Grey8);
+	square.vbuf().draw_box(0, 0, 5, 4, 0xFF);
+
+	assert_eq!(round.render(), square.render());
+}
+
+#[test]
+fn draws_colored_boxes_as_outlines() {
+	let color = Color {
+		r: 0x12,
+		g: 0x34,
+		b: 0x56,
+	};
+	let colored = MockVbuf::new(6, 5, MockFormat::Rgb888);
+	colored.vbuf().draw_box_color(5, 4, 1, 1, color);
+
+	let grey = MockVbuf::new(6, 5, MockFormat::Rgb888);
+	grey.vbuf().draw_box(1, 1, 5, 4, 0xFF);
+
+	assert_eq!(colored.render(), grey.render());
+	assert_eq!(colored.raw_pixel(1, 1), 0x12_3456);
+	assert_eq!(colored.raw_pixel(5, 4), 0x12_3456);
+}
+
+#[test]
+fn draws_circles() {
+	let mock = MockVbuf::new(7, 7, MockFormat::Grey8);
+	mock.vbuf().draw_circle(3, 3, 3, 0xFF);
+
+	assert_eq!(
+		mock.render(),
+		picture(&[
+			"..###..", //
+			".#...#.", "#.....#", "#.....#", "#.....#", ".#...#.", "..###..",
+		])
+	);
+}
+
+#[test]
+fn fills_circles_out_to_their_outline() {
+	let outline = MockVbuf::new(9, 9, MockFormat::Grey8);
+	outline.vbuf().draw_circle(4, 4, 4, 0xFF);
+
+	let filled = MockVbuf::new(9, 9, MockFormat::Grey8);
+	filled.vbuf().fill_circle(4, 4, 4, 0xFF);
+
+	for y in 0..9 {
+		let lit = |mock: &MockVbuf| (0..9).filter(|&x| mock.is_lit(x, y)).collect::<Vec<_>>();
+		let (outline, filled) = (lit(&outline), lit(&filled));
+
+		// Each row is filled solid, between the outline's ends.
+		let (first, last) = (outline[0], outline[outline.len() - 1]);
+		assert_eq!(filled, (first..=last).collect::<Vec<_>>(), "row {y}");
+	}
+}
+
+#[test]
+fn draws_a_single_pixel_for_a_zero_radius() {
+	let mock = MockVbuf::new(3, 3, MockFormat::Grey8);
+	mock.vbuf().draw_circle(1, 1, 0, 0xFF);
+	mock.vbuf().fill_circle(1, 1, 0, 0xFF);
+
+	assert_eq!(mock.lit_count(), 1);
+	assert!(mock.is_lit(1, 1));
+}
+
+#[test]
+fn clips_circles_to_the_buffer() {
+	let mock = MockVbuf::new(4, 4, MockFormat::Grey8);
+	mock.vbuf().draw_circle(0, 0, 3, 0xFF);
+	mock.vbuf().fill_circle(3, 3, 1, 0xFF);
+
+	assert_eq!(
+		mock.render(),
+		picture(&[
+			"...#", //
+			"...#", "..##", "####",
+		])
+	);
+}
+
+#[test]
+fn blits_rgb_images_clipped_to_the_buffer() {
+	let mock = MockVbuf::new(3, 2, MockFormat::Rgb888);
+	let image = [
+		0x11, 0x22, 0x33, 0x44, 0x55, 0x66, //
+		0x77, 0x88, 0x99, 0xAA, 0xBB, 0xCC,
+	];
+
+	mock.vbuf().blit_rgb(2, 0, 2, 2, &image).unwrap();
+
+	assert_eq!(mock.raw_pixel(2, 0), 0x11_2233);
+	assert_eq!(mock.raw_pixel(2, 1), 0x77_8899);
+	assert_eq!(mock.lit_count(), 2);
+}
+
+#[test]
+fn rejects_blits_of_the_wrong_size() {
+	let mock = MockVbuf::new(4, 4, MockFormat::Grey8);
+	let err = mock.vbuf().blit_grey(0, 0, 2, 2, &[0xFF; 3]).unwrap_err();
+
+	assert_eq!((err.expected, err.actual), (4, 3));
+	assert_eq!(mock.lit_count(), 0);
+}
+
+#[test]
+fn rejects_pixels_smaller_than_a_byte() {
+	let mut memory = [0u8; 16];
+
+	for bits_per_pixel in [0, 1, 4, 7] {
+		let raw = RawFramebuffer {
+			width: 4,
+			height: 4,
+			pitch: 2,
+			bits_per_pixel,
+			red_size: 0,
+			green_size: 0,
+			blue_size: 0,
+			red_shift: 0,
+			green_shift: 0,
+			blue_shift: 0,
+			opaque: true,
+			base: memory.as_mut_ptr(),
+		};
+
+		// SAFETY: The memory is `height * pitch` bytes long, and outlives
+		// SAFETY: the buffer (were one to be made).
+		let result = unsafe { Vbuf::from_raw(&raw) };
+		assert!(
+			matches!(result, Err(VbufError::UnsupportedBpp(bpp)) if bpp == bits_per_pixel),
+			"{bits_per_pixel} bits per pixel"
+		);
+	}
+}
+
+#[test]
+fn accepts_whole_byte_pixels() {
+	for (format, bytes_per_pixel) in [
+		(MockFormat::Grey8, 1),
+		(MockFormat::Rgb565, 2),
+		(MockFormat::Rgb888, 3),
+		(MockFormat::Xrgb8888, 4),
+	] {
+		let mock = MockVbuf::new(2, 2, format);
+		assert!(mock.vbuf().check_supported().is_ok(), "{format:?}");
+		assert_eq!(mock.vbuf().bytes_per_pixel, bytes_per_pixel, "{format:?}");
+	}
+}
+
+#[test]
+fn surfaces_failed_field_reads_as_query_errors() {
+	let result = read_field_with(VbufField::Pitch, |key| {
+		assert_eq!(key, VbufField::Pitch.key());
+		Err((Error::BadKey, 42))
+	});
+
+	assert!(matches!(
+		result,
+		Err(VbufError::Query {
+			field: VbufField::Pitch,
+			err:   Error::BadKey,
+			ext:   42,
+		})
+	));
+}
+
+#[test]
+fn passes_successful_field_reads_through() {
+	assert!(matches!(
+		read_field_with(VbufField::Width, |_| Ok(640)),
+		Ok(640)
+	));
+}
+
+#[test]
+fn truncates_levels_without_dithering() {
+	for bits in 1..=8 {
+		for level in 0..=u8::MAX {
+			for (x, y) in [(0, 0), (1, 2), (3, 3)] {
+				assert_eq!(
+					quantize(level, bits, x, y, false),
+					u16::from(level) >> (8 - bits),
+					"level {level} at {bits} bits"
+				);
+			}
+		}
+	}
+}
+
+#[test]
+fn dithers_levels_within_one_step() {
+	for bits in 1..=8 {
+		let max = (1 << bits) - 1;
+
+		for level in 0..=u8::MAX {
+			let truncated = u16::from(level) >> (8 - bits);
+			let mut total = 0;
+
+			for y in 0..4 {
+				for x in 0..4 {
+					let dithered = quantize(level, bits, x, y, true);
+					assert!(
+						dithered == truncated || dithered == truncated + 1,
+						"level {level} at {bits} bits dithered to {dithered}"
+					);
+					assert!(dithered <= max, "level {level} at {bits} bits");
+					total += u32::from(dithered);
+				}
+			}
+
+			// Averaged over the matrix, the dithered level is within a
+			// step of the real one.
+			let average = total * 256 / 16;
+			let real = u32::from(level) << bits;
+			assert!(
+				average.abs_diff(real) <= 256,
+				"level {level} at {bits} bits"
+			);
+		}
+	}
+}
+
+#[test]
+fn leaves_full_depth_levels_alone_when_dithering() {
+	for level in 0..=u8::MAX {
+		for (x, y) in [(0, 0), (1, 2), (3, 3)] {
+			assert_eq!(quantize(level, 8, x, y, true), u16::from(level));
+		}
+	}
+}
+
+#[test]
+fn fills_rects_with_whole_pixels_at_any_alignment() {
+	let color = Color {
+		r: 0x12,
+		g: 0x34,
+		b: 0x56,
+	};
+
+	for format in [
+		MockFormat::Grey8,
+		MockFormat::Rgb555,
+		MockFormat::Rgb565,
+		MockFormat::Rgb888,
+		MockFormat::Xrgb8888,
+	] {
+		let expected = MockVbuf::new(1, 1, format);
+		expected.vbuf().set_pixel(0, 0, color);
+		let expected = expected.raw_pixel(0, 0);
+
+		// Each row starts at a different offset from a word boundary, and
+		// runs for a different length.
+		let mock = MockVbuf::new(40, 8, format);
+		for y in 0..8 {
+			mock.vbuf()
+				.fill_rect_color(&Rect::new(y, y, 33 - y * 3, 1), color);
+		}
+
+		for y in 0..8 {
+			for x in 0..40 {
+				let inside = x >= y && x < 33 - y * 2;
+				let pixel = mock.raw_pixel(x, y);
+				assert_eq!(
+					pixel == expected,
+					inside,
+					"({x}, {y}) of {format:?}: {pixel:#x}"
+				);
+			}
+		}
+	}
+}
+
+#[test]
+fn fills_opaque_black_with_its_unused_bits_set() {
+	let mock = MockVbuf::new(9, 2, MockFormat::Xrgb8888);
+	mock.vbuf().fill_rect(&Rect::new(0, 0, 9, 2), 0xFF);
+	mock.vbuf().fill_rect(&Rect::new(1, 0, 7, 2), 0);
+
+	for y in 0..2 {
+		assert_eq!(mock.raw_pixel(0, y), 0xFFFF_FFFF);
+		for x in 1..8 {
+			assert_eq!(mock.raw_pixel(x, y), 0xFF00_0000, "({x}, {y})");
+		}
+		assert_eq!(mock.raw_pixel(8, y), 0xFFFF_FFFF);
+	}
+}
+
+/// Returns a buffer drawing into the same memory as `mock`'s, as a
+/// source of video buffers would.
+fn share(mock: &MockVbuf) -> Vbuf {
+	// SAFETY: The memory outlives the buffer, in every test that uses it.
+	unsafe { mock.vbuf().with_memory(mock.vbuf().data) }
+}
+
+#[test]
+fn falls_back_to_the_firmware_framebuffer() {
+	let mock = MockVbuf::new(4, 4, MockFormat::Xrgb8888);
+
+	let (vbuf, source) =
+		acquire_from(0, |_| Err(VbufError::NoSource), |_| Ok(share(&mock))).unwrap();
+	assert_eq!(source, VbufSource::Firmware);
+
+	// The buffer is usable, drawing into the firmware's memory.
+	assert!(vbuf.check_supported().is_ok());
+	vbuf.fill_rect(&Rect::new(1, 1, 2, 2), 0xFF);
+	assert_eq!(
+		mock.render(),
+		picture(&[
+			"....", //
+			".##.", ".##.", "....",
+		])
+	);
+}
+
+#[test]
+fn prefers_the_boot_video_buffer() {
+	let mock = MockVbuf::new(4, 4, MockFormat::Xrgb8888);
+
+	let (_, source) = acquire_from(
+		0,
+		|_| Ok(share(&mock)),
+		|_| panic!("the firmware framebuffer shouldn't be tried"),
+	)
+	.unwrap();
+	assert_eq!(source, VbufSource::BootVbuf);
+}
+
+#[test]
+fn only_falls_back_when_there_is_no_boot_video_buffer() {
+	let result = acquire_from(
+		0,
+		|_| Err(VbufError::UnsupportedBpp(4)),
+		|_| panic!("the firmware framebuffer shouldn't be tried"),
+	);
+	assert!(matches!(result, Err(VbufError::UnsupportedBpp(4))));
+
+	let result = acquire_from(
+		0,
+		|_| Err(VbufError::NoSource),
+		|_| Err(VbufError::NoSource),
+	);
+	assert!(matches!(result, Err(VbufError::NoSource)));
+}
+
+#[test]
+fn unmaps_mapped_buffers_once_dropped() {
+	static RELEASED: AtomicU64 = AtomicU64::new(0);
+	#[expect(clippy::unnecessary_wraps)]
+	fn release(_iface: u64, idx: u64) -> Result<(), (Error, u64)> {
+		RELEASED.fetch_add(idx, Ordering::Relaxed);
+		Ok(())
+	}
+
+	let mock = MockVbuf::new(4, 4, MockFormat::Grey8);
+	let mapped = share(&mock).mapped_as(3, release);
+
+	// Buffers merely sharing its memory (e.g. back buffers) don't own the mapping.
+	// SAFETY: The mock outlives the copy.
+	drop(unsafe { mapped.with_memory(mock.vbuf().data) });
+	assert_eq!(RELEASED.load(Ordering::Relaxed), 0);
+
+	drop(mapped);
+	assert_eq!(RELEASED.load(Ordering::Relaxed), 3);
+}
+
+#[test]
+fn survives_failing_to_unmap() {
+	fn release(_iface: u64, _idx: u64) -> Result<(), (Error, u64)> {
+		Err((Error::BadKey, 0))
+	}
+
+	let mock = MockVbuf::new(4, 4, MockFormat::Grey8);
+	drop(share(&mock).mapped_as(0, release));
+}
+
+#[test]
+fn blends_set_bits_of_bitmaps_and_leaves_unset_ones() {
+	let backdrop = Color {
+		r: 0x40,
+		g: 0x80,
+		b: 0xC0,
+	};
+	let fg = Color {
+		r: 0xFF,
+		g: 0,
+		b: 0,
+	};
+
+	let mock = MockVbuf::new(4, 2, MockFormat::Xrgb8888);
+	let vbuf = mock.vbuf();
+	vbuf.fill_rect_color(&Rect::of(vbuf), backdrop);
+	vbuf.draw_bitmap(0, 0, 4, 2, &[0b1010_0000, 0b0101_0000], fg, 0x80);
+
+	for y in 0..2 {
+		for x in 0..4 {
+			let expected = if (x + y) % 2 == 0 {
+				fg.blended_over(backdrop, 0x80)
+			} else {
+				backdrop
+			};
+			assert_eq!(vbuf.get_pixel(x, y), Some(expected), "({x}, {y})");
+		}
+	}
+}
@@ -0,0 +1,94 @@
+//! Implements the (optional) large, faint logo watermark drawn
+//! behind the log text.
+
+use oro_logo_rle::{Command, OroLogoData};
+
+use crate::{LIGHTNESSES, OroLogo, Rect, Vbuf, vbuf::blend};
+
+/// The opacity of the watermark, from `0` (invisible) to `255` (opaque).
+const WATERMARK_ALPHA: u8 = 0x18;
+
+/// A statically scaled, faint copy of the first frame of the logo.
+pub struct Watermark {
+	/// Where the watermark is drawn, in buffer coordinates.
+	rect:  Rect,
+	/// The integer scale factor the logo is drawn at.
+	scale: u64,
+	/// The logo's lightness values, packed 2 bits per pixel.
+	data:  [u8; (OroLogo::WIDTH * OroLogo::HEIGHT) / 4],
+}
+
+impl Watermark {
+	/// Creates a watermark centered within (and as large as will
+	/// comfortably fit into) `area`.
+	///
+	/// Returns `None` if the area is too small to hold the logo.
+	pub fn new(area: &Rect) -> Option<Self> {
+		let logo_w = OroLogo::WIDTH as u64;
+		let logo_h = OroLogo::HEIGHT as u64;
+
+		// Leave a quarter of the area free so the watermark
+		// doesn't run right up to the edges.
+		let scale = (area.w.min(area.h) * 3 / 4) / logo_w.max(logo_h);
+		if scale == 0 {
+			return None;
+		}
+
+		let w = logo_w * scale;
+		let h = logo_h * scale;
+
+		let mut data = [0; (OroLogo::WIDTH * OroLogo::HEIGHT) / 4];
+		let mut off = 0usize;
+
+		for command in OroLogo::new() {
+			match command {
+				Command::End => break,
+				Command::Draw(count, lightness) => {
+					for i in 0..count as usize {
+						let off = off + i;
+						let bit_off = (off % 4) * 2;
+						data[off / 4] =
+							data[off / 4] & !(0b11 << bit_off) | ((lightness & 0b11) << bit_off);
+					}
+					off += count as usize;
+				}
+				Command::Skip(count) => off += count as usize,
+			}
+		}
+
+		Some(Self {
+			rect: Rect::new(area.x + (area.w - w) / 2, area.y + (area.h - h) / 2, w, h),
+			scale,
+			data,
+		})
+	}
+
+	/// Returns the watermark's grey level at the given buffer coordinates,
+	/// or black if the point lies outside of it.
+	pub fn level_at(&self, x: u64, y: u64) -> u8 {
+		if !self.rect.contains(x, y) {
+			return 0;
+		}
+
+		let lx = ((x - self.rect.x) / self.scale) as usize;
+		let ly = ((y - self.rect.y) / self.scale) as usize;
+		let off = ly * OroLogo::WIDTH + lx;
+		let lightness = (self.data[off / 4] >> ((off % 4) * 2)) & 0b11;
+
+		blend(LIGHTNESSES[lightness as usize], 0, WATERMARK_ALPHA)
+	}
+
+	/// Draws the entire watermark.
+	pub fn draw(&self, vbuf: &Vbuf) {
+		let rect = self.rect.intersect(&Rect::of(vbuf));
+
+		for y in rect.y..rect.bottom() {
+			for x in rect.x..rect.right() {
+				// SAFETY: We clipped to the buffer bounds above.
+				unsafe {
+					vbuf.set_grey_pixel_unchecked(x, y, self.level_at(x, y));
+				}
+			}
+		}
+	}
+}
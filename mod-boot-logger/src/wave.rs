@@ -0,0 +1,65 @@
+//! Provides precomputed, integer-only wave tables for animations.
+//!
+//! The boot environment may not have (or may not want to pay for)
+//! floating point, so every animation samples one of these tables
+//! instead of computing its curve directly.
+
+/// The number of samples in each wave table (i.e. the number of
+/// distinct phases in a period).
+const SAMPLES: usize = 256;
+
+/// A triangle wave; rises linearly from `0` to `255` halfway through
+/// the period, then falls linearly back.
+pub static TRIANGLE: [u8; SAMPLES] = {
+	let mut table = [0; SAMPLES];
+	let mut i = 0;
+	while i < SAMPLES {
+		let ramp = if i <= SAMPLES / 2 { i } else { SAMPLES - i };
+		table[i] = if ramp * 2 > 255 {
+			255
+		} else {
+			(ramp * 2) as u8
+		};
+		i += 1;
+	}
+	table
+};
+
+/// A raised cosine wave; eases from `0` to `255` halfway through the
+/// period, then eases back.
+///
+/// Computed as `sin²(πi/256)` using Bhāskara I's rational approximation
+/// of the sine, which stays within a few levels of the real thing.
+pub static SINE: [u8; SAMPLES] = {
+	// One degree, in 64ths of a degree.
+	const DEG: u64 = 64;
+
+	let mut table = [0; SAMPLES];
+	let mut i = 0;
+	while i < SAMPLES {
+		// The angle, from 0 up to (but not including) 180 degrees.
+		let angle = i as u64 * 180 * DEG / SAMPLES as u64;
+		let q = angle * (180 * DEG - angle);
+		// sin(x) ≈ 4x(180 - x) / (40500 - x(180 - x)), for x in degrees.
+		let sine = 255 * 4 * q / (40500 * DEG * DEG - q);
+		table[i] = (sine * sine / 255) as u8;
+		i += 1;
+	}
+	table
+};
+
+/// Returns the phase (an index into a wave table) of time `t`
+/// within a wave with the given period.
+pub fn phase(t: u64, period: u64) -> u8 {
+	let period = period.max(1);
+	((t % period) * SAMPLES as u64 / period) as u8
+}
+
+/// Samples a wave table with the given period at time `t`,
+/// scaling it to `0..=amplitude`.
+pub fn sample(table: &[u8; SAMPLES], t: u64, period: u64, amplitude: u64) -> u64 {
+	u64::from(table[phase(t, period) as usize]) * amplitude / 255
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,47 @@
+//! Tests for the wave tables.
+
+use super::{SAMPLES, SINE, TRIANGLE, phase, sample};
+
+#[test]
+fn waves_start_and_end_at_zero() {
+	for table in [&TRIANGLE, &SINE] {
+		assert_eq!(table[0], 0);
+		assert!(table[SAMPLES - 1] <= 2, "ends at {}", table[SAMPLES - 1]);
+	}
+}
+
+#[test]
+fn waves_peak_at_full_brightness_halfway() {
+	for table in [&TRIANGLE, &SINE] {
+		assert_eq!(table[SAMPLES / 2], u8::MAX);
+		assert_eq!(table.iter().max(), Some(&u8::MAX));
+	}
+}
+
+#[test]
+fn waves_are_symmetric() {
+	for table in [&TRIANGLE, &SINE] {
+		for i in 1..SAMPLES {
+			assert_eq!(table[i], table[SAMPLES - i], "sample {i}");
+		}
+	}
+}
+
+#[test]
+fn waves_rise_then_fall() {
+	for table in [&TRIANGLE, &SINE] {
+		assert!(table[..=SAMPLES / 2].is_sorted());
+		assert!(table[SAMPLES / 2..].iter().rev().is_sorted());
+	}
+}
+
+#[test]
+fn samples_wrap_around_each_period() {
+	assert_eq!(phase(0, 1000), 0);
+	assert_eq!(phase(500, 1000), 128);
+	assert_eq!(phase(1500, 1000), 128);
+	assert_eq!(phase(5, 0), 0);
+
+	assert_eq!(sample(&TRIANGLE, 500, 1000, 100), 100);
+	assert_eq!(sample(&TRIANGLE, 2000, 1000, 100), 0);
+}
@@ -0,0 +1,292 @@
+//! Higher-level UI widgets composed from the drawing primitives.
+
+use crate::{
+	Rect, Vbuf,
+	font_rasterizer::{self, LINE_HEIGHT},
+	vbuf::Color,
+};
+
+/// The padding between a widget's frame and its contents, in pixels.
+pub const PADDING: u64 = 4;
+
+/// Draws a framed table of key/value pairs within `rect`.
+///
+/// Keys are left-aligned and values are right-aligned, one pair per line.
+/// Rows that don't fit within the frame are not drawn, and keys or values
+/// that are too long to fit are clipped to the frame.
+pub fn draw_kv_table(vbuf: &Vbuf, rect: &Rect, rows: &[(&str, &str)], level: u8) {
+	if rect.w < 2 || rect.h < 2 {
+		return;
+	}
+
+	vbuf.fill_rect(rect, 0);
+	vbuf.draw_rect(rect, level);
+
+	let inner = rect.inset(PADDING);
+
+	let line_height = LINE_HEIGHT as u64;
+	let visible_rows = (inner.h / line_height) as usize;
+
+	for (i, (key, value)) in rows.iter().take(visible_rows).enumerate() {
+		let y = inner.y + i as u64 * line_height;
+
+		font_rasterizer::draw_text(vbuf, inner.x, y, key, level, &inner);
+
+		let value_width = font_rasterizer::measure_text(value) as u64;
+		let value_x = inner.x + inner.w.saturating_sub(value_width);
+		font_rasterizer::draw_text(vbuf, value_x, y, value, level, &inner);
+	}
+}
+
+/// The width and height of a boot step's status icon, in pixels.
+const ICON_SIZE: u64 = 7;
+
+/// The status of a single boot step.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(not(all(test, feature = "mock-vbuf")), expect(dead_code))]
+pub enum StepStatus {
+	/// The step hasn't started yet.
+	Pending,
+	/// The step is underway.
+	InProgress,
+	/// The step completed successfully.
+	Done,
+	/// The step failed.
+	Failed,
+}
+
+impl StepStatus {
+	/// Returns the status's icon, as a 1-bit bitmap (see [`Vbuf::draw_bitmap`]),
+	/// and the level to draw it with.
+	fn icon(self) -> (&'static [u8; ICON_SIZE as usize], u8) {
+		match self {
+			Self::Pending => {
+				(
+					&[
+						0b0011_1000,
+						0b0100_0100,
+						0b1000_0010,
+						0b1000_0010,
+						0b1000_0010,
+						0b0100_0100,
+						0b0011_1000,
+					],
+					0x77,
+				)
+			}
+			Self::InProgress => {
+				(
+					&[
+						0b0011_1000,
+						0b0100_0100,
+						0b1011_1010,
+						0b1011_1010,
+						0b1011_1010,
+						0b0100_0100,
+						0b0011_1000,
+					],
+					0xFF,
+				)
+			}
+			Self::Done => {
+				(
+					&[
+						0b0000_0010,
+						0b0000_0110,
+						0b0000_1100,
+						0b1001_1000,
+						0b1111_0000,
+						0b0110_0000,
+						0b0010_0000,
+					],
+					0xFF,
+				)
+			}
+			Self::Failed => {
+				(
+					&[
+						0b1000_0010,
+						0b0100_0100,
+						0b0010_1000,
+						0b0001_0000,
+						0b0010_1000,
+						0b0100_0100,
+						0b1000_0010,
+					],
+					0xFF,
+				)
+			}
+		}
+	}
+}
+
+/// An ordered list of named boot steps, each with a status icon.
+///
+/// The steps are given up front; their statuses are then updated as
+/// progress is made, and only the rows whose status changed are redrawn.
+pub struct StepList<'a, const N: usize> {
+	/// The region the list is drawn within.
+	rect:   Rect,
+	/// The name of each step.
+	names:  [&'a str; N],
+	/// The current status of each step.
+	status: [StepStatus; N],
+	/// The status each step was last drawn with, if it's been drawn at all.
+	drawn:  [Option<StepStatus>; N],
+}
+
+#[cfg_attr(not(all(test, feature = "mock-vbuf")), expect(dead_code))]
+impl<'a, const N: usize> StepList<'a, N> {
+	/// Creates a new step list drawn within `rect`, with every step pending.
+	pub fn new(rect: Rect, names: [&'a str; N]) -> Self {
+		Self {
+			rect,
+			names,
+			status: [StepStatus::Pending; N],
+			drawn: [None; N],
+		}
+	}
+
+	/// Sets the status of the step at `index`.
+	///
+	/// Does nothing if there is no such step.
+	pub fn set_status(&mut self, index: usize, status: StepStatus) {
+		if let Some(current) = self.status.get_mut(index) {
+			*current = status;
+		}
+	}
+
+	/// Draws any steps that have changed since they were last drawn.
+	///
+	/// The first time a step is drawn, its whole row is drawn; after that,
+	/// only its icon is. Steps that don't fit within the region are not drawn.
+	pub fn draw(&mut self, vbuf: &Vbuf) {
+		let line_height = LINE_HEIGHT as u64;
+		let visible_rows = (self.rect.h / line_height) as usize;
+		let icon_y_offset = line_height.saturating_sub(ICON_SIZE) / 2;
+		let text_x = self.rect.x + ICON_SIZE + PADDING;
+
+		for i in 0..N.min(visible_rows) {
+			let status = self.status[i];
+			if self.drawn[i] == Some(status) {
+				continue;
+			}
+
+			let y = self.rect.y + i as u64 * line_height;
+
+			if self.drawn[i].is_none() {
+				let row = Rect::new(self.rect.x, y, self.rect.w, line_height);
+				vbuf.fill_rect(&row, 0);
+				font_rasterizer::draw_text(vbuf, text_x, y, self.names[i], 0xFF, &row);
+			}
+
+			if self.rect.w >= ICON_SIZE {
+				let (icon, level) = status.icon();
+				let icon_y = y + icon_y_offset;
+				vbuf.fill_rect(&Rect::new(self.rect.x, icon_y, ICON_SIZE, ICON_SIZE), 0);
+				vbuf.draw_bitmap(
+					self.rect.x,
+					icon_y,
+					ICON_SIZE,
+					ICON_SIZE,
+					icon,
+					Color::grey(level),
+					u8::MAX,
+				);
+			}
+
+			self.drawn[i] = Some(status);
+		}
+	}
+}
+
+/// A framed horizontal bar, filled from the left in proportion to how far
+/// along something is.
+///
+/// As with [`StepList`], drawing only draws what's changed: while the bar
+/// only grows, just the newly filled part of it is drawn.
+pub struct ProgressBar {
+	/// The region the bar (including its frame) is drawn within.
+	rect:     Rect,
+	/// How far along the bar is, from `0.0` to `1.0`.
+	fraction: f32,
+	/// The width of the filled part of the bar, in pixels, as it was last
+	/// drawn, if it's been drawn at all.
+	drawn:    Option<u64>,
+}
+
+#[cfg_attr(not(all(test, feature = "mock-vbuf")), expect(dead_code))]
+impl ProgressBar {
+	/// Creates a new, empty progress bar drawn within `rect`.
+	pub fn new(rect: Rect) -> Self {
+		Self {
+			rect,
+			fraction: 0.0,
+			drawn: None,
+		}
+	}
+
+	/// Sets how far along the bar is, from `0.0` (empty) to `1.0` (full).
+	///
+	/// Fractions outside that range are clamped to it; NaN is taken as `0.0`.
+	pub fn set_fraction(&mut self, fraction: f32) {
+		self.fraction = if fraction.is_nan() {
+			0.0
+		} else {
+			fraction.clamp(0.0, 1.0)
+		};
+	}
+
+	/// Returns the region within the frame that the bar fills,
+	/// leaving a pixel's gap between the two.
+	fn inner(&self) -> Rect {
+		self.rect.inset(2)
+	}
+
+	/// Returns the width of the filled part of the bar, in pixels,
+	/// given the width it fills when full.
+	#[expect(
+		clippy::cast_precision_loss,
+		clippy::cast_possible_truncation,
+		clippy::cast_sign_loss
+	)]
+	fn filled_width(&self, full: u64) -> u64 {
+		// The fraction is clamped, so this never exceeds `full`.
+		((full as f32 * self.fraction) as u64).min(full)
+	}
+
+	/// Draws the bar, if it's changed since it was last drawn.
+	///
+	/// The first time the bar is drawn (or if it's shrunk since), it's
+	/// drawn in full; otherwise, only the newly filled part is.
+	pub fn draw(&mut self, vbuf: &Vbuf) {
+		let inner = self.inner();
+		if inner.is_empty() {
+			return;
+		}
+
+		let filled = self.filled_width(inner.w);
+
+		let from = match self.drawn {
+			Some(drawn) if drawn == filled => return,
+			Some(drawn) if drawn < filled => drawn,
+			_ => {
+				vbuf.fill_rect(&self.rect, 0);
+				vbuf.draw_rect(&self.rect, 0xFF);
+				0
+			}
+		};
+
+		if filled > from {
+			vbuf.fill_rect(
+				&Rect::new(inner.x + from, inner.y, filled - from, inner.h),
+				0xFF,
+			);
+		}
+
+		self.drawn = Some(filled);
+	}
+}
+
+#[cfg(all(test, feature = "mock-vbuf"))]
+mod tests;
@@ -0,0 +1,190 @@
+//! Tests for the widgets, against a [`MockVbuf`].
+
+use super::{ICON_SIZE, PADDING, ProgressBar, StepList, StepStatus, draw_kv_table};
+use crate::{
+	Rect,
+	font_rasterizer::{LINE_HEIGHT, cell_width},
+	mock_vbuf::{MockFormat, MockVbuf},
+};
+
+/// Returns whether any pixel within `rect` is lit.
+fn any_lit(mock: &MockVbuf, rect: &Rect) -> bool {
+	(rect.y..rect.bottom()).any(|y| (rect.x..rect.x + rect.w).any(|x| mock.is_lit(x, y)))
+}
+
+/// Returns the rectangle of row `row` of a step list at the origin.
+fn step_row(row: u64, width: u64) -> Rect {
+	let line = LINE_HEIGHT as u64;
+	Rect::new(0, row * line, width, line)
+}
+
+#[test]
+fn draws_the_steps_that_fit() {
+	let line = LINE_HEIGHT as u64;
+	let mock = MockVbuf::new(120, line * 3, MockFormat::Grey8);
+	let mut steps = StepList::new(Rect::new(0, 0, 120, line * 2), ["mm", "heap", "smp"]);
+
+	steps.draw(mock.vbuf());
+
+	assert!(any_lit(&mock, &step_row(0, 120)));
+	assert!(any_lit(&mock, &step_row(1, 120)));
+	assert!(!any_lit(&mock, &step_row(2, 120)));
+}
+
+#[test]
+fn redraws_only_the_icons_of_steps_that_changed() {
+	let line = LINE_HEIGHT as u64;
+	let mock = MockVbuf::new(120, line * 2, MockFormat::Grey8);
+	let vbuf = mock.vbuf();
+	let mut steps = StepList::new(Rect::of(vbuf), ["mm", "heap"]);
+	steps.draw(vbuf);
+
+	// Nothing's redrawn until a step changes.
+	vbuf.fill_rect(&Rect::of(vbuf), 0);
+	steps.draw(vbuf);
+	assert_eq!(mock.lit_count(), 0);
+
+	steps.set_status(1, StepStatus::Done);
+	steps.set_status(7, StepStatus::Failed);
+	steps.draw(vbuf);
+
+	let (icon, _) = StepStatus::Done.icon();
+	let icon_rect = Rect::new(0, line + (line - ICON_SIZE) / 2, ICON_SIZE, ICON_SIZE);
+	let lit: usize = icon.iter().map(|row| row.count_ones() as usize).sum();
+	assert_eq!(mock.lit_count(), lit);
+	assert!(any_lit(&mock, &icon_rect));
+}
+
+#[test]
+fn gives_each_status_its_own_icon() {
+	let statuses = [
+		StepStatus::Pending,
+		StepStatus::InProgress,
+		StepStatus::Done,
+		StepStatus::Failed,
+	];
+
+	for (i, a) in statuses.iter().enumerate() {
+		for b in &statuses[i + 1..] {
+			assert_ne!(a.icon().0, b.icon().0, "{a:?} and {b:?}");
+		}
+	}
+}
+
+/// Returns the columns of row `y` that are lit.
+fn lit_columns(mock: &MockVbuf, y: u64) -> Vec<u64> {
+	(0..mock.vbuf().width)
+		.filter(|&x| mock.is_lit(x, y))
+		.collect()
+}
+
+#[test]
+fn fills_the_bar_in_proportion() {
+	let mock = MockVbuf::new(24, 6, MockFormat::Grey8);
+	let mut bar = ProgressBar::new(Rect::of(mock.vbuf()));
+
+	bar.set_fraction(0.5);
+	bar.draw(mock.vbuf());
+
+	// The frame, then a pixel's gap, then half of the 20 pixels within.
+	assert_eq!(lit_columns(&mock, 0), (0..24).collect::<Vec<_>>());
+	let mut filled = vec![0];
+	filled.extend(2..12);
+	filled.push(23);
+	assert_eq!(lit_columns(&mock, 2), filled);
+}
+
+#[test]
+fn only_draws_what_the_bar_has_grown_by() {
+	let mock = MockVbuf::new(24, 6, MockFormat::Grey8);
+	let vbuf = mock.vbuf();
+	let mut bar = ProgressBar::new(Rect::of(vbuf));
+	bar.set_fraction(0.5);
+	bar.draw(vbuf);
+
+	vbuf.fill_rect(&Rect::of(vbuf), 0);
+	bar.set_fraction(0.75);
+	bar.draw(vbuf);
+	assert_eq!(lit_columns(&mock, 2), (12..17).collect::<Vec<_>>());
+	assert_eq!(mock.lit_count(), 5 * 2);
+
+	// Shrinking it draws it afresh, frame and all.
+	bar.set_fraction(0.25);
+	bar.draw(vbuf);
+	assert!(mock.is_lit(0, 0));
+	assert_eq!(lit_columns(&mock, 2).len(), 2 + 5);
+}
+
+#[test]
+fn clamps_fractions_out_of_range() {
+	let mock = MockVbuf::new(24, 6, MockFormat::Grey8);
+	let mut bar = ProgressBar::new(Rect::of(mock.vbuf()));
+
+	bar.set_fraction(2.0);
+	bar.draw(mock.vbuf());
+	assert_eq!(lit_columns(&mock, 2).len(), 2 + 20);
+
+	bar.set_fraction(f32::NAN);
+	bar.draw(mock.vbuf());
+	assert_eq!(lit_columns(&mock, 2), [0, 23]);
+}
+
+/// Returns the leftmost and rightmost lit columns within `rect`, if any.
+fn lit_span(mock: &MockVbuf, rect: &Rect) -> Option<(u64, u64)> {
+	let lit = |x: &u64| (rect.y..rect.bottom()).any(|y| mock.is_lit(*x, y));
+	let columns = rect.x..rect.x + rect.w;
+	Some((columns.clone().find(lit)?, columns.rev().find(lit)?))
+}
+
+#[test]
+fn aligns_keys_left_and_values_right() {
+	let line = LINE_HEIGHT as u64;
+	let mock = MockVbuf::new(120, line + PADDING * 2, MockFormat::Grey8);
+	let vbuf = mock.vbuf();
+	draw_kv_table(vbuf, &Rect::of(vbuf), &[("ab", "cd")], 0xFF);
+
+	let cell = cell_width() as u64;
+	let inner = Rect::of(vbuf).inset(PADDING);
+	let (left, _) = lit_span(&mock, &Rect::new(inner.x, inner.y, inner.w / 2, line)).unwrap();
+	let (_, right) = lit_span(
+		&mock,
+		&Rect::new(inner.x + inner.w / 2, inner.y, inner.w / 2, line),
+	)
+	.unwrap();
+	assert!(left < inner.x + cell, "the key starts at {left}");
+	assert!(
+		right + cell >= inner.x + inner.w,
+		"the value ends at {right}"
+	);
+}
+
+#[test]
+fn clips_rows_and_columns_to_the_frame() {
+	let line = LINE_HEIGHT as u64;
+	// Room for two rows and a half.
+	let mock = MockVbuf::new(120, line * 5 / 2 + PADDING * 2, MockFormat::Grey8);
+	let vbuf = mock.vbuf();
+	let rows = [
+		("ab", "cd"),
+		("a-key-long-enough-to-run-past-the-frame", ""),
+		("three", "3"),
+	];
+	draw_kv_table(vbuf, &Rect::of(vbuf), &rows, 0xFF);
+
+	let inner = Rect::of(vbuf).inset(PADDING);
+	assert!(any_lit(
+		&mock,
+		&Rect::new(inner.x, inner.y + line, inner.w, line)
+	));
+	// Nothing's drawn between the text and the frame, to the right of the
+	// over-long key or below the rows that fit.
+	let right = Rect::new(inner.x + inner.w, 1, PADDING - 1, vbuf.height - 2);
+	assert!(!any_lit(&mock, &right));
+	let below = Rect::new(
+		1,
+		inner.y + line * 2,
+		vbuf.width - 2,
+		vbuf.height - line * 2 - PADDING - 1,
+	);
+	assert!(!any_lit(&mock, &below));
+}